@@ -0,0 +1,17 @@
+#version 450
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 top;
+	vec4 bottom;
+} pushConstants;
+
+// The top of the screen has uv y of 0.
+void main()
+{
+	outFragColor = mix(pushConstants.top, pushConstants.bottom, inUv.y);
+}
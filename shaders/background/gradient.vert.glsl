@@ -0,0 +1,10 @@
+#version 450
+
+layout (location = 0) out vec2 outUv;
+
+// Fullscreen triangle on the far plane, so it's only drawn where nothing else was.
+void main()
+{
+	outUv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+	gl_Position = vec4(outUv * 2.0f - 1.0f, 1.0f, 1.0f);
+}
@@ -0,0 +1,35 @@
+#version 450
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 color;
+	vec4 boundsMin;
+	vec4 boundsMax;
+	uint instanceIndex;
+} pushConstants;
+
+// Corner bits are x, y, z, every pair of corners is one of the 12 box edges.
+const uint EDGE_CORNERS[24] = uint[](
+	0u, 1u, 2u, 3u, 4u, 5u, 6u, 7u,
+	0u, 2u, 1u, 3u, 4u, 6u, 5u, 7u,
+	0u, 4u, 1u, 5u, 2u, 6u, 3u, 7u
+);
+
+void main()
+{
+	uint corner = EDGE_CORNERS[gl_VertexIndex];
+	vec3 position = mix(pushConstants.boundsMin.xyz, pushConstants.boundsMax.xyz, vec3(corner & 1u, (corner >> 1u) & 1u, (corner >> 2u) & 1u));
+
+	gl_Position = frame.viewProjection * vec4(position, 1.0f);
+}
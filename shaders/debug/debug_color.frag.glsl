@@ -0,0 +1,16 @@
+#version 450
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 color;
+	vec4 boundsMin;
+	vec4 boundsMax;
+	uint instanceIndex;
+} pushConstants;
+
+void main()
+{
+	outFragColor = pushConstants.color;
+}
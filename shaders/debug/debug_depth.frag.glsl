@@ -0,0 +1,30 @@
+#version 450
+
+// Distance at which the shade halves.
+#define DEPTH_HALF_DISTANCE 10.0f
+
+layout (location = 0) flat in uint inInstanceIndex;
+
+layout (location = 0) out vec4 outFragColor;
+layout (location = 1) out uint outInstanceId;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+void main()
+{
+	vec4 viewPosition = frame.inverseProjection * vec4(0.0f, 0.0f, gl_FragCoord.z, 1.0f);
+	float linearDepth = -viewPosition.z / viewPosition.w;
+	float shade = DEPTH_HALF_DISTANCE / (DEPTH_HALF_DISTANCE + linearDepth);
+
+	outFragColor = vec4(vec3(shade), 1.0f);
+	outInstanceId = inInstanceIndex + 1u;
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout (location = 0) in vec3 vPosition;
+layout (location = 1) in vec4 vColor;
+
+layout (location = 0) out vec4 outColor;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+void main()
+{
+	gl_Position = frame.viewProjection * vec4(vPosition, 1.0f);
+
+	outColor = vColor;
+}
@@ -0,0 +1,42 @@
+#version 450
+
+layout (location = 0) in vec3 vPosition;
+
+layout (location = 0) flat out uint outInstanceIndex;
+
+struct Instance
+{
+	mat4 model;
+	vec4 boundingSphere;
+	uvec4 draw;
+	vec4 dequantization;
+	uvec4 vertexFormat;
+	vec4 baseColor;
+	uvec4 material;
+};
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (std430, set = 0, binding = 4) readonly buffer Instances
+{
+	Instance instances[];
+};
+
+// Positions only, for debug views that don't shade.
+void main()
+{
+	Instance instance = instances[gl_InstanceIndex];
+	vec3 position = instance.dequantization.xyz + vPosition * instance.dequantization.w;
+
+	gl_Position = frame.viewProjection * instance.model * vec4(position, 1.0f);
+	outInstanceIndex = gl_InstanceIndex;
+}
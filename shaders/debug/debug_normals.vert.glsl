@@ -0,0 +1,63 @@
+#version 450
+
+#define NORMAL_OCTAHEDRAL 1
+// Relative to the bounding sphere radius.
+#define NORMAL_LENGTH 0.02f
+
+// Advanced once per instance, every instance is a vertex of the mesh drawn as one line.
+layout (location = 0) in vec3 vPosition;
+layout (location = 1) in vec3 vNormal;
+
+struct Instance
+{
+	mat4 model;
+	vec4 boundingSphere;
+	uvec4 draw;
+	vec4 dequantization;
+	uvec4 vertexFormat;
+	vec4 baseColor;
+	uvec4 material;
+};
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (std430, set = 0, binding = 4) readonly buffer Instances
+{
+	Instance instances[];
+};
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 color;
+	vec4 boundsMin;
+	vec4 boundsMax;
+	uint instanceIndex;
+} pushConstants;
+
+vec3 decodeOctahedral(vec2 encoded)
+{
+	vec3 normal = vec3(encoded, 1.0f - abs(encoded.x) - abs(encoded.y));
+	float fold = max(-normal.z, 0.0f);
+	normal.xy += vec2(normal.x >= 0.0f ? -fold : fold, normal.y >= 0.0f ? -fold : fold);
+
+	return normalize(normal);
+}
+
+void main()
+{
+	Instance instance = instances[pushConstants.instanceIndex];
+	vec3 position = instance.dequantization.xyz + vPosition * instance.dequantization.w;
+	vec3 normal = instance.vertexFormat.y == NORMAL_OCTAHEDRAL ? decodeOctahedral(vNormal.xy) : vNormal;
+
+	position += normal * instance.boundingSphere.w * NORMAL_LENGTH * float(gl_VertexIndex);
+	gl_Position = frame.viewProjection * instance.model * vec4(position, 1.0f);
+}
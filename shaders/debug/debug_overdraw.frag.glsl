@@ -0,0 +1,15 @@
+#version 450
+
+// Added up by blending, ten layers reach full red.
+#define OVERDRAW_STEP vec4(0.1f, 0.03f, 0.01f, 1.0f)
+
+layout (location = 0) flat in uint inInstanceIndex;
+
+layout (location = 0) out vec4 outFragColor;
+layout (location = 1) out uint outInstanceId;
+
+void main()
+{
+	outFragColor = OVERDRAW_STEP;
+	outInstanceId = inInstanceIndex + 1u;
+}
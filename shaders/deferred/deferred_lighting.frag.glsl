@@ -0,0 +1,231 @@
+#version 450
+
+#define TILE_SIZE 16
+#define MAX_LIGHTS_PER_TILE 255
+#define TILE_STRIDE (MAX_LIGHTS_PER_TILE + 1)
+#define LIGHT_TYPE_DIRECTIONAL 0
+#define LIGHT_TYPE_POINT 1
+#define LIGHT_TYPE_SPOT 2
+#define AMBIENT_INTENSITY 0.03f
+#define MAX_SHININESS 256.0f
+#define DIELECTRIC_REFLECTANCE 0.04f
+#define CASCADES_COUNT 4
+#define SHADOW_NORMAL_OFFSET 0.02f
+
+layout (location = 0) out vec4 outFragColor;
+
+struct Light
+{
+	vec4 positionRange;
+	vec4 directionType;
+	vec4 colorIntensity;
+	vec4 coneCosines;
+};
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (std430, set = 0, binding = 1) readonly buffer Lights
+{
+	Light lights[];
+};
+
+layout (std430, set = 0, binding = 2) readonly buffer TileLights
+{
+	uint tileLights[];
+};
+
+layout (std430, set = 0, binding = 3) readonly buffer ShadowData
+{
+	mat4 lightViewProjections[CASCADES_COUNT];
+	vec4 splitDepths;
+	uvec4 info;
+} shadow;
+
+layout (set = 0, binding = 9) uniform sampler2DArrayShadow shadowMap;
+
+layout (set = 0, binding = 10) uniform sampler2D gbufferAlbedo;
+layout (set = 0, binding = 11) uniform sampler2D gbufferNormal;
+layout (set = 0, binding = 12) uniform sampler2D gbufferMaterial;
+layout (set = 0, binding = 13) uniform sampler2D sceneDepth;
+// Screen sized, or a single texel of 1 while SSAO is off.
+layout (set = 0, binding = 14) uniform sampler2D ambientOcclusion;
+// Only sampled once an environment is loaded, see frame.lightInfo.
+layout (set = 0, binding = 16) uniform samplerCube irradianceMap;
+layout (set = 0, binding = 17) uniform samplerCube prefilteredMap;
+layout (set = 0, binding = 18) uniform sampler2D brdfLut;
+
+const vec3 cascadeColors[CASCADES_COUNT] = vec3[CASCADES_COUNT](
+	vec3(1.0f, 0.25f, 0.25f),
+	vec3(0.25f, 1.0f, 0.25f),
+	vec3(0.25f, 0.25f, 1.0f),
+	vec3(1.0f, 1.0f, 0.25f)
+);
+
+uint selectCascade(vec3 worldPosition)
+{
+	float viewDepth = -(frame.view * vec4(worldPosition, 1.0f)).z;
+
+	for (uint i = 0; i < shadow.info.x - 1; ++i)
+	{
+		if (viewDepth < shadow.splitDepths[i])
+		{
+			return i;
+		}
+	}
+
+	return shadow.info.x - 1;
+}
+
+// 3x3 PCF on top of the hardware 2x2 comparison filtering.
+float sampleShadow(vec3 worldPosition, vec3 normal, uint cascade)
+{
+	vec4 lightSpacePosition = shadow.lightViewProjections[cascade] * vec4(worldPosition + normal * SHADOW_NORMAL_OFFSET, 1.0f);
+	vec3 projected = lightSpacePosition.xyz / lightSpacePosition.w;
+	if (projected.z > 1.0f)
+	{
+		return 1.0f;
+	}
+
+	vec2 uv = projected.xy * 0.5f + 0.5f;
+	vec2 texelSize = 1.0f / vec2(textureSize(shadowMap, 0).xy);
+
+	float visibility = 0.0f;
+	for (int x = -1; x <= 1; ++x)
+	{
+		for (int y = -1; y <= 1; ++y)
+		{
+			visibility += texture(shadowMap, vec4(uv + vec2(x, y) * texelSize, float(cascade), projected.z));
+		}
+	}
+
+	return visibility / 9.0f;
+}
+
+// Constant without an environment, otherwise its diffuse irradiance and the split sum
+// approximation of its specular reflection. Blinn-Phong shininess maps to a GGX roughness.
+vec3 ambientLight(vec3 albedo, vec3 normal, vec3 viewDirection, float specularIntensity, float shininess)
+{
+	if (frame.lightInfo.y == 0)
+	{
+		return albedo * AMBIENT_INTENSITY;
+	}
+
+	float roughness = sqrt(2.0f / (shininess + 2.0f));
+	float normalDotView = max(dot(normal, viewDirection), 0.0f);
+	vec3 reflected = reflect(-viewDirection, normal);
+
+	vec3 irradiance = texture(irradianceMap, normal).rgb;
+	vec3 prefiltered = textureLod(prefilteredMap, reflected, roughness * float(frame.lightInfo.z)).rgb;
+	vec2 brdf = texture(brdfLut, vec2(normalDotView, roughness)).rg;
+	vec3 specular = prefiltered * (DIELECTRIC_REFLECTANCE * brdf.x + brdf.y) * specularIntensity;
+
+	return (albedo * irradiance + specular) * uintBitsToFloat(frame.lightInfo.w);
+}
+
+float rangeAttenuation(float lightDistance, float range)
+{
+	float ratio = clamp(1.0f - pow(lightDistance / range, 4.0f), 0.0f, 1.0f);
+
+	return ratio * ratio / (lightDistance * lightDistance + 1.0f);
+}
+
+vec3 shadeLight(Light light, vec3 worldPosition, vec3 albedo, vec3 normal, vec3 viewDirection, vec2 material)
+{
+	uint lightType = uint(light.directionType.w);
+
+	vec3 lightDirection;
+	float attenuation = 1.0f;
+	if (lightType == LIGHT_TYPE_DIRECTIONAL)
+	{
+		lightDirection = -normalize(light.directionType.xyz);
+	}
+	else
+	{
+		vec3 toLight = light.positionRange.xyz - worldPosition;
+		float lightDistance = length(toLight);
+		lightDirection = toLight / lightDistance;
+		attenuation = rangeAttenuation(lightDistance, light.positionRange.w);
+
+		if (lightType == LIGHT_TYPE_SPOT)
+		{
+			float cosine = dot(-lightDirection, normalize(light.directionType.xyz));
+			attenuation *= smoothstep(light.coneCosines.y, light.coneCosines.x, cosine);
+		}
+	}
+
+	float diffuse = max(dot(normal, lightDirection), 0.0f);
+	vec3 halfway = normalize(lightDirection + viewDirection);
+	float specular = diffuse > 0.0f ? pow(max(dot(normal, halfway), 0.0f), material.y * MAX_SHININESS) * material.x : 0.0f;
+
+	vec3 radiance = light.colorIntensity.rgb * light.colorIntensity.a * attenuation;
+
+	return (albedo * diffuse + specular) * radiance;
+}
+
+// World position from the depth the G-buffer pass wrote.
+vec3 reconstructPosition(float depth)
+{
+	vec2 ndc = gl_FragCoord.xy / vec2(frame.screen.xy) * 2.0f - 1.0f;
+	vec4 viewPosition = frame.inverseProjection * vec4(ndc, depth, 1.0f);
+	viewPosition.xyz /= viewPosition.w;
+
+	return transpose(mat3(frame.view)) * (viewPosition.xyz - frame.view[3].xyz);
+}
+
+void main()
+{
+	ivec2 texel = ivec2(gl_FragCoord.xy);
+	vec4 gbufferColor = texelFetch(gbufferAlbedo, texel, 0);
+	vec3 albedo = gbufferColor.rgb;
+	float depth = texelFetch(sceneDepth, texel, 0).r;
+	// Nothing was drawn, the albedo holds the clear color.
+	if (depth >= 1.0f)
+	{
+		outFragColor = gbufferColor;
+		return;
+	}
+
+	vec3 normal = normalize(texelFetch(gbufferNormal, texel, 0).xyz);
+	vec2 material = texelFetch(gbufferMaterial, texel, 0).xy;
+	vec3 worldPosition = reconstructPosition(depth);
+	vec3 viewDirection = normalize(frame.cameraPosition.xyz - worldPosition);
+
+	uvec2 tile = uvec2(gl_FragCoord.xy) / TILE_SIZE;
+	uint tileOffset = (tile.y * frame.screen.z + tile.x) * TILE_STRIDE;
+	uint tileLightsCount = tileLights[tileOffset];
+
+	bool hasShadows = shadow.info.x > 0;
+	uint cascade = hasShadows ? selectCascade(worldPosition) : 0;
+
+	float occlusion = textureLod(ambientOcclusion, gl_FragCoord.xy / vec2(frame.screen.xy), 0.0f).r;
+
+	vec3 color = ambientLight(albedo, normal, viewDirection, material.x, material.y * MAX_SHININESS) * occlusion;
+	for (uint i = 0; i < tileLightsCount; ++i)
+	{
+		uint lightIndex = tileLights[tileOffset + 1 + i];
+		vec3 lightColor = shadeLight(lights[lightIndex], worldPosition, albedo, normal, viewDirection, material);
+
+		if (hasShadows && lightIndex == shadow.info.z)
+		{
+			lightColor *= sampleShadow(worldPosition, normal, cascade);
+		}
+
+		color += lightColor;
+	}
+
+	if (hasShadows && shadow.info.y != 0)
+	{
+		color *= cascadeColors[cascade];
+	}
+
+	outFragColor = vec4(color, 1.0f);
+}
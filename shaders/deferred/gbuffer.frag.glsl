@@ -0,0 +1,23 @@
+#version 450
+
+#define SPECULAR_INTENSITY 1.0f
+#define SHININESS 32.0f
+#define MAX_SHININESS 256.0f
+
+layout (location = 1) in vec3 inNormal;
+layout (location = 2) in vec4 inColor;
+layout (location = 3) flat in uint inInstanceIndex;
+
+layout (location = 0) out vec4 outAlbedo;
+// Instance index plus one for picking, at the same location as in the forward pass.
+layout (location = 1) out uint outInstanceId;
+layout (location = 2) out vec4 outNormal;
+layout (location = 3) out vec4 outMaterial;
+
+void main()
+{
+	outAlbedo = vec4(clamp(inColor.rgb, 0.0f, 1.0f), 1.0f);
+	outInstanceId = inInstanceIndex + 1u;
+	outNormal = vec4(normalize(inNormal), 0.0f);
+	outMaterial = vec4(SPECULAR_INTENSITY, SHININESS / MAX_SHININESS, 0.0f, 0.0f);
+}
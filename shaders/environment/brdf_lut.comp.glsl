@@ -0,0 +1,78 @@
+#version 450
+
+#define PI 3.14159265f
+#define SAMPLES_COUNT 1024u
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout (set = 0, binding = 21, rg16f) uniform writeonly image2D brdfLut;
+
+vec2 hammersley(uint i, uint count)
+{
+	uint bits = bitfieldReverse(i);
+
+	return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10f);
+}
+
+// Halfway vector around the normal, distributed like the GGX lobe of the roughness.
+vec3 importanceSampleGgx(vec2 xi, vec3 normal, float roughness)
+{
+	float alpha = roughness * roughness;
+	float phi = 2.0f * PI * xi.x;
+	float cosTheta = sqrt((1.0f - xi.y) / (1.0f + (alpha * alpha - 1.0f) * xi.y));
+	float sinTheta = sqrt(1.0f - cosTheta * cosTheta);
+	vec3 halfway = vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+
+	vec3 up = abs(normal.z) < 0.999f ? vec3(0.0f, 0.0f, 1.0f) : vec3(1.0f, 0.0f, 0.0f);
+	vec3 tangent = normalize(cross(up, normal));
+	vec3 bitangent = cross(normal, tangent);
+
+	return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+}
+
+float geometrySchlickGgx(float cosine, float roughness)
+{
+	// Remapped for image based lighting.
+	float k = roughness * roughness * 0.5f;
+
+	return cosine / (cosine * (1.0f - k) + k);
+}
+
+// Scale and bias to the specular reflectance at normal incidence, indexed by the cosine
+// between the normal and the view along x and the roughness along y.
+void main()
+{
+	ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+	ivec2 size = imageSize(brdfLut);
+	if (any(greaterThanEqual(texel, size)))
+	{
+		return;
+	}
+
+	vec2 uv = (vec2(texel) + 0.5f) / vec2(size);
+	float normalDotView = uv.x;
+	float roughness = uv.y;
+	vec3 viewDirection = vec3(sqrt(1.0f - normalDotView * normalDotView), 0.0f, normalDotView);
+	vec3 normal = vec3(0.0f, 0.0f, 1.0f);
+
+	vec2 scaleBias = vec2(0.0f);
+	for (uint i = 0u; i < SAMPLES_COUNT; ++i)
+	{
+		vec3 halfway = importanceSampleGgx(hammersley(i, SAMPLES_COUNT), normal, roughness);
+		vec3 lightDirection = normalize(2.0f * dot(viewDirection, halfway) * halfway - viewDirection);
+
+		float normalDotLight = max(lightDirection.z, 0.0f);
+		if (normalDotLight > 0.0f)
+		{
+			float normalDotHalfway = max(halfway.z, 0.0f);
+			float viewDotHalfway = max(dot(viewDirection, halfway), 0.0f);
+			float geometry = geometrySchlickGgx(normalDotView, roughness) * geometrySchlickGgx(normalDotLight, roughness);
+			float visibility = geometry * viewDotHalfway / (normalDotHalfway * normalDotView);
+			float fresnel = pow(1.0f - viewDotHalfway, 5.0f);
+
+			scaleBias += vec2((1.0f - fresnel) * visibility, fresnel * visibility);
+		}
+	}
+
+	imageStore(brdfLut, texel, vec4(scaleBias / float(SAMPLES_COUNT), 0.0f, 0.0f));
+}
@@ -0,0 +1,64 @@
+#version 450
+
+#define PI 3.14159265f
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout (set = 0, binding = 10) uniform sampler2D equirectangular;
+layout (set = 0, binding = 21, rgba16f) uniform writeonly image2DArray outputCube;
+
+// Direction through the center of a texel of a cube face, faces in +X, -X, +Y, -Y, +Z, -Z order.
+vec3 cubeDirection(ivec3 texel, ivec2 size)
+{
+	vec2 uv = (vec2(texel.xy) + 0.5f) / vec2(size) * 2.0f - 1.0f;
+
+	vec3 direction;
+	switch (texel.z)
+	{
+		case 0: direction = vec3(1.0f, -uv.y, -uv.x); break;
+		case 1: direction = vec3(-1.0f, -uv.y, uv.x); break;
+		case 2: direction = vec3(uv.x, 1.0f, uv.y); break;
+		case 3: direction = vec3(uv.x, -1.0f, -uv.y); break;
+		case 4: direction = vec3(uv.x, -uv.y, 1.0f); break;
+		default: direction = vec3(-uv.x, -uv.y, -1.0f); break;
+	}
+
+	return normalize(direction);
+}
+
+// 32-bit float images aren't guaranteed to be filterable, so texels are blended by hand.
+// Wraps around horizontally.
+vec3 sampleBilinear(vec2 uv)
+{
+	ivec2 size = textureSize(equirectangular, 0);
+	vec2 position = uv * vec2(size) - 0.5f;
+	ivec2 base = ivec2(floor(position));
+	vec2 weight = fract(position);
+
+	vec3 texels[4];
+	for (int i = 0; i < 4; ++i)
+	{
+		ivec2 texel = base + ivec2(i & 1, i >> 1);
+		texel.x = (texel.x % size.x + size.x) % size.x;
+		texel.y = clamp(texel.y, 0, size.y - 1);
+		texels[i] = texelFetch(equirectangular, texel, 0).rgb;
+	}
+
+	return mix(mix(texels[0], texels[1], weight.x), mix(texels[2], texels[3], weight.x), weight.y);
+}
+
+void main()
+{
+	ivec3 texel = ivec3(gl_GlobalInvocationID);
+	ivec2 size = imageSize(outputCube).xy;
+	if (any(greaterThanEqual(texel.xy, size)))
+	{
+		return;
+	}
+
+	// Rows of the image go from +Y at the top to -Y at the bottom.
+	vec3 direction = cubeDirection(texel, size);
+	vec2 uv = vec2(atan(direction.z, direction.x) / (2.0f * PI) + 0.5f, acos(clamp(direction.y, -1.0f, 1.0f)) / PI);
+
+	imageStore(outputCube, texel, vec4(sampleBilinear(uv), 1.0f));
+}
@@ -0,0 +1,62 @@
+#version 450
+
+#define PI 3.14159265f
+#define SAMPLE_DELTA 0.025f
+// Face size of 32, fine enough for the low frequency result and without aliasing.
+#define SOURCE_MIP 4.0f
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout (set = 0, binding = 10) uniform samplerCube environment;
+layout (set = 0, binding = 21, rgba16f) uniform writeonly image2DArray outputCube;
+
+// Direction through the center of a texel of a cube face, faces in +X, -X, +Y, -Y, +Z, -Z order.
+vec3 cubeDirection(ivec3 texel, ivec2 size)
+{
+	vec2 uv = (vec2(texel.xy) + 0.5f) / vec2(size) * 2.0f - 1.0f;
+
+	vec3 direction;
+	switch (texel.z)
+	{
+		case 0: direction = vec3(1.0f, -uv.y, -uv.x); break;
+		case 1: direction = vec3(-1.0f, -uv.y, uv.x); break;
+		case 2: direction = vec3(uv.x, 1.0f, uv.y); break;
+		case 3: direction = vec3(uv.x, -1.0f, -uv.y); break;
+		case 4: direction = vec3(uv.x, -uv.y, 1.0f); break;
+		default: direction = vec3(-uv.x, -uv.y, -1.0f); break;
+	}
+
+	return normalize(direction);
+}
+
+// Cosine weighted integral of the incoming light over the hemisphere around the normal.
+void main()
+{
+	ivec3 texel = ivec3(gl_GlobalInvocationID);
+	ivec2 size = imageSize(outputCube).xy;
+	if (any(greaterThanEqual(texel.xy, size)))
+	{
+		return;
+	}
+
+	vec3 normal = cubeDirection(texel, size);
+	vec3 up = abs(normal.y) < 0.999f ? vec3(0.0f, 1.0f, 0.0f) : vec3(0.0f, 0.0f, 1.0f);
+	vec3 tangent = normalize(cross(up, normal));
+	vec3 bitangent = cross(normal, tangent);
+
+	vec3 irradiance = vec3(0.0f);
+	float samplesCount = 0.0f;
+	for (float phi = 0.0f; phi < 2.0f * PI; phi += SAMPLE_DELTA)
+	{
+		for (float theta = 0.0f; theta < 0.5f * PI; theta += SAMPLE_DELTA)
+		{
+			vec3 local = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+			vec3 direction = tangent * local.x + bitangent * local.y + normal * local.z;
+
+			irradiance += textureLod(environment, direction, SOURCE_MIP).rgb * cos(theta) * sin(theta);
+			samplesCount += 1.0f;
+		}
+	}
+
+	imageStore(outputCube, texel, vec4(PI * irradiance / samplesCount, 1.0f));
+}
@@ -0,0 +1,104 @@
+#version 450
+
+#define PI 3.14159265f
+#define SAMPLES_COUNT 512u
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout (set = 0, binding = 10) uniform samplerCube environment;
+layout (set = 0, binding = 21, rgba16f) uniform writeonly image2DArray outputCube;
+
+layout (push_constant) uniform PushConstants
+{
+	float roughness;
+	float sourceSize;
+} pushConstants;
+
+// Direction through the center of a texel of a cube face, faces in +X, -X, +Y, -Y, +Z, -Z order.
+vec3 cubeDirection(ivec3 texel, ivec2 size)
+{
+	vec2 uv = (vec2(texel.xy) + 0.5f) / vec2(size) * 2.0f - 1.0f;
+
+	vec3 direction;
+	switch (texel.z)
+	{
+		case 0: direction = vec3(1.0f, -uv.y, -uv.x); break;
+		case 1: direction = vec3(-1.0f, -uv.y, uv.x); break;
+		case 2: direction = vec3(uv.x, 1.0f, uv.y); break;
+		case 3: direction = vec3(uv.x, -1.0f, -uv.y); break;
+		case 4: direction = vec3(uv.x, -uv.y, 1.0f); break;
+		default: direction = vec3(-uv.x, -uv.y, -1.0f); break;
+	}
+
+	return normalize(direction);
+}
+
+vec2 hammersley(uint i, uint count)
+{
+	uint bits = bitfieldReverse(i);
+
+	return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10f);
+}
+
+// Halfway vector around the normal, distributed like the GGX lobe of the roughness.
+vec3 importanceSampleGgx(vec2 xi, vec3 normal, float roughness)
+{
+	float alpha = roughness * roughness;
+	float phi = 2.0f * PI * xi.x;
+	float cosTheta = sqrt((1.0f - xi.y) / (1.0f + (alpha * alpha - 1.0f) * xi.y));
+	float sinTheta = sqrt(1.0f - cosTheta * cosTheta);
+	vec3 halfway = vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+
+	vec3 up = abs(normal.z) < 0.999f ? vec3(0.0f, 0.0f, 1.0f) : vec3(1.0f, 0.0f, 0.0f);
+	vec3 tangent = normalize(cross(up, normal));
+	vec3 bitangent = cross(normal, tangent);
+
+	return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+}
+
+float distributionGgx(float normalDotHalfway, float roughness)
+{
+	float alpha = roughness * roughness;
+	float alphaSquared = alpha * alpha;
+	float denominator = normalDotHalfway * normalDotHalfway * (alphaSquared - 1.0f) + 1.0f;
+
+	return alphaSquared / (PI * denominator * denominator);
+}
+
+// Incoming light convolved with the GGX lobe, assuming the view along the normal. Samples
+// read from the mip whose texels cover their solid angle, against bright spots aliasing.
+void main()
+{
+	ivec3 texel = ivec3(gl_GlobalInvocationID);
+	ivec2 size = imageSize(outputCube).xy;
+	if (any(greaterThanEqual(texel.xy, size)))
+	{
+		return;
+	}
+
+	vec3 normal = cubeDirection(texel, size);
+	float roughness = pushConstants.roughness;
+	float texelSolidAngle = 4.0f * PI / (6.0f * pushConstants.sourceSize * pushConstants.sourceSize);
+
+	vec3 color = vec3(0.0f);
+	float totalWeight = 0.0f;
+	for (uint i = 0u; i < SAMPLES_COUNT; ++i)
+	{
+		vec3 halfway = importanceSampleGgx(hammersley(i, SAMPLES_COUNT), normal, roughness);
+		vec3 lightDirection = normalize(2.0f * dot(normal, halfway) * halfway - normal);
+
+		float normalDotLight = dot(normal, lightDirection);
+		if (normalDotLight > 0.0f)
+		{
+			float normalDotHalfway = max(dot(normal, halfway), 0.0f);
+			float pdf = distributionGgx(normalDotHalfway, roughness) * 0.25f + 1e-4f;
+			float sampleSolidAngle = 1.0f / (float(SAMPLES_COUNT) * pdf);
+			float mip = roughness == 0.0f ? 0.0f : 0.5f * log2(sampleSolidAngle / texelSolidAngle);
+
+			color += textureLod(environment, lightDirection, mip).rgb * normalDotLight;
+			totalWeight += normalDotLight;
+		}
+	}
+
+	imageStore(outputCube, texel, vec4(color / max(totalWeight, 1e-4f), 1.0f));
+}
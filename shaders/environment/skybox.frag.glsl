@@ -0,0 +1,25 @@
+#version 450
+
+layout (location = 0) in vec3 inDirection;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (set = 0, binding = 15) uniform samplerCube environment;
+
+void main()
+{
+	float intensity = uintBitsToFloat(frame.lightInfo.w);
+
+	outFragColor = vec4(textureLod(environment, normalize(inDirection), 0.0f).rgb * intensity, 1.0f);
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout (location = 0) out vec3 outDirection;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+// Fullscreen triangle on the far plane, so it's only drawn where nothing else was.
+void main()
+{
+	vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0f - 1.0f;
+	vec4 viewPosition = frame.inverseProjection * vec4(position, 1.0f, 1.0f);
+
+	outDirection = transpose(mat3(frame.view)) * (viewPosition.xyz / viewPosition.w);
+	gl_Position = vec4(position, 1.0f, 1.0f);
+}
@@ -0,0 +1,10 @@
+#version 450
+
+layout (location = 0) out vec2 outUv;
+
+// One triangle covering the whole screen, no vertex buffers needed.
+void main()
+{
+	outUv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+	gl_Position = vec4(outUv * 2.0f - 1.0f, 0.0f, 1.0f);
+}
@@ -0,0 +1,34 @@
+#version 450
+
+// Clip space, relative to the screen height.
+#define GIZMO_SIZE 0.1f
+#define GIZMO_MARGIN 0.15f
+
+layout (location = 0) out vec4 outColor;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+// Three lines from the center of the gizmo along the world axes as seen by the camera.
+void main()
+{
+	uint axisIndex = gl_VertexIndex / 2;
+	vec3 axis = vec3(axisIndex == 0, axisIndex == 1, axisIndex == 2);
+	vec3 viewAxis = mat3(frame.view) * axis * float(gl_VertexIndex & 1);
+
+	// Keeps it square, Y points down in clip space.
+	float aspectCorrection = float(frame.screen.y) / float(frame.screen.x);
+	vec2 center = vec2(-1.0f + GIZMO_MARGIN * aspectCorrection, 1.0f - GIZMO_MARGIN);
+	vec2 offset = vec2(viewAxis.x * aspectCorrection, -viewAxis.y) * GIZMO_SIZE;
+
+	gl_Position = vec4(center + offset, 0.0f, 1.0f);
+	outColor = vec4(axis, 1.0f);
+}
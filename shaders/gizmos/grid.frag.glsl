@@ -0,0 +1,70 @@
+#version 450
+
+#define X_AXIS_COLOR vec4(0.9f, 0.2f, 0.2f, 1.0f)
+#define Z_AXIS_COLOR vec4(0.2f, 0.3f, 0.9f, 1.0f)
+
+layout (location = 0) in vec3 inNearPoint;
+layout (location = 1) in vec3 inFarPoint;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 minorColor;
+	vec4 majorColor;
+	// x - cell size, y - cells between major lines, z - fade distance.
+	vec4 parameters;
+} pushConstants;
+
+// 1 on a line, antialiased over a pixel.
+float gridLines(vec2 coordinates)
+{
+	vec2 lineDistance = abs(fract(coordinates - 0.5f) - 0.5f) / fwidth(coordinates);
+
+	return 1.0f - min(min(lineDistance.x, lineDistance.y), 1.0f);
+}
+
+void main()
+{
+	// Where the ray hits the XZ plane, only in front of the camera.
+	float t = -inNearPoint.y / (inFarPoint.y - inNearPoint.y);
+	vec3 worldPosition = inNearPoint + t * (inFarPoint - inNearPoint);
+
+	vec4 clipPosition = frame.viewProjection * vec4(worldPosition, 1.0f);
+	gl_FragDepth = clamp(clipPosition.z / clipPosition.w, 0.0f, 1.0f);
+
+	vec2 coordinates = worldPosition.xz / pushConstants.parameters.x;
+	float minor = gridLines(coordinates);
+	float major = gridLines(coordinates / pushConstants.parameters.y);
+	vec4 color = major > 0.0f ? pushConstants.majorColor * major : pushConstants.minorColor * minor;
+
+	vec2 axisWidth = fwidth(worldPosition.xz);
+	if (abs(worldPosition.z) < axisWidth.y)
+	{
+		color = X_AXIS_COLOR;
+	}
+	if (abs(worldPosition.x) < axisWidth.x)
+	{
+		color = Z_AXIS_COLOR;
+	}
+
+	float fade = 1.0f - smoothstep(0.0f, pushConstants.parameters.z, distance(worldPosition, frame.cameraPosition.xyz));
+	float alpha = color.a * fade * float(t > 0.0f);
+	if (alpha <= 0.0f)
+	{
+		discard;
+	}
+
+	outFragColor = vec4(color.rgb, alpha);
+}
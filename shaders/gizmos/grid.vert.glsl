@@ -0,0 +1,33 @@
+#version 450
+
+layout (location = 0) out vec3 outNearPoint;
+layout (location = 1) out vec3 outFarPoint;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+vec3 unproject(mat4 inverseViewProjection, vec2 position, float depth)
+{
+	vec4 worldPosition = inverseViewProjection * vec4(position, depth, 1.0f);
+
+	return worldPosition.xyz / worldPosition.w;
+}
+
+// Fullscreen triangle, every pixel casts a ray from the near to the far plane.
+void main()
+{
+	vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0f - 1.0f;
+	mat4 inverseViewProjection = inverse(frame.viewProjection);
+
+	outNearPoint = unproject(inverseViewProjection, position, 0.0f);
+	outFarPoint = unproject(inverseViewProjection, position, 1.0f);
+	gl_Position = vec4(position, 0.0f, 1.0f);
+}
@@ -0,0 +1,81 @@
+#version 450
+
+#define WORKGROUP_SIZE 64
+
+layout (local_size_x = WORKGROUP_SIZE) in;
+
+struct Instance
+{
+	mat4 model;
+	vec4 boundingSphere;
+	uvec4 draw;
+	vec4 dequantization;
+	uvec4 vertexFormat;
+	vec4 baseColor;
+	uvec4 material;
+};
+
+// Matches VkDrawIndexedIndirectCommand.
+struct DrawCommand
+{
+	uint indexCount;
+	uint instanceCount;
+	uint firstIndex;
+	int vertexOffset;
+	uint firstInstance;
+};
+
+layout (std430, set = 0, binding = 4) readonly buffer Instances
+{
+	Instance instances[];
+};
+
+layout (std430, set = 0, binding = 5) writeonly buffer DrawCommands
+{
+	DrawCommand drawCommands[];
+};
+
+layout (std430, set = 0, binding = 6) buffer DrawCounts
+{
+	uint drawCounts[];
+};
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 frustumPlanes[6];
+	uint instancesCount;
+} pushConstants;
+
+bool isVisible(vec3 center, float radius)
+{
+	for (int i = 0; i < 6; ++i)
+	{
+		if (dot(pushConstants.frustumPlanes[i].xyz, center) + pushConstants.frustumPlanes[i].w < -radius)
+		{
+			return false;
+		}
+	}
+
+	return true;
+}
+
+void main()
+{
+	uint instanceIndex = gl_GlobalInvocationID.x;
+	if (instanceIndex >= pushConstants.instancesCount)
+	{
+		return;
+	}
+
+	Instance instance = instances[instanceIndex];
+	vec3 center = (instance.model * vec4(instance.boundingSphere.xyz, 1.0f)).xyz;
+	float scale = max(length(instance.model[0].xyz), max(length(instance.model[1].xyz), length(instance.model[2].xyz)));
+	if (!isVisible(center, instance.boundingSphere.w * scale))
+	{
+		return;
+	}
+
+	// Visible instances of a batch are compacted at the start of its command range.
+	uint drawIndex = atomicAdd(drawCounts[instance.draw.y], 1);
+	drawCommands[instance.draw.z + drawIndex] = DrawCommand(instance.draw.x, 1, instance.draw.w, 0, instanceIndex);
+}
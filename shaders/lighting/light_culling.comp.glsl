@@ -0,0 +1,113 @@
+#version 450
+
+#define TILE_SIZE 16
+#define MAX_LIGHTS_PER_TILE 255
+#define TILE_STRIDE (MAX_LIGHTS_PER_TILE + 1)
+#define LIGHT_TYPE_DIRECTIONAL 0
+
+layout (local_size_x = TILE_SIZE, local_size_y = TILE_SIZE) in;
+
+struct Light
+{
+	vec4 positionRange;
+	vec4 directionType;
+	vec4 colorIntensity;
+	vec4 coneCosines;
+};
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (std430, set = 0, binding = 1) readonly buffer Lights
+{
+	Light lights[];
+};
+
+layout (std430, set = 0, binding = 2) writeonly buffer TileLights
+{
+	uint tileLights[];
+};
+
+shared uint tileLightsCount;
+shared vec3 tilePlanes[4];
+
+vec3 screenToView(vec2 screenPosition)
+{
+	vec2 ndc = screenPosition / vec2(frame.screen.xy) * 2.0f - 1.0f;
+	vec4 viewPosition = frame.inverseProjection * vec4(ndc, 1.0f, 1.0f);
+
+	return viewPosition.xyz / viewPosition.w;
+}
+
+void main()
+{
+	uvec2 tile = gl_WorkGroupID.xy;
+	uint tileIndex = tile.y * frame.screen.z + tile.x;
+
+	if (gl_LocalInvocationIndex == 0)
+	{
+		tileLightsCount = 0;
+
+		vec2 minScreen = vec2(tile * TILE_SIZE);
+		vec2 maxScreen = vec2((tile + 1) * TILE_SIZE);
+		vec3 tileCenter = screenToView((minScreen + maxScreen) * 0.5f);
+		vec3 corners[4] = vec3[4](
+			screenToView(vec2(minScreen.x, minScreen.y)),
+			screenToView(vec2(maxScreen.x, minScreen.y)),
+			screenToView(vec2(maxScreen.x, maxScreen.y)),
+			screenToView(vec2(minScreen.x, maxScreen.y))
+		);
+
+		// Side planes pass through the camera, so only normals are stored.
+		for (int i = 0; i < 4; ++i)
+		{
+			vec3 normal = normalize(cross(corners[i], corners[(i + 1) % 4]));
+			tilePlanes[i] = dot(normal, tileCenter) < 0.0f ? -normal : normal;
+		}
+	}
+
+	barrier();
+
+	uint threadsCount = gl_WorkGroupSize.x * gl_WorkGroupSize.y;
+	for (uint lightIndex = gl_LocalInvocationIndex; lightIndex < frame.lightInfo.x; lightIndex += threadsCount)
+	{
+		Light light = lights[lightIndex];
+
+		bool isVisible = true;
+		if (uint(light.directionType.w) != LIGHT_TYPE_DIRECTIONAL)
+		{
+			vec3 center = (frame.view * vec4(light.positionRange.xyz, 1.0f)).xyz;
+			float radius = light.positionRange.w;
+
+			isVisible = center.z - radius < 0.0f;
+			for (int i = 0; i < 4 && isVisible; ++i)
+			{
+				isVisible = dot(tilePlanes[i], center) > -radius;
+			}
+		}
+
+		if (isVisible)
+		{
+			uint slot = atomicAdd(tileLightsCount, 1);
+			if (slot < MAX_LIGHTS_PER_TILE)
+			{
+				tileLights[tileIndex * TILE_STRIDE + 1 + slot] = lightIndex;
+			}
+		}
+	}
+
+	barrier();
+
+	if (gl_LocalInvocationIndex == 0)
+	{
+		tileLights[tileIndex * TILE_STRIDE] = min(tileLightsCount, MAX_LIGHTS_PER_TILE);
+	}
+}
@@ -0,0 +1,65 @@
+#version 450
+
+#define NORMAL_OCTAHEDRAL 1
+
+layout (location = 0) in vec3 vPosition;
+layout (location = 1) in vec3 vNormal;
+layout (location = 2) in vec4 vColor;
+
+layout (location = 0) out vec3 outWorldPosition;
+layout (location = 1) out vec3 outNormal;
+layout (location = 2) out vec4 outColor;
+layout (location = 3) flat out uint outInstanceIndex;
+layout (location = 4) flat out uint outOutputMode;
+
+struct Instance
+{
+	mat4 model;
+	vec4 boundingSphere;
+	uvec4 draw;
+	vec4 dequantization;
+	uvec4 vertexFormat;
+	vec4 baseColor;
+	uvec4 material;
+};
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (std430, set = 0, binding = 4) readonly buffer Instances
+{
+	Instance instances[];
+};
+
+vec3 decodeOctahedral(vec2 encoded)
+{
+	vec3 normal = vec3(encoded, 1.0f - abs(encoded.x) - abs(encoded.y));
+	float fold = max(-normal.z, 0.0f);
+	normal.xy += vec2(normal.x >= 0.0f ? -fold : fold, normal.y >= 0.0f ? -fold : fold);
+
+	return normalize(normal);
+}
+
+void main()
+{
+	Instance instance = instances[gl_InstanceIndex];
+	vec3 position = instance.dequantization.xyz + vPosition * instance.dequantization.w;
+	vec3 normal = instance.vertexFormat.y == NORMAL_OCTAHEDRAL ? decodeOctahedral(vNormal.xy) : vNormal;
+
+	vec4 worldPosition = instance.model * vec4(position, 1.0f);
+	gl_Position = frame.viewProjection * worldPosition;
+
+	outWorldPosition = worldPosition.xyz;
+	outNormal = mat3(instance.model) * normal;
+	outColor = vColor * instance.baseColor;
+	outInstanceIndex = gl_InstanceIndex;
+	outOutputMode = instance.material.x;
+}
@@ -0,0 +1,39 @@
+#version 450
+
+layout (local_size_x = 8, local_size_y = 8) in;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+
+layout (set = 0, binding = 21, rgba16f) uniform writeonly image2D outputImage;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+const float weights[5] = float[](0.227027f, 0.1945946f, 0.1216216f, 0.054054f, 0.016216f);
+
+// One direction of a separable 9-tap gaussian.
+void main()
+{
+	ivec2 outputSize = imageSize(outputImage);
+	ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+	if (any(greaterThanEqual(texel, outputSize)))
+	{
+		return;
+	}
+
+	vec2 uv = (vec2(texel) + 0.5f) / vec2(outputSize);
+	vec2 offset = pushConstants.texelSize * pushConstants.direction;
+
+	vec3 color = texture(inputImage, uv).rgb * weights[0];
+	for (int i = 1; i < 5; ++i)
+	{
+		color += texture(inputImage, uv + offset * i).rgb * weights[i];
+		color += texture(inputImage, uv - offset * i).rgb * weights[i];
+	}
+
+	imageStore(outputImage, texel, vec4(color, 1.0f));
+}
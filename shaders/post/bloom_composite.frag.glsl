@@ -0,0 +1,24 @@
+#version 450
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+layout (set = 0, binding = 10) uniform sampler2D bloomImage;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+void main()
+{
+	vec4 source = texture(inputImage, inUv);
+	vec3 color = source.rgb;
+	vec3 bloom = texture(bloomImage, inUv).rgb;
+
+	outFragColor = vec4(color + bloom * pushConstants.parameters.z, source.a);
+}
@@ -0,0 +1,48 @@
+#version 450
+
+layout (local_size_x = 8, local_size_y = 8) in;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+
+layout (set = 0, binding = 21, rgba16f) uniform writeonly image2D outputImage;
+
+// x - threshold, y - soft knee relative to the threshold, z - intensity.
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+// Quadratic curve around the threshold instead of a hard cut, avoids flickering highlights.
+vec3 threshold(vec3 color)
+{
+	float threshold = pushConstants.parameters.x;
+	float knee = threshold * pushConstants.parameters.y;
+	float brightness = max(color.r, max(color.g, color.b));
+
+	float soft = clamp(brightness - threshold + knee, 0.0f, 2.0f * knee);
+	soft = soft * soft / (4.0f * knee + 1e-4f);
+
+	return color * max(soft, brightness - threshold) / max(brightness, 1e-4f);
+}
+
+void main()
+{
+	ivec2 outputSize = imageSize(outputImage);
+	ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+	if (any(greaterThanEqual(texel, outputSize)))
+	{
+		return;
+	}
+
+	// Each bilinear tap averages a 2x2 block, so four of them cover the 4x4 footprint.
+	vec2 uv = (vec2(texel) + 0.5f) / vec2(outputSize);
+	vec2 offset = pushConstants.texelSize;
+	vec3 color = texture(inputImage, uv + vec2(-offset.x, -offset.y)).rgb;
+	color += texture(inputImage, uv + vec2(offset.x, -offset.y)).rgb;
+	color += texture(inputImage, uv + vec2(-offset.x, offset.y)).rgb;
+	color += texture(inputImage, uv + vec2(offset.x, offset.y)).rgb;
+
+	imageStore(outputImage, texel, vec4(threshold(color * 0.25f), 1.0f));
+}
@@ -0,0 +1,27 @@
+#version 450
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+void main()
+{
+	// Grows towards the edges, the center stays sharp.
+	vec2 offset = (inUv - 0.5f) * 2.0f * pushConstants.parameters.x;
+
+	float red = texture(inputImage, inUv + offset).r;
+	vec4 middle = texture(inputImage, inUv);
+	float green = middle.g;
+	float blue = texture(inputImage, inUv - offset).b;
+
+	outFragColor = vec4(red, green, blue, middle.a);
+}
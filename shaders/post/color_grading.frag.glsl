@@ -0,0 +1,28 @@
+#version 450
+
+#define LUT_SIZE 32.0f
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+layout (set = 0, binding = 10) uniform sampler3D lut;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+void main()
+{
+	vec4 source = texture(inputImage, inUv);
+	vec3 color = clamp(source.rgb, 0.0f, 1.0f);
+
+	// Sample between the texel centers of the first and the last entries.
+	vec3 uvw = color * ((LUT_SIZE - 1.0f) / LUT_SIZE) + 0.5f / LUT_SIZE;
+
+	outFragColor = vec4(texture(lut, uvw).rgb, source.a);
+}
@@ -0,0 +1,63 @@
+#version 450
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+
+// x - span max, y - reduce multiplier, z - reduce minimum.
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+// Edges are found on perceptual luma, the input is linear.
+float luma(vec3 color)
+{
+	return sqrt(dot(color, vec3(0.299f, 0.587f, 0.114f)));
+}
+
+void main()
+{
+	vec2 texelSize = pushConstants.texelSize;
+	float spanMax = pushConstants.parameters.x;
+	float reduceMul = pushConstants.parameters.y;
+	float reduceMin = pushConstants.parameters.z;
+
+	vec4 middle = texture(inputImage, inUv);
+	vec3 colorMiddle = middle.rgb;
+	float lumaNorthWest = luma(texture(inputImage, inUv + vec2(-1.0f, -1.0f) * texelSize).rgb);
+	float lumaNorthEast = luma(texture(inputImage, inUv + vec2(1.0f, -1.0f) * texelSize).rgb);
+	float lumaSouthWest = luma(texture(inputImage, inUv + vec2(-1.0f, 1.0f) * texelSize).rgb);
+	float lumaSouthEast = luma(texture(inputImage, inUv + vec2(1.0f, 1.0f) * texelSize).rgb);
+	float lumaMiddle = luma(colorMiddle);
+
+	float lumaMin = min(lumaMiddle, min(min(lumaNorthWest, lumaNorthEast), min(lumaSouthWest, lumaSouthEast)));
+	float lumaMax = max(lumaMiddle, max(max(lumaNorthWest, lumaNorthEast), max(lumaSouthWest, lumaSouthEast)));
+
+	vec2 direction = vec2(
+		(lumaSouthWest + lumaSouthEast) - (lumaNorthWest + lumaNorthEast),
+		(lumaNorthWest + lumaSouthWest) - (lumaNorthEast + lumaSouthEast)
+	);
+
+	float directionReduce = max((lumaNorthWest + lumaNorthEast + lumaSouthWest + lumaSouthEast) * 0.25f * reduceMul, reduceMin);
+	float inverseDirectionMin = 1.0f / (min(abs(direction.x), abs(direction.y)) + directionReduce);
+	direction = clamp(direction * inverseDirectionMin, vec2(-spanMax), vec2(spanMax)) * texelSize;
+
+	vec3 colorA = 0.5f * (
+		texture(inputImage, inUv + direction * (1.0f / 3.0f - 0.5f)).rgb +
+		texture(inputImage, inUv + direction * (2.0f / 3.0f - 0.5f)).rgb
+	);
+	vec3 colorB = colorA * 0.5f + 0.25f * (
+		texture(inputImage, inUv - direction * 0.5f).rgb +
+		texture(inputImage, inUv + direction * 0.5f).rgb
+	);
+
+	float lumaB = luma(colorB);
+	vec3 color = (lumaB < lumaMin || lumaB > lumaMax) ? colorA : colorB;
+
+	outFragColor = vec4(color, middle.a);
+}
@@ -0,0 +1,33 @@
+#version 450
+
+#define MIDDLE_GREY 0.18f
+
+layout (local_size_x = 4, local_size_y = 4, local_size_z = 4) in;
+
+layout (set = 0, binding = 21, rgba16f) uniform writeonly image3D lut;
+
+// x - contrast, y - saturation, z - temperature, w - strength. texelSize.x is the step between LUT entries.
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+void main()
+{
+	ivec3 texel = ivec3(gl_GlobalInvocationID);
+	vec3 original = vec3(texel) * pushConstants.texelSize.x;
+
+	float temperature = pushConstants.parameters.z;
+	vec3 color = original * vec3(1.0f + temperature * 0.1f, 1.0f, 1.0f - temperature * 0.1f);
+
+	color = pow(max(color, vec3(0.0f)) / MIDDLE_GREY, vec3(pushConstants.parameters.x)) * MIDDLE_GREY;
+
+	float luma = dot(color, vec3(0.2126f, 0.7152f, 0.0722f));
+	color = mix(vec3(luma), color, pushConstants.parameters.y);
+
+	color = mix(original, max(color, vec3(0.0f)), pushConstants.parameters.w);
+
+	imageStore(lut, texel, vec4(color, 1.0f));
+}
@@ -0,0 +1,39 @@
+#version 450
+
+#define NO_SEED 0xFFFFFFFFu
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+
+layout (set = 0, binding = 22, r32ui) uniform readonly uimage2D selectionSeeds;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+// Covers pixels outside the selection up to the thickness away from it, with a soft edge.
+void main()
+{
+	vec4 source = texture(inputImage, inUv);
+	vec3 color = source.rgb;
+
+	ivec2 texel = ivec2(gl_FragCoord.xy);
+	uint seed = imageLoad(selectionSeeds, texel).r;
+	if (seed == NO_SEED)
+	{
+		outFragColor = source;
+		return;
+	}
+
+	vec2 seedTexel = vec2(seed & 0xFFFFu, seed >> 16u);
+	float distance = length(seedTexel - vec2(texel));
+	float coverage = distance > 0.0f ? clamp(pushConstants.parameters.w + 0.5f - distance, 0.0f, 1.0f) : 0.0f;
+
+	outFragColor = vec4(mix(color, pushConstants.parameters.rgb, coverage), mix(source.a, 1.0f, coverage));
+}
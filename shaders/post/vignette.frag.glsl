@@ -0,0 +1,26 @@
+#version 450
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D inputImage;
+
+layout (push_constant) uniform PushConstants
+{
+	vec4 parameters;
+	vec2 texelSize;
+	vec2 direction;
+} pushConstants;
+
+void main()
+{
+	vec4 source = texture(inputImage, inUv);
+	vec3 color = source.rgb;
+
+	// 0 in the center, 1 in the corners.
+	float centerDistance = length(inUv - 0.5f) * 1.41421356f;
+	float falloff = smoothstep(1.0f - pushConstants.parameters.y, 1.0f, centerDistance);
+
+	outFragColor = vec4(color * (1.0f - pushConstants.parameters.x * falloff), source.a);
+}
@@ -0,0 +1,60 @@
+#version 450
+
+#define NO_SEED 0xFFFFFFFFu
+
+layout (local_size_x = 8, local_size_y = 8) in;
+
+layout (set = 0, binding = 21, r32ui) uniform readonly uimage2D inputSeeds;
+layout (set = 0, binding = 22, r32ui) uniform writeonly uimage2D outputSeeds;
+
+layout (push_constant) uniform PushConstants
+{
+	uint step;
+} pushConstants;
+
+ivec2 unpackSeed(uint seed)
+{
+	return ivec2(seed & 0xFFFFu, seed >> 16u);
+}
+
+// Keeps the closest of the seeds found in the 3x3 neighbourhood spread by the step.
+void main()
+{
+	ivec2 outputSize = imageSize(outputSeeds);
+	ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+	if (any(greaterThanEqual(texel, outputSize)))
+	{
+		return;
+	}
+
+	int step = int(pushConstants.step);
+	uint closestSeed = NO_SEED;
+	int closestDistance = 0x7FFFFFFF;
+	for (int y = -1; y <= 1; ++y)
+	{
+		for (int x = -1; x <= 1; ++x)
+		{
+			ivec2 neighbour = texel + ivec2(x, y) * step;
+			if (any(lessThan(neighbour, ivec2(0))) || any(greaterThanEqual(neighbour, outputSize)))
+			{
+				continue;
+			}
+
+			uint seed = imageLoad(inputSeeds, neighbour).r;
+			if (seed == NO_SEED)
+			{
+				continue;
+			}
+
+			ivec2 offset = unpackSeed(seed) - texel;
+			int distance = offset.x * offset.x + offset.y * offset.y;
+			if (distance < closestDistance)
+			{
+				closestDistance = distance;
+				closestSeed = seed;
+			}
+		}
+	}
+
+	imageStore(outputSeeds, texel, uvec4(closestSeed));
+}
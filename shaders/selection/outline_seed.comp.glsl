@@ -0,0 +1,31 @@
+#version 450
+
+#define NO_SEED 0xFFFFFFFFu
+
+layout (local_size_x = 8, local_size_y = 8) in;
+
+layout (std430, set = 0, binding = 7) readonly buffer SelectedFlags
+{
+	uint selectedFlags[];
+};
+
+layout (set = 0, binding = 21, r32ui) uniform readonly uimage2D instanceIds;
+layout (set = 0, binding = 22, r32ui) uniform writeonly uimage2D outputSeeds;
+
+// Pixels of selected instances seed themselves, coordinates are packed as 16 bits each.
+void main()
+{
+	ivec2 outputSize = imageSize(outputSeeds);
+	ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+	if (any(greaterThanEqual(texel, outputSize)))
+	{
+		return;
+	}
+
+	// Ids are the instance index plus one, zero where nothing was drawn.
+	uint instanceId = imageLoad(instanceIds, texel).r;
+	bool isSelected = instanceId != 0u && selectedFlags[instanceId - 1u] != 0u;
+
+	uint seed = isSelected ? uint(texel.x) | (uint(texel.y) << 16u) : NO_SEED;
+	imageStore(outputSeeds, texel, uvec4(seed));
+}
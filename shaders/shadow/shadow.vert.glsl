@@ -0,0 +1,41 @@
+#version 450
+
+#define CASCADES_COUNT 4
+
+layout (location = 0) in vec3 vPosition;
+
+struct Instance
+{
+	mat4 model;
+	vec4 boundingSphere;
+	uvec4 draw;
+	vec4 dequantization;
+	uvec4 vertexFormat;
+	vec4 baseColor;
+	uvec4 material;
+};
+
+layout (std430, set = 0, binding = 3) readonly buffer ShadowData
+{
+	mat4 lightViewProjections[CASCADES_COUNT];
+	vec4 splitDepths;
+	uvec4 info;
+} shadow;
+
+layout (std430, set = 0, binding = 4) readonly buffer Instances
+{
+	Instance instances[];
+};
+
+layout (push_constant) uniform PushConstants
+{
+	uint cascadeIndex;
+} pushConstants;
+
+void main()
+{
+	Instance instance = instances[gl_InstanceIndex];
+	vec3 position = instance.dequantization.xyz + vPosition * instance.dequantization.w;
+
+	gl_Position = shadow.lightViewProjections[pushConstants.cascadeIndex] * instance.model * vec4(position, 1.0f);
+}
@@ -0,0 +1,105 @@
+#version 450
+
+#define MAX_SAMPLES 64
+#define NOISE_SIZE 4
+#define TAU 6.28318531f
+
+layout (location = 0) in vec2 inUv;
+
+// Ambient light left after occlusion, 1 where nothing is occluded.
+layout (location = 0) out float outOcclusion;
+
+layout (set = 0, binding = 0) uniform FrameData
+{
+	mat4 view;
+	mat4 projection;
+	mat4 viewProjection;
+	mat4 inverseProjection;
+	vec4 cameraPosition;
+	uvec4 screen;
+	uvec4 lightInfo;
+} frame;
+
+layout (set = 0, binding = 10) uniform sampler2D sceneDepth;
+layout (set = 0, binding = 11) uniform sampler2D sceneNormal;
+
+layout (push_constant) uniform PushConstants
+{
+	float radius;
+	float intensity;
+	float bias;
+	uint samplesCount;
+} pushConstants;
+
+uint hash(uint value)
+{
+	value ^= value >> 16;
+	value *= 0x7feb352du;
+	value ^= value >> 15;
+	value *= 0x846ca68bu;
+	value ^= value >> 16;
+
+	return value;
+}
+
+float random(uint seed)
+{
+	return float(hash(seed)) / 4294967296.0f;
+}
+
+vec3 viewPosition(vec2 uv)
+{
+	float depth = textureLod(sceneDepth, uv, 0.0f).r;
+	vec4 position = frame.inverseProjection * vec4(uv * 2.0f - 1.0f, depth, 1.0f);
+
+	return position.xyz / position.w;
+}
+
+// Fixed kernel in the hemisphere around +z, denser towards the center.
+vec3 kernelSample(uint index, uint samplesCount)
+{
+	vec3 direction = normalize(vec3(
+		random(index * 3u) * 2.0f - 1.0f,
+		random(index * 3u + 1u) * 2.0f - 1.0f,
+		random(index * 3u + 2u) + 0.05f
+	));
+	float scale = float(index + 1u) / float(samplesCount);
+
+	return direction * mix(0.1f, 1.0f, scale * scale);
+}
+
+void main()
+{
+	if (textureLod(sceneDepth, inUv, 0.0f).r >= 1.0f)
+	{
+		outOcclusion = 1.0f;
+		return;
+	}
+
+	vec3 position = viewPosition(inUv);
+	vec3 normal = normalize(mat3(frame.view) * textureLod(sceneNormal, inUv, 0.0f).xyz);
+
+	// Rotates the kernel around the normal in a pattern repeating every NOISE_SIZE pixels,
+	// which the blur pass averages away.
+	uvec2 noiseTexel = uvec2(gl_FragCoord.xy) % NOISE_SIZE;
+	float angle = random(0x9e3779b9u + noiseTexel.y * NOISE_SIZE + noiseTexel.x) * TAU;
+	vec3 rotation = vec3(cos(angle), sin(angle), 0.0f);
+	vec3 tangent = normalize(rotation - normal * dot(rotation, normal));
+	mat3 tangentToView = mat3(tangent, cross(normal, tangent), normal);
+
+	uint samplesCount = clamp(pushConstants.samplesCount, 1u, MAX_SAMPLES);
+	float occlusion = 0.0f;
+	for (uint i = 0; i < samplesCount; ++i)
+	{
+		vec3 samplePosition = position + tangentToView * kernelSample(i, samplesCount) * pushConstants.radius;
+		vec4 projected = frame.projection * vec4(samplePosition, 1.0f);
+		vec2 sampleUv = projected.xy / projected.w * 0.5f + 0.5f;
+
+		// View space looks down -z, so closer surfaces have a greater z.
+		float surfaceDepth = viewPosition(sampleUv).z;
+		float rangeCheck = smoothstep(0.0f, 1.0f, pushConstants.radius / abs(position.z - surfaceDepth));
+		occlusion += (surfaceDepth >= samplePosition.z + pushConstants.bias ? 1.0f : 0.0f) * rangeCheck;
+	}
+
+	outOcclusion = clamp(1.0f - occlusion / float(samplesCount) * pushConstants.intensity, 0.0f, 1.0f);
+}
@@ -0,0 +1,27 @@
+#version 450
+
+#define NOISE_SIZE 4
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out float outOcclusion;
+
+layout (set = 0, binding = 10) uniform sampler2D inputOcclusion;
+
+// Box filter as wide as the noise pattern of the occlusion pass.
+void main()
+{
+	vec2 texelSize = 1.0f / vec2(textureSize(inputOcclusion, 0));
+
+	float occlusion = 0.0f;
+	for (int x = 0; x < NOISE_SIZE; ++x)
+	{
+		for (int y = 0; y < NOISE_SIZE; ++y)
+		{
+			vec2 offset = vec2(x, y) - float(NOISE_SIZE / 2);
+			occlusion += texture(inputOcclusion, inUv + offset * texelSize).r;
+		}
+	}
+
+	outOcclusion = occlusion / float(NOISE_SIZE * NOISE_SIZE);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout (location = 1) in vec3 inNormal;
+
+// World space like the G-buffer normals the deferred path feeds the occlusion pass with.
+layout (location = 0) out vec4 outNormal;
+
+void main()
+{
+	outNormal = vec4(normalize(inNormal), 0.0f);
+}
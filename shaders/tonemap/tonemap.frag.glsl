@@ -0,0 +1,117 @@
+#version 450
+
+#define TONEMAPPER_ACES 0
+#define TONEMAPPER_REINHARD 1
+#define TONEMAPPER_AGX 2
+
+#define DISPLAY_MODE_SDR 0
+#define DISPLAY_MODE_HDR10 1
+#define DISPLAY_MODE_SCRGB 2
+
+#define SCRGB_REFERENCE_NITS 80.0f
+#define PQ_MAX_NITS 10000.0f
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D hdrTarget;
+
+layout (push_constant) uniform PushConstants
+{
+	float exposure;
+	uint tonemapper;
+	uint displayMode;
+	float paperWhiteNits;
+} pushConstants;
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 color)
+{
+	return clamp((color * (2.51f * color + 0.03f)) / (color * (2.43f * color + 0.59f) + 0.14f), 0.0f, 1.0f);
+}
+
+vec3 reinhard(vec3 color)
+{
+	return color / (1.0f + color);
+}
+
+vec3 agxContrast(vec3 x)
+{
+	vec3 x2 = x * x;
+	vec3 x4 = x2 * x2;
+
+	return 15.5f * x4 * x2 - 40.14f * x4 * x + 31.96f * x4 - 6.868f * x2 * x + 0.4298f * x2 + 0.1191f * x - 0.00232f;
+}
+
+vec3 agx(vec3 color)
+{
+	const mat3 agxInset = mat3(
+		0.842479062253094f, 0.0423282422610123f, 0.0423756549057051f,
+		0.0784335999999992f, 0.878468636469772f, 0.0784336f,
+		0.0792237451477643f, 0.0791661274605434f, 0.879142973793104f
+	);
+	const mat3 agxOutset = mat3(
+		1.19687900512017f, -0.0528968517574562f, -0.0529716355144438f,
+		-0.0980208811401368f, 1.15190312990417f, -0.0980434501171241f,
+		-0.0990297440797205f, -0.0989611768448433f, 1.15107367264116f
+	);
+	const float minEv = -12.47393f;
+	const float maxEv = 4.026069f;
+
+	color = agxInset * color;
+	color = clamp(log2(max(color, vec3(1e-10f))), minEv, maxEv);
+	color = (color - minEv) / (maxEv - minEv);
+	color = agxOutset * agxContrast(color);
+
+	// The curve outputs display encoded values, the sRGB swapchain expects linear ones.
+	return pow(max(color, vec3(0.0f)), vec3(2.2f));
+}
+
+vec3 encodePq(vec3 nits)
+{
+	const float m1 = 0.1593017578125f;
+	const float m2 = 78.84375f;
+	const float c1 = 0.8359375f;
+	const float c2 = 18.8515625f;
+	const float c3 = 18.6875f;
+
+	vec3 y = pow(clamp(nits / PQ_MAX_NITS, 0.0f, 1.0f), vec3(m1));
+
+	return pow((c1 + c2 * y) / (1.0f + c3 * y), vec3(m2));
+}
+
+void main()
+{
+	vec4 hdr = texture(hdrTarget, inUv);
+	vec3 color = hdr.rgb * pushConstants.exposure;
+
+	switch (pushConstants.tonemapper)
+	{
+		case TONEMAPPER_REINHARD:
+			color = reinhard(color);
+			break;
+		case TONEMAPPER_AGX:
+			color = agx(color);
+			break;
+		default:
+			color = aces(color);
+			break;
+	}
+
+	if (pushConstants.displayMode == DISPLAY_MODE_HDR10)
+	{
+		const mat3 rec709ToRec2020 = mat3(
+			0.6274040f, 0.0690970f, 0.0163916f,
+			0.3292820f, 0.9195400f, 0.0880132f,
+			0.0433136f, 0.0113612f, 0.8955950f
+		);
+		color = encodePq(rec709ToRec2020 * color * pushConstants.paperWhiteNits);
+	}
+	else if (pushConstants.displayMode == DISPLAY_MODE_SCRGB)
+	{
+		color *= pushConstants.paperWhiteNits / SCRGB_REFERENCE_NITS;
+	}
+
+	outFragColor = vec4(color, hdr.a);
+}
@@ -0,0 +1,23 @@
+#version 450
+
+layout (location = 0) in vec2 inUv;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D accumulationImage;
+layout (set = 0, binding = 10) uniform sampler2D revealageImage;
+
+// Average color of the transparent layers, covering as much of the scene as they hide.
+void main()
+{
+	float revealage = texture(revealageImage, inUv).r;
+	if (revealage >= 1.0f)
+	{
+		discard;
+	}
+
+	vec4 accumulation = texture(accumulationImage, inUv);
+	vec3 averageColor = accumulation.rgb / max(accumulation.a, 1e-5f);
+
+	outFragColor = vec4(averageColor, 1.0f - revealage);
+}
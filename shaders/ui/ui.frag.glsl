@@ -0,0 +1,63 @@
+#version 450
+
+#define DISPLAY_MODE_SDR 0
+#define DISPLAY_MODE_HDR10 1
+#define DISPLAY_MODE_SCRGB 2
+
+#define SCRGB_REFERENCE_NITS 80.0f
+#define PQ_MAX_NITS 10000.0f
+
+layout (location = 0) in vec2 inUv;
+layout (location = 1) in vec4 inColor;
+
+layout (location = 0) out vec4 outFragColor;
+
+layout (set = 0, binding = 9) uniform sampler2D uiTexture;
+
+layout (push_constant) uniform PushConstants
+{
+	vec2 screenSize;
+	uint displayMode;
+	float paperWhiteNits;
+} pushConstants;
+
+vec3 srgbToLinear(vec3 color)
+{
+	return mix(color / 12.92f, pow((color + 0.055f) / 1.055f, vec3(2.4f)), greaterThan(color, vec3(0.04045f)));
+}
+
+vec3 encodePq(vec3 nits)
+{
+	const float m1 = 0.1593017578125f;
+	const float m2 = 78.84375f;
+	const float c1 = 0.8359375f;
+	const float c2 = 18.8515625f;
+	const float c3 = 18.6875f;
+
+	vec3 y = pow(clamp(nits / PQ_MAX_NITS, 0.0f, 1.0f), vec3(m1));
+
+	return pow((c1 + c2 * y) / (1.0f + c3 * y), vec3(m2));
+}
+
+// Vertex colors and textures are premultiplied sRGB, the output is encoded for the swapchain.
+void main()
+{
+	vec4 color = inColor * texture(uiTexture, inUv);
+	vec3 linearColor = srgbToLinear(color.rgb);
+
+	if (pushConstants.displayMode == DISPLAY_MODE_HDR10)
+	{
+		const mat3 rec709ToRec2020 = mat3(
+			0.6274040f, 0.0690970f, 0.0163916f,
+			0.3292820f, 0.9195400f, 0.0880132f,
+			0.0433136f, 0.0113612f, 0.8955950f
+		);
+		linearColor = encodePq(rec709ToRec2020 * linearColor * pushConstants.paperWhiteNits);
+	}
+	else if (pushConstants.displayMode == DISPLAY_MODE_SCRGB)
+	{
+		linearColor *= pushConstants.paperWhiteNits / SCRGB_REFERENCE_NITS;
+	}
+
+	outFragColor = vec4(linearColor, color.a);
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout (location = 0) in vec2 vPosition;
+layout (location = 1) in vec2 vUv;
+layout (location = 2) in vec4 vColor;
+
+layout (location = 0) out vec2 outUv;
+layout (location = 1) out vec4 outColor;
+
+layout (push_constant) uniform PushConstants
+{
+	vec2 screenSize;
+	uint displayMode;
+	float paperWhiteNits;
+} pushConstants;
+
+// Positions are in points with the origin in the top left corner, same as clip space Y.
+void main()
+{
+	gl_Position = vec4(2.0f * vPosition / pushConstants.screenSize - 1.0f, 0.0f, 1.0f);
+
+	outUv = vUv;
+	outColor = vColor;
+}
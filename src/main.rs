@@ -6,63 +6,134 @@ mod fps_counter;
 mod no_engine;
 
 use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
     event_loop,
 };
 
 use mimalloc::MiMalloc;
 
+use no_engine::{Background, Config, DebugView, PostEffectKind};
+
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
 fn main() {
+    let config = Config::load(Config::PATH);
+
     let event_loop = event_loop::EventLoop::new();
     let window = winit::window::WindowBuilder::new()
         .with_title("Hello winit")
         // TODO: Later remove
         .with_resizable(false)
+        // Decided at startup, switching to a transparent background later shows black until
+        // the next run.
+        .with_transparent(matches!(config.background, Background::Transparent))
         .build(&event_loop)
         .unwrap();
 
-    let mut no_engine = no_engine::NoEngine::new(&window);
+    let mut no_engine = no_engine::NoEngine::new(&window, config);
+    no_engine.set_picking(true);
     let mut fps_counter = fps_counter::FPSCounter::new();
-
-    let mut does_show_fps = false;
-    let mut next_time_to_show = std::time::Instant::now() + std::time::Duration::from_secs(1);
+    let mut cursor_position = winit::dpi::PhysicalPosition::<f64>::default();
 
     event_loop.run(move |event, _, control_flow| match event {
-        Event::WindowEvent { window_id, event } if window_id == window.id() => match event {
-            WindowEvent::CloseRequested => control_flow.set_exit(),
-            WindowEvent::DroppedFile(path) => {
-                no_engine.load_file(path);
+        Event::WindowEvent { window_id, event }
+            if window_id == window.id() && !no_engine.handle_window_event(&event) =>
+        {
+            match event {
+                WindowEvent::CloseRequested => control_flow.set_exit(),
+                WindowEvent::DroppedFile(path) => {
+                    no_engine.load_file(path);
+                }
+                WindowEvent::CursorMoved { position, .. } => cursor_position = position,
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                } => {
+                    let picked = no_engine.pick(cursor_position.x as _, cursor_position.y as _);
+                    no_engine.clear_selection();
+                    if let Some(instance_id) = picked {
+                        no_engine.select(instance_id);
+                    }
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Escape),
+                            ..
+                        },
+                    ..
+                } => control_flow.set_exit(),
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(virtual_keycode),
+                            ..
+                        },
+                    ..
+                } => match virtual_keycode {
+                    VirtualKeyCode::C => no_engine.toggle_cascades_debug(),
+                    VirtualKeyCode::M => no_engine.cycle_msaa(),
+                    VirtualKeyCode::I => no_engine.toggle_gpu_driven(),
+                    VirtualKeyCode::O => no_engine.cycle_transparency_mode(),
+                    VirtualKeyCode::P => no_engine.cycle_render_path(),
+                    VirtualKeyCode::A => no_engine.toggle_ssao(),
+                    VirtualKeyCode::T => no_engine.cycle_tonemapper(),
+                    VirtualKeyCode::B => no_engine.toggle_post_effect(PostEffectKind::Bloom),
+                    VirtualKeyCode::G => no_engine.toggle_post_effect(PostEffectKind::ColorGrading),
+                    VirtualKeyCode::X => {
+                        no_engine.toggle_post_effect(PostEffectKind::ChromaticAberration)
+                    }
+                    VirtualKeyCode::V => no_engine.toggle_post_effect(PostEffectKind::Vignette),
+                    VirtualKeyCode::F => no_engine.toggle_post_effect(PostEffectKind::Fxaa),
+                    VirtualKeyCode::Key1 => no_engine.set_debug_view(DebugView::Lit),
+                    VirtualKeyCode::Key2 => no_engine.set_debug_view(DebugView::Wireframe),
+                    VirtualKeyCode::Key3 => no_engine.set_debug_view(DebugView::Overdraw),
+                    VirtualKeyCode::Key4 => no_engine.set_debug_view(DebugView::Depth),
+                    VirtualKeyCode::N => no_engine.toggle_normals_debug(),
+                    VirtualKeyCode::K => no_engine.toggle_bounds_debug(),
+                    VirtualKeyCode::H => no_engine.toggle_grid(),
+                    VirtualKeyCode::J => no_engine.toggle_axis_gizmo(),
+                    VirtualKeyCode::Equals => no_engine.set_exposure(no_engine.exposure() * 1.25),
+                    VirtualKeyCode::Minus => no_engine.set_exposure(no_engine.exposure() / 1.25),
+                    VirtualKeyCode::E => {
+                        if let Err(error) = no_engine.cycle_background() {
+                            eprintln!("Failed to save the config: {error}");
+                        }
+                    }
+                    VirtualKeyCode::F1 => no_engine.toggle_ui(),
+                    _ => (),
+                },
+                _ => (),
             }
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        state: ElementState::Pressed,
-                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                        ..
-                    },
-                ..
-            } => control_flow.set_exit(),
-            _ => (),
-        },
+        }
         Event::MainEventsCleared => {
             window.request_redraw();
-
-            if std::time::Instant::now() >= next_time_to_show {
-                does_show_fps = !does_show_fps;
-            }
         }
         Event::RedrawRequested(_) => {
-            no_engine.draw();
             fps_counter.frame();
 
-            if does_show_fps {
-                window.set_title(&format!("FPS: {}", fps_counter.fps()));
-                does_show_fps = !does_show_fps;
-                next_time_to_show = std::time::Instant::now() + std::time::Duration::from_secs(1);
-            }
+            let culling_stats = no_engine.culling_stats();
+            no_engine.draw_text(
+                glam::Vec2::splat(8.0),
+                18.0,
+                glam::Vec4::ONE,
+                &format!(
+                    "FPS: {}\nFrame time: {:.2} ms\nDrawn: {} | Culled: {} | Dropped: {} | Draw calls: {}",
+                    fps_counter.fps(),
+                    no_engine.frame_time().as_secs_f64() * 1000.0,
+                    culling_stats.drawn,
+                    culling_stats.culled,
+                    culling_stats.dropped,
+                    culling_stats.draw_calls
+                ),
+            );
+
+            no_engine.update_ui(&window);
+            no_engine.draw();
         }
         Event::RedrawEventsCleared => {
             no_engine.update();
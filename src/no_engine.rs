@@ -1,25 +1,81 @@
 mod allocator;
 mod asset;
+mod background;
+mod camera;
 mod command;
+mod compute;
+mod config;
+mod debug_draw;
 mod debug_utils;
+mod debug_views;
+mod deferred;
 mod device;
+mod environment;
+mod fullscreen;
+mod gizmos;
+mod gpu_data;
 mod id;
+mod instances;
+mod lighting;
 mod objects;
+mod picking;
+mod post_processing;
 mod register;
+mod render_graph;
+mod render_state;
 mod rendering_info;
+mod selection;
 mod shader;
+mod shadows;
+mod ssao;
 mod surface;
 mod swapchain;
+mod text;
+mod tonemapping;
+mod transparency;
+mod ui_overlay;
 mod utils;
 
+pub use allocator::AllocatedBuffer;
+pub use background::Background;
+pub use compute::{ComputeBarrier, ComputeDispatch, UnknownComputeShader};
+pub use config::Config;
+pub use debug_draw::DebugDraw;
+pub use debug_views::DebugView;
+pub use deferred::RenderPath;
 pub use id::*;
+pub use instances::CullingStats;
+pub use objects::{
+    instance::MeshInstance,
+    light::{Light, LightType},
+    material::{BlendMode, Material},
+    vertex_format::{NormalFormat, PositionFormat, VertexFormat},
+};
+pub use post_processing::{PostEffect, PostEffectKind};
+pub use render_state::Msaa;
+pub use shader::descriptors::DescriptorWriter;
+pub use ssao::SsaoSettings;
+pub use swapchain::ColorTarget;
+pub use tonemapping::{DisplayMode, Tonemapper};
+pub use transparency::TransparencyMode;
 
-use std::{ffi::CString, mem::ManuallyDrop, rc::Rc};
+use std::{
+    ffi::CString,
+    mem::ManuallyDrop,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
+use arrayvec::ArrayVec;
 use ash::vk;
 use raw_window_handle::HasRawDisplayHandle;
 
-use self::asset::ObjectsQueue;
+use self::{
+    asset::ObjectsQueue,
+    gpu_data::FrameData,
+    objects::ObjectType,
+    render_graph::{BufferAccess, ImageAccess, RenderGraph},
+};
 
 pub struct NoEngine<'a> {
     entry: ManuallyDrop<ash::Entry>,
@@ -35,7 +91,34 @@ pub struct NoEngine<'a> {
     allocator: allocator::Allocator,
     asset_manager: asset::AssetManager,
     register: register::Register,
-    frame_count: u32,
+    camera: camera::Camera,
+    frame_data_buffer: allocator::AllocatedBuffer,
+    compute_queue: Vec<ComputeDispatch>,
+    /// Created since the last frame, moved to `GENERAL` before the queued dispatches.
+    uninitialized_storage_images: Vec<vk::Image>,
+    light_manager: lighting::LightManager,
+    instance_manager: instances::InstanceManager,
+    shadow_manager: shadows::ShadowManager,
+    debug_views: debug_views::DebugViews,
+    debug_draw: DebugDraw,
+    gizmos: gizmos::Gizmos,
+    picking: picking::Picking,
+    selection: selection::Selection,
+    transparency: transparency::Transparency,
+    deferred: deferred::Deferred,
+    ssao: ssao::Ssao,
+    environment: environment::Environment,
+    /// Saved whenever a setting kept in it changes.
+    config: Config,
+    transient_pool: render_graph::TransientPool,
+    render_state: render_state::RenderState,
+    msaa: Msaa,
+    post_processing: post_processing::PostProcessing,
+    display_mode: DisplayMode,
+    text_renderer: text::TextRenderer,
+    ui_overlay: ui_overlay::UiOverlay,
+    last_frame_instant: Instant,
+    frame_time: Duration,
 }
 
 impl NoEngine<'_> {
@@ -45,14 +128,28 @@ impl NoEngine<'_> {
     pub const APPLICATION_NAME: &'static str = "Hello Triangle";
     pub const VALIDATION_LAYER_NAME: &'static str = "VK_LAYER_KHRONOS_validation";
 
+    pub const MESH_SHADER_NAME: &'static str = "lit";
+
+    /// `config` is usually loaded from `Config::PATH`, a transparent background in it needs
+    /// the window to be created transparent.
     #[inline(always)]
-    pub fn new(window: &winit::window::Window) -> Self {
+    pub fn new(window: &winit::window::Window, config: Config) -> Self {
+        Self::with_display_mode(window, Default::default(), config)
+    }
+
+    /// HDR display modes fall back to SDR when the surface doesn't support them.
+    pub fn with_display_mode(
+        window: &winit::window::Window,
+        display_mode: DisplayMode,
+        config: Config,
+    ) -> Self {
         let entry = ash::Entry::linked();
         let instance = Self::create_instance(window, &entry);
         let debug_handler = debug_utils::DebugHandler::new(&entry, &instance);
 
         let surface_manager = surface::SurfaceManager::new(&entry, &instance, window);
-        let device_manager = unsafe { device::DeviceManager::new(&instance, &surface_manager) };
+        let device_manager =
+            unsafe { device::DeviceManager::new(&instance, &surface_manager, display_mode) };
         let command_manager = unsafe {
             command::CommandManager::new(
                 &device_manager.device,
@@ -79,6 +176,7 @@ impl NoEngine<'_> {
             extent,
             surface_manager.surface,
             allocator,
+            Msaa::default().sample_count(),
         );
 
         let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
@@ -90,6 +188,24 @@ impl NoEngine<'_> {
         };
         let mut shader_manager = shader::ShaderManager::new(&instance, &device_manager.device);
         shader_manager.compile_shaders_from_folder(r"shaders/unlit");
+        shader_manager.compile_shaders_from_folder(r"shaders/lit");
+        shader_manager.compile_shaders_from_folder(r"shaders/lighting");
+        shader_manager.compile_shaders_from_folder(r"shaders/instances");
+        shader_manager.compile_shaders_from_folder(r"shaders/shadow");
+        shader_manager.compile_shaders_from_folder(r"shaders/fullscreen");
+        shader_manager.compile_shaders_from_folder(r"shaders/tonemap");
+        shader_manager.compile_shaders_from_folder(r"shaders/post");
+        shader_manager.compile_shaders_from_folder(r"shaders/selection");
+        shader_manager.compile_shaders_from_folder(r"shaders/transparency");
+        shader_manager.compile_shaders_from_folder(r"shaders/deferred");
+        shader_manager.compile_shaders_from_folder(r"shaders/ssao");
+        shader_manager.compile_shaders_from_folder(r"shaders/environment");
+        shader_manager.compile_shaders_from_folder(r"shaders/background");
+        shader_manager.compile_shaders_from_folder(r"shaders/debug");
+        shader_manager.compile_shaders_from_folder(r"shaders/gizmos");
+        shader_manager.compile_shaders_from_folder(r"shaders/text");
+        shader_manager.compile_shaders_from_folder(r"shaders/ui");
+        shader_manager.upload_required_shaders();
 
         let semaphore_info = vk::SemaphoreCreateInfo::default();
         let semaphore_rendering = unsafe {
@@ -114,6 +230,48 @@ impl NoEngine<'_> {
 
         let asset_manager = asset::AssetManager::new();
 
+        let camera = camera::Camera::new(extent.width as f32 / extent.height as f32);
+        let frame_data_buffer = allocator.allocate_uninit_buffer(
+            std::mem::size_of::<FrameData>() as _,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            ObjectType::Uniform,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+        let light_manager = lighting::LightManager::new(&allocator, extent);
+        let instance_manager = instances::InstanceManager::new(&allocator);
+        let shadow_manager = shadows::ShadowManager::new(
+            &device_manager.device,
+            &allocator,
+            device_manager.features.depth_clamp,
+        );
+        let debug_views = debug_views::DebugViews::new();
+        let debug_draw = DebugDraw::new(&allocator);
+        let gizmos = gizmos::Gizmos::new();
+        let picking = picking::Picking::new(&allocator);
+        let selection = selection::Selection::new(&allocator);
+        let transparency = transparency::Transparency::new(&device_manager.device);
+        let deferred = deferred::Deferred::new(&device_manager.device);
+        let ssao = ssao::Ssao::new(&device_manager.device, &allocator);
+        let environment = environment::Environment::new(&device_manager.device, &allocator);
+
+        let post_processing =
+            post_processing::PostProcessing::new(&device_manager.device, &allocator, extent);
+        let display_mode = DisplayMode::from_color_space(device_manager.surface_format.color_space);
+        let text_renderer = text::TextRenderer::new(&device_manager.device, &allocator);
+        let ui_overlay = ui_overlay::UiOverlay::new(&device_manager.device, &allocator, window);
+
+        let render_state = render_state::RenderState {
+            samples: swapchain_manager.samples,
+            ..render_state::RenderState::opaque()
+        };
+
+        let mut register = register::Register::new();
+        register.register_light(Light::directional(
+            glam::Vec3::new(-0.3, -1.0, -0.4),
+            glam::Vec3::ONE,
+            3.0,
+        ));
+
         Self {
             entry: ManuallyDrop::new(entry),
             instance,
@@ -127,8 +285,33 @@ impl NoEngine<'_> {
             rendering_info,
             allocator,
             asset_manager,
-            register: register::Register::new(),
-            frame_count: Default::default(),
+            register,
+            camera,
+            frame_data_buffer,
+            compute_queue: Default::default(),
+            uninitialized_storage_images: Default::default(),
+            light_manager,
+            instance_manager,
+            shadow_manager,
+            debug_views,
+            debug_draw,
+            gizmos,
+            picking,
+            selection,
+            transparency,
+            deferred,
+            ssao,
+            environment,
+            config,
+            transient_pool: render_graph::TransientPool::new(),
+            render_state,
+            msaa: Default::default(),
+            post_processing,
+            display_mode,
+            text_renderer,
+            ui_overlay,
+            last_frame_instant: Instant::now(),
+            frame_time: Default::default(),
         }
     }
 
@@ -158,6 +341,21 @@ impl NoEngine<'_> {
                 .to_vec();
         required_extensions.push(ash::extensions::ext::DebugUtils::NAME.as_ptr());
 
+        // HDR color spaces are only exposed by surfaces when this extension is enabled.
+        let does_support_colorspace = unsafe {
+            entry
+                .enumerate_instance_extension_properties(None)
+                .unwrap()
+                .iter()
+                .any(|extension| {
+                    std::ffi::CStr::from_ptr(extension.extension_name.as_ptr())
+                        == vk::ExtSwapchainColorspaceFn::NAME
+                })
+        };
+        if does_support_colorspace {
+            required_extensions.push(vk::ExtSwapchainColorspaceFn::NAME.as_ptr());
+        }
+
         let instance_info = vk::InstanceCreateInfo::default()
             .application_info(&application_info)
             .enabled_layer_names(&required_validation_layers)
@@ -176,6 +374,533 @@ impl NoEngine<'_> {
         self.asset_manager.load_file(path_buf);
     }
 
+    #[inline(always)]
+    pub fn set_mesh_optimization(&mut self, is_enabled: bool) {
+        self.asset_manager.set_mesh_optimization(is_enabled);
+    }
+
+    #[inline(always)]
+    pub fn set_vertex_format(&mut self, vertex_format: VertexFormat) {
+        self.asset_manager.set_vertex_format(vertex_format);
+    }
+
+    #[inline(always)]
+    pub fn add_light(&mut self, light: Light) -> Id {
+        self.register.register_light(light)
+    }
+
+    #[inline(always)]
+    pub fn remove_light(&mut self, id: Id) -> Option<Light> {
+        self.register.unregister_light(id)
+    }
+
+    #[inline(always)]
+    pub fn light_mut(&mut self, id: Id) -> Option<&mut Light> {
+        self.register.get_light_mut(id)
+    }
+
+    /// Compiles every `.glsl` shader in the folder, named after the file like the engine's own.
+    /// Panics on GLSL that doesn't compile.
+    pub fn compile_shaders_from_folder(&mut self, folder_path: &str) {
+        self.shader_manager.compile_shaders_from_folder(folder_path);
+        self.shader_manager.upload_required_shaders();
+    }
+
+    /// Recorded at the start of the next frame, before any engine work, which sees its results.
+    /// The compute shader must already be compiled, see `compile_shaders_from_folder`.
+    pub fn dispatch_compute(
+        &mut self,
+        dispatch: ComputeDispatch,
+    ) -> Result<(), UnknownComputeShader> {
+        if self
+            .shader_manager
+            .get_shader(dispatch.shader_name(), vk::ShaderStageFlags::COMPUTE)
+            .is_none()
+        {
+            return Err(UnknownComputeShader {
+                name: dispatch.shader_name().to_owned(),
+            });
+        }
+
+        self.compute_queue.push(dispatch);
+
+        Ok(())
+    }
+
+    /// Host visible, filled with `write_storage_buffer` and bound through `DescriptorWriter`.
+    #[inline(always)]
+    pub fn create_storage_buffer(&self, size: u64) -> AllocatedBuffer {
+        self.allocator.allocate_uninit_buffer(
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            ObjectType::Storage,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        )
+    }
+
+    /// Host visible and cached, for dispatches whose results are read back with
+    /// `read_storage_buffer`.
+    #[inline(always)]
+    pub fn create_readback_storage_buffer(&self, size: u64) -> AllocatedBuffer {
+        self.allocator.allocate_uninit_buffer(
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            ObjectType::Storage,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+        )
+    }
+
+    #[inline(always)]
+    pub fn write_storage_buffer<T>(&self, storage_buffer: &AllocatedBuffer, data: &[T]) {
+        self.allocator.write_buffer(storage_buffer, data);
+    }
+
+    /// Dispatches queued before a frame are finished once the next `draw` starts, it waits for
+    /// the previous frame.
+    #[inline(always)]
+    pub fn read_storage_buffer<T: Copy>(&self, storage_buffer: &AllocatedBuffer, data: &mut [T]) {
+        self.allocator.read_buffer(storage_buffer, data);
+    }
+
+    /// Waits for the device, the buffer may still be used by a frame in flight.
+    pub fn destroy_storage_buffer(&self, storage_buffer: AllocatedBuffer) {
+        unsafe { self.device_manager.device.device_wait_idle().unwrap() };
+        self.allocator.destroy_buffer(&storage_buffer);
+    }
+
+    /// Bound through `DescriptorWriter::storage_image`, or sampled in the `GENERAL` layout.
+    /// The layout is set at the start of the next frame, before the queued dispatches.
+    pub fn create_storage_image(
+        &mut self,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> ColorTarget {
+        let storage_image = swapchain::SwapchainManager::create_color_target(
+            &self.device_manager.device,
+            &self.allocator,
+            format,
+            extent,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+        );
+        self.uninitialized_storage_images
+            .push(storage_image.allocated_image.image);
+
+        storage_image
+    }
+
+    /// Waits for the device, the image may still be used by a frame in flight.
+    pub fn destroy_storage_image(&mut self, storage_image: ColorTarget) {
+        unsafe { self.device_manager.device.device_wait_idle().unwrap() };
+        self.uninitialized_storage_images
+            .retain(|&image| image != storage_image.allocated_image.image);
+        storage_image.destroy(&self.device_manager.device, &self.allocator);
+    }
+
+    /// Ids of the uploaded meshes, in upload order.
+    #[inline(always)]
+    pub fn mesh_ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.register.get_meshes().iter().map(|mesh| mesh.id)
+    }
+
+    /// Instances are drawn once their mesh is uploaded, a loaded file gets one at the origin.
+    #[inline(always)]
+    pub fn add_instance(&mut self, mesh_id: Id, transform: glam::Mat4) -> Id {
+        self.register
+            .register_instance(MeshInstance::new(mesh_id, transform))
+    }
+
+    /// All instances of a mesh are drawn with a single instanced call.
+    pub fn add_instances(
+        &mut self,
+        mesh_id: Id,
+        transforms: impl IntoIterator<Item = glam::Mat4>,
+    ) -> Vec<Id> {
+        self.register.register_instances(
+            transforms
+                .into_iter()
+                .map(|transform| MeshInstance::new(mesh_id, transform)),
+        )
+    }
+
+    #[inline(always)]
+    pub fn remove_instance(&mut self, id: Id) -> Option<MeshInstance> {
+        self.register.unregister_instance(id)
+    }
+
+    #[inline(always)]
+    pub fn instance_mut(&mut self, id: Id) -> Option<&mut MeshInstance> {
+        self.register.get_instance_mut(id)
+    }
+
+    /// Counts of the last drawn frame.
+    #[inline(always)]
+    pub fn culling_stats(&self) -> CullingStats {
+        self.instance_manager.stats()
+    }
+
+    /// Culls instances against the camera in a compute pass and draws them indirectly. Stays
+    /// off on devices without `drawIndirectCount`.
+    #[inline(always)]
+    pub fn set_gpu_driven(&mut self, is_gpu_driven: bool) {
+        self.instance_manager.is_gpu_driven =
+            is_gpu_driven && self.device_manager.features.draw_indirect_count;
+    }
+
+    #[inline(always)]
+    pub fn toggle_gpu_driven(&mut self) {
+        self.set_gpu_driven(!self.instance_manager.is_gpu_driven);
+    }
+
+    /// Requests above what the device supports are clamped. Kept but not applied while the
+    /// render path is deferred.
+    pub fn set_msaa(&mut self, msaa: Msaa) {
+        unsafe { self.device_manager.device.device_wait_idle().unwrap() };
+
+        self.msaa = msaa;
+        self.recreate_scene_targets();
+    }
+
+    #[inline(always)]
+    pub fn cycle_msaa(&mut self) {
+        self.set_msaa(self.msaa.next());
+    }
+
+    /// Opaque instances are shaded while drawn or from a G-buffer. Only the lit view is
+    /// affected, debug views always draw forward.
+    pub fn set_render_path(&mut self, path: RenderPath) {
+        unsafe { self.device_manager.device.device_wait_idle().unwrap() };
+
+        self.deferred.set_path(path);
+        self.recreate_scene_targets();
+    }
+
+    #[inline(always)]
+    pub fn render_path(&self) -> RenderPath {
+        self.deferred.path()
+    }
+
+    #[inline(always)]
+    pub fn cycle_render_path(&mut self) {
+        self.set_render_path(self.deferred.path().next());
+    }
+
+    /// Scales the skybox and the ambient light of an environment loaded from a `.hdr` file.
+    #[inline(always)]
+    pub fn set_environment_intensity(&mut self, intensity: f32) {
+        self.environment.intensity = intensity.max(0.0);
+    }
+
+    #[inline(always)]
+    pub fn environment_intensity(&self) -> f32 {
+        self.environment.intensity
+    }
+
+    /// Only the lit view draws gradients and skyboxes, the debug views show the clear color.
+    /// Kept in the config, the error is from saving it.
+    pub fn set_background(&mut self, background: Background) -> std::io::Result<()> {
+        self.config.background = background;
+        self.config.save(Config::PATH)
+    }
+
+    #[inline(always)]
+    pub fn background(&self) -> Background {
+        self.config.background
+    }
+
+    #[inline(always)]
+    pub fn cycle_background(&mut self) -> std::io::Result<()> {
+        self.set_background(self.config.background.next())
+    }
+
+    /// Darkens the ambient light of the lit view where geometry is close together.
+    #[inline(always)]
+    pub fn set_ssao(&mut self, is_enabled: bool) {
+        self.ssao.is_enabled = is_enabled;
+    }
+
+    #[inline(always)]
+    pub fn toggle_ssao(&mut self) {
+        self.ssao.is_enabled = !self.ssao.is_enabled;
+    }
+
+    #[inline(always)]
+    pub fn set_ssao_settings(&mut self, settings: SsaoSettings) {
+        self.ssao.settings = settings;
+    }
+
+    #[inline(always)]
+    pub fn ssao_settings(&self) -> SsaoSettings {
+        self.ssao.settings
+    }
+
+    /// The G-buffer is single sampled, so the deferred path renders the scene without MSAA.
+    /// The device must be idle.
+    fn recreate_scene_targets(&mut self) {
+        let samples = if self.deferred.is_enabled() {
+            vk::SampleCountFlags::TYPE_1
+        } else {
+            self.msaa.sample_count()
+        };
+        self.swapchain_manager.recreate_render_targets(
+            &self.device_manager,
+            &self.allocator,
+            samples,
+        );
+        self.render_state.samples = self.swapchain_manager.samples;
+        self.picking.recreate_targets(
+            &self.device_manager.device,
+            &self.allocator,
+            self.swapchain_manager.extent,
+            self.swapchain_manager.samples,
+        );
+    }
+
+    #[inline(always)]
+    pub fn set_tonemapper(&mut self, tonemapper: Tonemapper) {
+        self.post_processing.tonemap_pass.tonemapper = tonemapper;
+    }
+
+    #[inline(always)]
+    pub fn cycle_tonemapper(&mut self) {
+        self.post_processing.tonemap_pass.tonemapper =
+            self.post_processing.tonemap_pass.tonemapper.next();
+    }
+
+    #[inline(always)]
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.post_processing.tonemap_pass.exposure = exposure.max(Default::default());
+    }
+
+    #[inline(always)]
+    pub fn exposure(&self) -> f32 {
+        self.post_processing.tonemap_pass.exposure
+    }
+
+    #[inline(always)]
+    pub fn post_effects(&self) -> &[PostEffect] {
+        self.post_processing.effects()
+    }
+
+    #[inline(always)]
+    pub fn post_effect_mut(&mut self, kind: PostEffectKind) -> Option<&mut PostEffect> {
+        self.post_processing.effect_mut(kind)
+    }
+
+    #[inline(always)]
+    pub fn toggle_post_effect(&mut self, kind: PostEffectKind) {
+        self.post_processing.toggle(kind);
+    }
+
+    /// HDR effects like bloom always run before tonemapping, the order only affects the rest.
+    #[inline(always)]
+    pub fn move_post_effect(&mut self, kind: PostEffectKind, index: usize) {
+        self.post_processing.move_effect(kind, index);
+    }
+
+    #[inline(always)]
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    #[inline(always)]
+    pub fn toggle_cascades_debug(&mut self) {
+        self.shadow_manager.toggle_debug();
+    }
+
+    /// The wireframe view is ignored on devices without `fillModeNonSolid`.
+    #[inline(always)]
+    pub fn set_debug_view(&mut self, debug_view: DebugView) {
+        if debug_view != DebugView::Wireframe || self.device_manager.features.fill_mode_non_solid {
+            self.debug_views.view = debug_view;
+        }
+    }
+
+    #[inline(always)]
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_views.view
+    }
+
+    #[inline(always)]
+    pub fn cycle_debug_view(&mut self) {
+        let debug_view = self.debug_views.view.next();
+        self.debug_views.view = match debug_view {
+            DebugView::Wireframe if !self.device_manager.features.fill_mode_non_solid => {
+                debug_view.next()
+            }
+            _ => debug_view,
+        };
+    }
+
+    #[inline(always)]
+    pub fn toggle_normals_debug(&mut self) {
+        self.debug_views.does_show_normals = !self.debug_views.does_show_normals;
+    }
+
+    #[inline(always)]
+    pub fn toggle_bounds_debug(&mut self) {
+        self.debug_views.does_show_bounds = !self.debug_views.does_show_bounds;
+    }
+
+    #[inline(always)]
+    pub fn toggle_grid(&mut self) {
+        self.gizmos.does_show_grid = !self.gizmos.does_show_grid;
+    }
+
+    #[inline(always)]
+    pub fn set_grid_cell_size(&mut self, cell_size: f32) {
+        self.gizmos.grid_cell_size = cell_size.max(f32::EPSILON);
+    }
+
+    #[inline(always)]
+    pub fn toggle_axis_gizmo(&mut self) {
+        self.gizmos.does_show_axis = !self.gizmos.does_show_axis;
+    }
+
+    /// Writes instance ids next to the scene color so `pick` can read them.
+    pub fn set_picking(&mut self, is_enabled: bool) {
+        unsafe { self.device_manager.device.device_wait_idle().unwrap() };
+
+        self.picking.set_enabled(
+            &self.device_manager.device,
+            &self.allocator,
+            is_enabled,
+            self.swapchain_manager.extent,
+            self.swapchain_manager.samples,
+        );
+        self.render_state.color_attachments_count = 1 + is_enabled as u32;
+    }
+
+    /// Instance drawn at pixel `x`, `y` in the last frame, `None` for the background or
+    /// while picking is off. Waits for the GPU, so it's meant for clicks.
+    pub fn pick(&mut self, x: u32, y: u32) -> Option<Id> {
+        let extent = self.swapchain_manager.extent;
+        if x >= extent.width || y >= extent.height {
+            return None;
+        }
+
+        let device = &self.device_manager.device;
+        unsafe {
+            device
+                .wait_for_fences(&self.render_fence, true, u64::MAX)
+                .unwrap()
+        };
+
+        // Frames only record into the first command buffer, the second one is free here.
+        let texel = self.picking.read(
+            device,
+            &self.allocator,
+            self.command_manager.command_buffers[1],
+            self.device_manager.graphics_queue,
+            x,
+            y,
+        )?;
+        let instance_id = self.instance_manager.instance_id(texel.checked_sub(1)?)?;
+
+        self.register
+            .get_instances()
+            .iter()
+            .any(|instance| instance.id == instance_id)
+            .then_some(instance_id)
+    }
+
+    /// Transparent materials are sorted back to front or blended order independently.
+    /// Stays sorted on devices without `independentBlend`.
+    pub fn set_transparency_mode(&mut self, mode: TransparencyMode) {
+        if mode == TransparencyMode::WeightedBlended
+            && !self.device_manager.features.independent_blend
+        {
+            return;
+        }
+
+        self.transparency.set_mode(mode);
+    }
+
+    #[inline(always)]
+    pub fn transparency_mode(&self) -> TransparencyMode {
+        self.transparency.mode()
+    }
+
+    #[inline(always)]
+    pub fn cycle_transparency_mode(&mut self) {
+        self.set_transparency_mode(self.transparency.mode().next());
+    }
+
+    /// Selected instances get an outline while picking and the `SelectionOutline` effect
+    /// are enabled.
+    #[inline(always)]
+    pub fn select(&mut self, id: Id) {
+        self.selection.select(id);
+    }
+
+    #[inline(always)]
+    pub fn deselect(&mut self, id: Id) {
+        self.selection.deselect(id);
+    }
+
+    #[inline(always)]
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+    }
+
+    #[inline(always)]
+    pub fn selection(&self) -> &[Id] {
+        self.selection.selected()
+    }
+
+    /// `color` is display encoded, `thickness` is in pixels.
+    pub fn set_selection_outline(&mut self, color: glam::Vec3, thickness: f32) {
+        if let Some(outline) = self
+            .post_processing
+            .effect_mut(PostEffectKind::SelectionOutline)
+        {
+            outline.parameters = color.extend(thickness.max(Default::default()));
+        }
+    }
+
+    /// Shapes added here are drawn by the next `draw` only.
+    #[inline(always)]
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// `position` and `size` are in pixels, the text is drawn by the next `draw` only.
+    #[inline(always)]
+    pub fn draw_text(&mut self, position: glam::Vec2, size: f32, color: glam::Vec4, text: &str) {
+        self.text_renderer.draw_text(position, size, color, text);
+    }
+
+    /// Returns whether the overlay consumed the event, the application should ignore it then.
+    #[inline(always)]
+    pub fn handle_window_event(&mut self, event: &winit::event::WindowEvent) -> bool {
+        self.ui_overlay.handle_window_event(event)
+    }
+
+    /// Runs the overlay panels, called once per frame before `draw`.
+    pub fn update_ui(&mut self, window: &winit::window::Window) {
+        let stats = ui_overlay::UiStats {
+            frame_time: self.frame_time,
+            culling: self.instance_manager.stats(),
+        };
+        self.ui_overlay.update(
+            window,
+            &stats,
+            self.register.get_meshes(),
+            self.shader_manager.get_shaders(),
+        );
+    }
+
+    #[inline(always)]
+    pub fn toggle_ui(&mut self) {
+        self.ui_overlay.is_visible = !self.ui_overlay.is_visible;
+    }
+
+    /// Time between the starts of the last two drawn frames.
+    #[inline(always)]
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
     #[inline(always)]
     fn check_upload_queue(&mut self) {
         let assets_to_upload = self.asset_manager.get_assets_to_upload();
@@ -185,13 +910,27 @@ impl NoEngine<'_> {
                 ObjectsQueue::Mesh(mesh) => {
                     let mesh = self.asset_manager.get_mesh(*mesh);
                     let allocated_mesh = self.allocator.upload_mesh(mesh);
+                    self.register.register_instance(MeshInstance::new(
+                        allocated_mesh.id,
+                        glam::Mat4::IDENTITY,
+                    ));
                     self.register.register_mesh(allocated_mesh);
                 }
+                ObjectsQueue::Environment(image) => {
+                    if let Some(image) = self.asset_manager.take_environment(*image) {
+                        self.environment
+                            .load(&self.device_manager.device, &self.allocator, image);
+                    }
+                }
             });
     }
 
     #[inline(always)]
     pub fn draw(&mut self) {
+        let now = Instant::now();
+        self.frame_time = now - self.last_frame_instant;
+        self.last_frame_instant = now;
+
         let device = &self.device_manager.device;
         let fences = &self.render_fence;
         unsafe {
@@ -237,6 +976,74 @@ impl NoEngine<'_> {
                 .unwrap_unchecked();
         };
 
+        let extent = self.swapchain_manager.extent;
+        let lights_count = self
+            .light_manager
+            .pack(&self.allocator, self.register.get_lights());
+        let tiles_count = self.light_manager.tiles_count();
+        let environment_info = self.environment.light_info();
+        let frame_data = FrameData::new(
+            &self.camera,
+            glam::UVec4::new(
+                extent.width,
+                extent.height,
+                tiles_count.width,
+                tiles_count.height,
+            ),
+            glam::UVec4::new(
+                lights_count,
+                environment_info.x,
+                environment_info.y,
+                environment_info.z,
+            ),
+        );
+        self.allocator
+            .write_buffer(&self.frame_data_buffer, std::slice::from_ref(&frame_data));
+
+        if !self.uninitialized_storage_images.is_empty() {
+            let image_barriers = self
+                .uninitialized_storage_images
+                .drain(..)
+                .map(|image| {
+                    vk::ImageMemoryBarrier2::default()
+                        .src_stage_mask(vk::PipelineStageFlags2::TOP_OF_PIPE)
+                        .dst_stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)
+                        .dst_access_mask(
+                            vk::AccessFlags2::SHADER_STORAGE_READ
+                                | vk::AccessFlags2::SHADER_STORAGE_WRITE
+                                | vk::AccessFlags2::SHADER_SAMPLED_READ,
+                        )
+                        .old_layout(vk::ImageLayout::UNDEFINED)
+                        .new_layout(vk::ImageLayout::GENERAL)
+                        .image(image)
+                        .subresource_range(
+                            vk::ImageSubresourceRange::default()
+                                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                                .level_count(1)
+                                .layer_count(1),
+                        )
+                })
+                .collect::<Vec<_>>();
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+            unsafe { device.cmd_pipeline_barrier2(command_buffer, &dependency_info) };
+        }
+
+        if !self.compute_queue.is_empty() {
+            self.compute_queue
+                .iter()
+                .for_each(|dispatch| dispatch.record(device, &self.shader_manager, command_buffer));
+            self.compute_queue.clear();
+            ComputeBarrier::COMPUTE_TO_ALL.record(device, command_buffer);
+        }
+
+        self.environment.update(
+            device,
+            &self.allocator,
+            &self.shader_manager,
+            command_buffer,
+        );
+
         let image = unsafe {
             *self
                 .swapchain_manager
@@ -249,89 +1056,266 @@ impl NoEngine<'_> {
                 .image_views
                 .get_unchecked::<usize>(next_image_index as _)
         };
-        let queue_family_index = self.device_manager.queue_family_index;
-
-        let color_barrier = vk::ImageMemoryBarrier2 {
-            src_stage_mask: vk::PipelineStageFlags2::NONE_KHR,
-            src_access_mask: vk::AccessFlags2KHR::NONE_KHR,
-            dst_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT_KHR,
-            dst_access_mask: vk::AccessFlags2KHR::COLOR_ATTACHMENT_WRITE_KHR,
-            old_layout: vk::ImageLayout::UNDEFINED,
-            new_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            src_queue_family_index: queue_family_index,
-            dst_queue_family_index: queue_family_index,
-            image,
-            subresource_range: vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                level_count: 1,
-                layer_count: 1,
-                base_array_layer: Default::default(),
-                base_mip_level: Default::default(),
-            },
-            ..Default::default()
-        };
-        let depth_barrier = vk::ImageMemoryBarrier2 {
-            src_stage_mask: vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
-                | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
-            src_access_mask: vk::AccessFlags2KHR::NONE_KHR,
-            dst_stage_mask: vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
-                | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
-            dst_access_mask: vk::AccessFlags2KHR::DEPTH_STENCIL_ATTACHMENT_WRITE,
-            old_layout: vk::ImageLayout::UNDEFINED,
-            new_layout: vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
-            src_queue_family_index: queue_family_index,
-            dst_queue_family_index: queue_family_index,
-            image: self.swapchain_manager.depth.allocated_image.image,
-            subresource_range: vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::DEPTH,
-                level_count: 1,
-                layer_count: 1,
-                base_array_layer: Default::default(),
-                base_mip_level: Default::default(),
-            },
-            ..Default::default()
+
+        // The deferred path fills the depth, ids and opaque color before the scene pass, which
+        // adds everything that isn't shaded from the G-buffer on top.
+        let is_deferred = self.deferred.is_enabled() && self.debug_views.view == DebugView::Lit;
+        let is_ssao = self.ssao.is_enabled && self.debug_views.view == DebugView::Lit;
+
+        // Debug views shade transparent instances in the scene pass like the sorted mode does.
+        let transparency_mode = if self.debug_views.view == DebugView::Lit {
+            self.transparency.mode()
+        } else {
+            TransparencyMode::Sorted
         };
-        let output_barrier = vk::ImageMemoryBarrier2 {
-            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT_KHR,
-            src_access_mask: vk::AccessFlags2KHR::COLOR_ATTACHMENT_WRITE_KHR,
-            dst_stage_mask: vk::PipelineStageFlags2::NONE_KHR,
-            dst_access_mask: vk::AccessFlags2KHR::NONE_KHR,
-            old_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            new_layout: vk::ImageLayout::PRESENT_SRC_KHR,
-            src_queue_family_index: queue_family_index,
-            dst_queue_family_index: queue_family_index,
+        self.instance_manager.pack(
+            &self.allocator,
+            &self.register,
+            &self.camera,
+            transparency_mode,
+        );
+        self.shadow_manager
+            .update(&self.allocator, &self.camera, self.register.get_lights());
+        let outline_thickness = self
+            .post_processing
+            .effect(PostEffectKind::SelectionOutline)
+            .filter(|outline| outline.is_enabled)
+            .map(|outline| outline.parameters.w)
+            .filter(|_| self.selection.pack(&self.allocator, &self.instance_manager));
+
+        // Barriers between the passes of the frame come from the graph, the modules still
+        // synchronize what they record within a pass.
+        let mut graph = RenderGraph::new();
+        let swapchain_image = graph.import_image(
+            "swapchain",
             image,
-            subresource_range: vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                level_count: 1,
-                layer_count: 1,
-                base_mip_level: Default::default(),
-                base_array_layer: Default::default(),
-            },
-            ..Default::default()
-        };
-        let image_barriers = [color_barrier, depth_barrier, output_barrier];
-        let dependency_info =
-            vk::DependencyInfoKHR::default().image_memory_barriers(&image_barriers);
-        unsafe { device.cmd_pipeline_barrier2(command_buffer, &dependency_info) };
+            image_view,
+            vk::ImageAspectFlags::COLOR,
+            ImageAccess::UNDEFINED,
+            Some(ImageAccess::PRESENT),
+        );
+        let hdr = graph.import_image(
+            "hdr",
+            self.swapchain_manager.hdr_color.allocated_image.image,
+            self.swapchain_manager.hdr_color.image_view,
+            vk::ImageAspectFlags::COLOR,
+            ImageAccess::UNDEFINED,
+            None,
+        );
+        let depth = graph.import_image(
+            "depth",
+            self.swapchain_manager.depth.allocated_image.image,
+            self.swapchain_manager.depth.image_view,
+            vk::ImageAspectFlags::DEPTH,
+            ImageAccess::UNDEFINED,
+            None,
+        );
+        let msaa_color = self
+            .swapchain_manager
+            .msaa_color
+            .as_ref()
+            .map(|msaa_color| {
+                graph.import_image(
+                    "msaa_color",
+                    msaa_color.allocated_image.image,
+                    msaa_color.image_view,
+                    vk::ImageAspectFlags::COLOR,
+                    ImageAccess::UNDEFINED,
+                    None,
+                )
+            });
+        // Left as attachments after the frame, which is where picking reads them back from.
+        let ids = self
+            .picking
+            .targets()
+            .map(|target| {
+                graph.import_image(
+                    "ids",
+                    target.allocated_image.image,
+                    target.image_view,
+                    vk::ImageAspectFlags::COLOR,
+                    ImageAccess::UNDEFINED,
+                    Some(ImageAccess::COLOR_ATTACHMENT_READ_WRITE),
+                )
+            })
+            .collect::<ArrayVec<_, 2>>();
+        let tile_lights =
+            graph.import_buffer("tile_lights", self.light_manager.tile_light_buffer().buffer);
 
-        let clear_values = unsafe {
-            self.rendering_info
-                .clear_values
-                .color
-                .float32
-                .get_unchecked_mut(2)
-        };
-        *clear_values = f32::abs(self.frame_count as f32 / 100.0 % 1.0 - 0.5) * 2.0;
+        let light_culling_pass = graph
+            .add_pass("light_culling")
+            .buffer(tile_lights, BufferAccess::COMPUTE_STORAGE_WRITE)
+            .build();
+        let instance_culling = self.instance_manager.add_culling_pass(&mut graph);
+        let draw_buffers = instance_culling
+            .as_ref()
+            .map_or(
+                &[][..],
+                |instance_culling| &instance_culling.draw_buffers[..],
+            );
+        let shadow_pass = self.shadow_manager.add_pass(&mut graph);
+        let shadow_map = shadow_pass.shadow_map;
+        let gbuffer = is_deferred.then(|| {
+            deferred::Deferred::add_gbuffer_pass(
+                &mut graph,
+                extent,
+                depth,
+                ids.first().copied(),
+                draw_buffers,
+            )
+        });
+        let ssao_passes = is_ssao.then(|| {
+            ssao::Ssao::add_passes(
+                &mut graph,
+                extent,
+                gbuffer.as_ref().map(|gbuffer| (depth, gbuffer.normal)),
+                draw_buffers,
+            )
+        });
+        let ambient_occlusion = ssao_passes
+            .as_ref()
+            .map(|ssao_passes| ssao_passes.ambient_occlusion);
+        let deferred_passes = gbuffer.map(|gbuffer| {
+            deferred::Deferred::add_lighting_pass(
+                &mut graph,
+                gbuffer,
+                depth,
+                hdr,
+                shadow_map,
+                tile_lights,
+                ambient_occlusion,
+            )
+        });
+        let mut scene_pass = graph
+            .add_pass("scene")
+            .image(hdr, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .image(depth, ImageAccess::DEPTH_ATTACHMENT)
+            .image(shadow_map, ImageAccess::DEPTH_SAMPLED)
+            .buffer(tile_lights, BufferAccess::FRAGMENT_STORAGE_READ)
+            .buffers(draw_buffers, BufferAccess::INDIRECT_READ);
+        if let Some(msaa_color) = msaa_color {
+            scene_pass = scene_pass.image(msaa_color, ImageAccess::COLOR_ATTACHMENT_WRITE);
+        }
+        for &ids in &ids {
+            scene_pass = scene_pass.image(ids, ImageAccess::COLOR_ATTACHMENT_READ_WRITE);
+        }
+        if let Some(ambient_occlusion) = ambient_occlusion {
+            scene_pass = scene_pass.image(ambient_occlusion, ImageAccess::FRAGMENT_SAMPLED);
+        }
+        let scene_pass = scene_pass.build();
+        let weighted_passes = (transparency_mode == TransparencyMode::WeightedBlended).then(|| {
+            transparency::Transparency::add_weighted_passes(
+                &mut graph,
+                extent,
+                self.swapchain_manager.samples,
+                hdr,
+                depth,
+                shadow_map,
+                tile_lights,
+                draw_buffers,
+            )
+        });
+        let selection_pass = ids
+            .first()
+            .copied()
+            .zip(outline_thickness)
+            .map(|(ids, thickness)| {
+                selection::Selection::add_pass(&mut graph, extent, ids, thickness)
+            });
+        let mut post_pass = graph
+            .add_pass("post")
+            .image(hdr, ImageAccess::SHADER_SAMPLED)
+            .image(swapchain_image, ImageAccess::COLOR_ATTACHMENT_WRITE);
+        if let Some(selection_pass) = &selection_pass {
+            post_pass = post_pass.image(selection_pass.outline, ImageAccess::FRAGMENT_STORAGE_READ);
+        }
+        let post_pass = post_pass.build();
+        let text_pass = graph
+            .add_pass("text")
+            .image(swapchain_image, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .build();
+        let ui_pass = graph
+            .add_pass("ui")
+            .image(swapchain_image, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .build();
+
+        let graph = graph.compile();
+        self.transient_pool.prepare(device, &self.allocator, &graph);
+
+        graph.begin_pass(
+            device,
+            command_buffer,
+            light_culling_pass,
+            &self.transient_pool,
+        );
+        self.light_manager.cull(
+            device,
+            &self.shader_manager,
+            command_buffer,
+            &self.frame_data_buffer,
+        );
 
+        self.instance_manager.cull(
+            device,
+            &self.shader_manager,
+            command_buffer,
+            &graph,
+            &self.transient_pool,
+            instance_culling.as_ref(),
+        );
+        self.shadow_manager.render(
+            device,
+            &self.shader_manager,
+            command_buffer,
+            &self.instance_manager,
+            &self.register,
+            &graph,
+            &self.transient_pool,
+            &shadow_pass,
+        );
+
+        self.rendering_info.clear_values = self.config.background.clear_value();
+
+        let scene_load_op = if is_deferred {
+            vk::AttachmentLoadOp::LOAD
+        } else {
+            vk::AttachmentLoadOp::CLEAR
+        };
         let color_attachment = unsafe {
             self.rendering_info
                 .color_attachments
                 .get_unchecked_mut::<usize>(Default::default())
         };
-        *color_attachment = color_attachment
-            .clear_value(self.rendering_info.clear_values)
-            .image_view(image_view);
+
+        // With MSAA the scene goes into the multisampled target and is resolved into the HDR one.
+        let hdr_view = self.swapchain_manager.hdr_color.image_view;
+        *color_attachment = match &self.swapchain_manager.msaa_color {
+            Some(msaa_color) => color_attachment
+                .image_view(msaa_color.image_view)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                .resolve_image_view(hdr_view)
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            None => color_attachment
+                .image_view(hdr_view)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .resolve_mode(vk::ResolveModeFlags::NONE)
+                .resolve_image_view(Default::default()),
+        }
+        .load_op(scene_load_op)
+        .clear_value(self.rendering_info.clear_values);
+        let id_attachment = self.picking.color_attachment();
+        self.rendering_info.color_attachments.truncate(1);
+        if let Some(id_attachment) = id_attachment {
+            self.rendering_info
+                .color_attachments
+                .push(id_attachment.load_op(scene_load_op));
+        }
+        self.rendering_info.depth_attachment = self
+            .rendering_info
+            .depth_attachment
+            .image_view(self.swapchain_manager.depth.image_view)
+            .load_op(scene_load_op);
 
         let rendering_info = vk::RenderingInfoKHR::default()
             .color_attachments(&self.rendering_info.color_attachments)
@@ -342,37 +1326,221 @@ impl NoEngine<'_> {
             })
             .layer_count(1);
 
+        let descriptor_writer = self.environment.write_descriptors(
+            self.instance_manager
+                .write_descriptors(self.shadow_manager.write_descriptors(
+                    self.light_manager.write_descriptors(
+                        DescriptorWriter::new().uniform_buffer(&self.frame_data_buffer),
+                    ),
+                )),
+        );
+        let descriptor_writer = self.ssao.write_descriptors(
+            device,
+            command_buffer,
+            &graph,
+            &self.transient_pool,
+            ssao_passes.as_ref(),
+            descriptor_writer,
+        );
+
+        if let Some(deferred_passes) = &deferred_passes {
+            self.deferred.render_gbuffer(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                &self.render_state,
+                extent,
+                &self.instance_manager,
+                &self.register,
+                &descriptor_writer,
+                &graph,
+                &self.transient_pool,
+                deferred_passes,
+                id_attachment,
+                self.rendering_info.clear_values,
+                self.swapchain_manager.depth.image_view,
+            );
+        }
+        if let Some(ssao_passes) = &ssao_passes {
+            self.ssao.render(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                extent,
+                &self.instance_manager,
+                &self.register,
+                &descriptor_writer,
+                &graph,
+                &self.transient_pool,
+                ssao_passes,
+            );
+        }
+        if let Some(deferred_passes) = &deferred_passes {
+            self.deferred.render_lighting(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                extent,
+                &descriptor_writer,
+                &graph,
+                &self.transient_pool,
+                deferred_passes,
+                hdr_view,
+                self.swapchain_manager.depth.image_view,
+            );
+        }
+
+        graph.begin_pass(device, command_buffer, scene_pass, &self.transient_pool);
         unsafe {
             device.cmd_begin_rendering(command_buffer, &rendering_info);
 
-            self.register.get_meshes().iter().for_each(|mesh| {
-                device.cmd_bind_vertex_buffers(
+            self.shader_manager.push_descriptors(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                &descriptor_writer,
+            );
+            if self.debug_views.view == DebugView::Lit {
+                self.config.background.render(
+                    device,
+                    &self.shader_manager,
                     command_buffer,
-                    Default::default(),
-                    self.register.get_buffers(),
-                    self.register.get_offsets(),
+                    &self.render_state,
+                    extent,
+                    &self.environment,
                 );
-                device.cmd_bind_index_buffer(
+            }
+            self.debug_views.bind_scene(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                &self.render_state,
+                extent,
+                Self::MESH_SHADER_NAME,
+            );
+
+            if !is_deferred {
+                self.instance_manager.draw_visible(
+                    device,
+                    &self.shader_manager,
                     command_buffer,
-                    mesh.index_buffer.buffer,
-                    Default::default(),
-                    vk::IndexType::UINT32,
+                    &self.register,
                 );
-
-                let metadata = mesh.metadata;
-                device.cmd_draw_indexed(
+            }
+            if self.debug_views.view == DebugView::Lit {
+                self.transparency.render_sorted(
+                    device,
+                    &self.shader_manager,
                     command_buffer,
-                    metadata.indices_count,
-                    1,
-                    Default::default(),
-                    Default::default(),
-                    Default::default(),
+                    &self.render_state,
+                    extent,
+                    &self.instance_manager,
+                    &self.register,
                 );
-            });
+            } else {
+                self.instance_manager.draw_visible_transparent(
+                    device,
+                    &self.shader_manager,
+                    command_buffer,
+                    &self.register,
+                    |_| (),
+                );
+            }
+            self.debug_views.render_overlays(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                &self.render_state,
+                extent,
+                &self.instance_manager,
+                &self.register,
+            );
+            self.debug_draw.render(
+                device,
+                &self.allocator,
+                &self.shader_manager,
+                command_buffer,
+                &self.render_state,
+                extent,
+            );
+            self.gizmos.render(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                &self.render_state,
+                extent,
+            );
 
             device.cmd_end_rendering(command_buffer);
         }
 
+        if let Some(weighted_passes) = &weighted_passes {
+            self.transparency.render_weighted(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                &self.render_state,
+                extent,
+                &self.instance_manager,
+                &self.register,
+                &descriptor_writer,
+                &graph,
+                &self.transient_pool,
+                weighted_passes,
+                hdr_view,
+                self.swapchain_manager.depth.image_view,
+            );
+        }
+
+        if let Some(selection_pass) = &selection_pass {
+            self.selection.render(
+                device,
+                &self.shader_manager,
+                command_buffer,
+                extent,
+                &graph,
+                &self.transient_pool,
+                selection_pass,
+            );
+        }
+        let selection_view = selection_pass.as_ref().and_then(|selection_pass| {
+            graph.image_view(selection_pass.outline, &self.transient_pool)
+        });
+
+        graph.begin_pass(device, command_buffer, post_pass, &self.transient_pool);
+        self.post_processing.render(
+            device,
+            &self.shader_manager,
+            command_buffer,
+            hdr_view,
+            image_view,
+            self.display_mode,
+            selection_view,
+        );
+        graph.begin_pass(device, command_buffer, text_pass, &self.transient_pool);
+        self.text_renderer.render(
+            device,
+            &self.allocator,
+            &self.shader_manager,
+            command_buffer,
+            image_view,
+            extent,
+            self.display_mode,
+            self.post_processing.tonemap_pass.paper_white_nits,
+        );
+        graph.begin_pass(device, command_buffer, ui_pass, &self.transient_pool);
+        self.ui_overlay.render(
+            device,
+            &self.allocator,
+            &self.shader_manager,
+            command_buffer,
+            image_view,
+            extent,
+            self.display_mode,
+            self.post_processing.tonemap_pass.paper_white_nits,
+        );
+
+        graph.finish(device, command_buffer, &self.transient_pool);
+
         unsafe {
             device.end_command_buffer(command_buffer).unwrap_unchecked();
         }
@@ -407,8 +1575,6 @@ impl NoEngine<'_> {
                 .queue_present(self.device_manager.graphics_queue, &present_info)
                 .unwrap_unchecked();
         };
-
-        self.frame_count += 1;
     }
 }
 
@@ -419,8 +1585,26 @@ impl Drop for NoEngine<'_> {
             device.device_wait_idle().unwrap();
 
             self.shader_manager.clear_uploaded_shaders();
+            self.shader_manager.destroy_layouts(device);
+            self.light_manager.destroy(&self.allocator);
+            self.instance_manager.destroy(&self.allocator);
+            self.debug_draw.destroy(&self.allocator);
+            self.picking.destroy(device, &self.allocator);
+            self.selection.destroy(&self.allocator);
+            self.transparency.destroy(device);
+            self.deferred.destroy(device);
+            self.ssao.destroy(device, &self.allocator);
+            self.environment.destroy(device, &self.allocator);
+            self.transient_pool.destroy(device, &self.allocator);
+            self.shadow_manager.destroy(device, &self.allocator);
+            self.post_processing.destroy(device, &self.allocator);
+            self.text_renderer.destroy(device, &self.allocator);
+            self.ui_overlay.destroy(device, &self.allocator);
+            self.allocator.destroy_buffer(&self.frame_data_buffer);
             device.destroy_command_pool(self.command_manager.command_pool, None);
 
+            self.swapchain_manager
+                .destroy_render_targets(device, &self.allocator);
             let swapchain_manager = &self.swapchain_manager;
 
             swapchain_manager
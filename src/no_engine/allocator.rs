@@ -13,6 +13,7 @@ use super::Id;
 use ash::vk;
 
 use super::objects::mesh::Mesh;
+use super::shader::layouts::ShaderLayout;
 
 #[derive(Clone, Copy)]
 pub struct Allocator {
@@ -34,19 +35,37 @@ impl Allocator {
 
     #[inline(always)]
     pub fn upload_mesh(&mut self, mesh: &Mesh) -> AllocatedMesh {
+        let vertex_format = mesh.metadata.vertex_format;
         let vertex_buffer = self.allocate_buffer(
-            &mesh.vertices,
+            &vertex_format.encode(&mesh.vertices, mesh.metadata.dequantization()),
             vk::BufferUsageFlags::VERTEX_BUFFER,
             vk::SharingMode::EXCLUSIVE,
         );
 
-        let index_buffer = self.allocate_buffer(
-            &mesh.indices,
-            vk::BufferUsageFlags::INDEX_BUFFER,
-            vk::SharingMode::EXCLUSIVE,
-        );
+        let index_buffer = match mesh.metadata.index_type() {
+            vk::IndexType::UINT16 => self.allocate_buffer(
+                &mesh
+                    .indices
+                    .iter()
+                    .map(|&index| index as u16)
+                    .collect::<Vec<_>>(),
+                vk::BufferUsageFlags::INDEX_BUFFER,
+                vk::SharingMode::EXCLUSIVE,
+            ),
+            _ => self.allocate_buffer(
+                &mesh.indices,
+                vk::BufferUsageFlags::INDEX_BUFFER,
+                vk::SharingMode::EXCLUSIVE,
+            ),
+        };
 
-        AllocatedMesh::new(mesh.metadata.id, mesh.metadata, vertex_buffer, index_buffer)
+        AllocatedMesh::new(
+            mesh.metadata.id,
+            mesh.metadata,
+            vertex_buffer,
+            index_buffer,
+            ShaderLayout::for_vertex_format(vertex_format),
+        )
     }
 
     #[inline(always)]
@@ -87,6 +106,44 @@ impl Allocator {
         AllocatedImage::new(Id::new(), format, image, allocation)
     }
 
+    /// Six square layers which can be viewed as a cube.
+    #[inline(always)]
+    pub fn allocate_cube_image(
+        &self,
+        format: vk::Format,
+        size: u32,
+        mip_map_levels: u32,
+        usage_flags: vk::ImageUsageFlags,
+    ) -> AllocatedImage {
+        let image_create_info = vk::ImageCreateInfo::default()
+            .flags(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+            .array_layers(6)
+            .mip_levels(mip_map_levels)
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D {
+                width: size,
+                height: size,
+                depth: 1,
+            })
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(usage_flags);
+
+        let allocation_info = vk_mem_alloc::AllocationCreateInfo {
+            usage: vk_mem_alloc::MemoryUsage::AUTO,
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            ..Default::default()
+        };
+
+        let (image, allocation, _) = unsafe {
+            vk_mem_alloc::create_image(self.allocator, &image_create_info, &allocation_info)
+                .unwrap()
+        };
+
+        AllocatedImage::new(Id::new(), format, image, allocation)
+    }
+
     pub fn allocate_buffer<T>(
         &self,
         data: &[T],
@@ -117,11 +174,177 @@ impl Allocator {
             std::ptr::copy_nonoverlapping(data.as_ptr(), mapped_data as _, data.len());
         }
 
-        unsafe { vk_mem_alloc::unmap_memory(self.allocator, allocation) }
+        unsafe {
+            vk_mem_alloc::flush_allocation(
+                self.allocator,
+                allocation,
+                Default::default(),
+                vk::WHOLE_SIZE,
+            )
+            .unwrap();
+            vk_mem_alloc::unmap_memory(self.allocator, allocation)
+        }
 
         buffer::AllocatedBuffer::new(Id::new(), buffer_size, ObjectType::Mesh, buffer, allocation)
     }
 
+    /// Allocates a buffer without initial data, `flags` decide whether it is host visible.
+    pub fn allocate_uninit_buffer(
+        &self,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        object_type: ObjectType,
+        flags: vk_mem_alloc::AllocationCreateFlags,
+    ) -> AllocatedBuffer {
+        let buffer_create_info = vk::BufferCreateInfo::default()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+        let allocation_info = vk_mem_alloc::AllocationCreateInfo {
+            usage: vk_mem_alloc::MemoryUsage::AUTO,
+            flags,
+            ..Default::default()
+        };
+
+        let (buffer, allocation, _) = unsafe {
+            vk_mem_alloc::create_buffer(self.allocator, &buffer_create_info, &allocation_info)
+                .unwrap()
+        };
+
+        buffer::AllocatedBuffer::new(Id::new(), size, object_type, buffer, allocation)
+    }
+
+    /// Buffer must be allocated as host visible and be big enough to hold `data`.
+    #[inline(always)]
+    pub fn write_buffer<T>(&self, allocated_buffer: &AllocatedBuffer, data: &[T]) {
+        debug_assert!(std::mem::size_of_val(data) as u64 <= allocated_buffer.size);
+
+        let mapped_data = unsafe {
+            vk_mem_alloc::map_memory(self.allocator, allocated_buffer.allocation).unwrap()
+        };
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), mapped_data as _, data.len());
+        }
+
+        // No-op on coherent memory, cached memory like `HOST_ACCESS_RANDOM` may not be.
+        unsafe {
+            vk_mem_alloc::flush_allocation(
+                self.allocator,
+                allocated_buffer.allocation,
+                Default::default(),
+                vk::WHOLE_SIZE,
+            )
+            .unwrap();
+            vk_mem_alloc::unmap_memory(self.allocator, allocated_buffer.allocation)
+        }
+    }
+
+    /// Buffer must be allocated as host visible and hold at least `data`.
+    #[inline(always)]
+    pub fn read_buffer<T: Copy>(&self, allocated_buffer: &AllocatedBuffer, data: &mut [T]) {
+        debug_assert!(std::mem::size_of_val(data) as u64 <= allocated_buffer.size);
+
+        let mapped_data = unsafe {
+            vk_mem_alloc::invalidate_allocation(
+                self.allocator,
+                allocated_buffer.allocation,
+                Default::default(),
+                vk::WHOLE_SIZE,
+            )
+            .unwrap();
+            vk_mem_alloc::map_memory(self.allocator, allocated_buffer.allocation).unwrap()
+        };
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(mapped_data as *const T, data.as_mut_ptr(), data.len());
+        }
+
+        unsafe { vk_mem_alloc::unmap_memory(self.allocator, allocated_buffer.allocation) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_buffer(&self, allocated_buffer: &AllocatedBuffer) {
+        unsafe {
+            vk_mem_alloc::destroy_buffer(
+                self.allocator,
+                allocated_buffer.buffer,
+                allocated_buffer.allocation,
+            );
+        }
+    }
+
+    #[inline(always)]
+    pub fn destroy_image(&self, allocated_image: &AllocatedImage) {
+        unsafe {
+            vk_mem_alloc::destroy_image(
+                self.allocator,
+                allocated_image.image,
+                allocated_image.allocation,
+            );
+        }
+    }
+
+    /// Creates images which are never in use at the same time bound to the same memory, sized
+    /// for the largest. Images whose memory types don't overlap with the others' get their own
+    /// allocation. Free the allocations with `free_memory` after destroying the images.
+    pub fn allocate_aliased_images(
+        &self,
+        device: &ash::Device,
+        image_create_infos: &[vk::ImageCreateInfo],
+    ) -> (Vec<vk::Image>, Vec<vk_mem_alloc::Allocation>) {
+        let images = image_create_infos
+            .iter()
+            .map(|image_create_info| unsafe {
+                device.create_image(image_create_info, None).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let mut groups = Vec::<(vk::MemoryRequirements, Vec<vk::Image>)>::new();
+        for &image in &images {
+            let requirements = unsafe { device.get_image_memory_requirements(image) };
+            match groups.iter_mut().find(|(group_requirements, _)| {
+                group_requirements.memory_type_bits & requirements.memory_type_bits != 0
+            }) {
+                Some((group_requirements, group_images)) => {
+                    group_requirements.size = group_requirements.size.max(requirements.size);
+                    group_requirements.alignment =
+                        group_requirements.alignment.max(requirements.alignment);
+                    group_requirements.memory_type_bits &= requirements.memory_type_bits;
+                    group_images.push(image);
+                }
+                None => groups.push((requirements, vec![image])),
+            }
+        }
+
+        let allocation_info = vk_mem_alloc::AllocationCreateInfo {
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            ..Default::default()
+        };
+        let allocations = groups
+            .iter()
+            .map(|(requirements, group_images)| {
+                let (allocation, _) = unsafe {
+                    vk_mem_alloc::allocate_memory(self.allocator, requirements, &allocation_info)
+                        .unwrap()
+                };
+                group_images.iter().for_each(|&image| unsafe {
+                    vk_mem_alloc::bind_image_memory(self.allocator, allocation, image).unwrap()
+                });
+
+                allocation
+            })
+            .collect();
+
+        (images, allocations)
+    }
+
+    #[inline(always)]
+    pub fn free_memory(&self, allocation: vk_mem_alloc::Allocation) {
+        unsafe { vk_mem_alloc::free_memory(self.allocator, allocation) }
+    }
+
     #[inline(always)]
     pub fn destroy_allocator(&mut self) {
         unsafe {
@@ -1,4 +1,4 @@
-use crate::no_engine::{objects::mesh::MeshMetadata, Id};
+use crate::no_engine::{objects::mesh::MeshMetadata, shader::layouts::ShaderLayout, Id};
 
 use super::buffer::AllocatedBuffer;
 
@@ -7,6 +7,8 @@ pub struct AllocatedMesh {
     pub metadata: MeshMetadata,
     pub vertex_buffer: AllocatedBuffer,
     pub index_buffer: AllocatedBuffer,
+    /// Vertex input matching the mesh vertex format.
+    pub shader_layout: ShaderLayout<'static>,
 }
 
 impl AllocatedMesh {
@@ -16,12 +18,14 @@ impl AllocatedMesh {
         metadata: MeshMetadata,
         vertex_buffer: AllocatedBuffer,
         index_buffer: AllocatedBuffer,
+        shader_layout: ShaderLayout<'static>,
     ) -> Self {
         Self {
             id,
             metadata,
             vertex_buffer,
             index_buffer,
+            shader_layout,
         }
     }
 }
@@ -1,14 +1,23 @@
-use super::{objects::mesh::Mesh, Id};
+use super::{
+    objects::{mesh::Mesh, vertex_format::VertexFormat},
+    Id,
+};
 
+pub mod hdr;
 mod loader;
+mod lod;
+mod optimizer;
 
 pub enum ObjectsQueue {
     Mesh(Id),
+    Environment(Id),
 }
 
 pub struct AssetManager {
     loader: loader::ObjectsLoader,
     meshes: Vec<Mesh>,
+    /// Taken once uploaded, the decoded texels are only needed until then.
+    environments: Vec<Option<hdr::HdrImage>>,
     assets_to_upload: Vec<ObjectsQueue>,
     next_mesh_id: Id,
     next_image_id: Id,
@@ -19,6 +28,7 @@ impl AssetManager {
         Self {
             loader: loader::ObjectsLoader::new(),
             meshes: Default::default(),
+            environments: Default::default(),
             assets_to_upload: Default::default(),
             next_mesh_id: Default::default(),
             next_image_id: Default::default(),
@@ -42,15 +52,44 @@ impl AssetManager {
                 }
                 self.next_mesh_id.next();
             }
+            "hdr" => {
+                let image_id = self.next_image_id;
+                let image = hdr::load_hdr(path);
+                if image.is_some() {
+                    self.assets_to_upload
+                        .push(ObjectsQueue::Environment(image_id));
+                }
+                self.environments.push(image);
+                self.next_image_id.next();
+            }
             _ => panic!("File extension not supported"),
         }
     }
 
+    /// Applies to meshes loaded afterwards.
+    #[inline(always)]
+    pub fn set_mesh_optimization(&mut self, is_enabled: bool) {
+        self.loader.is_optimizing = is_enabled;
+    }
+
+    /// Applies to meshes loaded afterwards.
+    #[inline(always)]
+    pub fn set_vertex_format(&mut self, vertex_format: VertexFormat) {
+        self.loader.vertex_format = vertex_format;
+    }
+
     #[inline(always)]
     pub fn get_mesh(&self, id: Id) -> &Mesh {
         unsafe { self.meshes.get_unchecked::<usize>(id.into()) }
     }
 
+    #[inline(always)]
+    pub fn take_environment(&mut self, id: Id) -> Option<hdr::HdrImage> {
+        self.environments
+            .get_mut::<usize>(id.into())
+            .and_then(Option::take)
+    }
+
     #[inline(always)]
     pub fn get_assets_to_upload(&mut self) -> Vec<ObjectsQueue> {
         std::mem::take(&mut self.assets_to_upload)
@@ -0,0 +1,236 @@
+/// Linear RGBA texels of a Radiance `.hdr` image, rows from the top.
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>,
+}
+
+const SIGNATURES: [&[u8]; 2] = [b"#?RADIANCE", b"#?RGBE"];
+const FORMAT: &[u8] = b"FORMAT=32-bit_rle_rgbe";
+/// Mantissas are stored with 8 bits, so the exponent bias includes them.
+const EXPONENT_BIAS: i32 = 128 + 8;
+/// Adaptive run length encoding is only used for widths in this range.
+const RLE_WIDTHS: std::ops::Range<usize> = 8..0x8000;
+
+/// Reads the standard `-Y height +X width` orientation, scanlines either flat or with the
+/// adaptive run length encoding. `None` if the file can't be read or is anything else.
+pub fn load_hdr(path: std::path::PathBuf) -> Option<HdrImage> {
+    let data = std::fs::read(path).ok()?;
+    let mut lines = data.split(|&byte| byte == b'\n');
+
+    let signature = lines.next()?;
+    if !SIGNATURES
+        .iter()
+        .any(|&prefix| signature.starts_with(prefix))
+    {
+        return None;
+    }
+
+    // A missing format means RGBE as well.
+    let mut header_size = signature.len() + 1;
+    for line in lines.by_ref() {
+        header_size += line.len() + 1;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != FORMAT {
+            return None;
+        }
+    }
+
+    let resolution = lines.next()?;
+    header_size += resolution.len() + 1;
+    let resolution = std::str::from_utf8(resolution).ok()?;
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse().ok()?, width.parse().ok()?),
+        _ => return None,
+    };
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let rgbe = decode_scanlines(data.get(header_size..)?, width, height)?;
+    let pixels = rgbe
+        .chunks_exact(4)
+        .map(|texel| {
+            if texel[3] == 0 {
+                return [0.0, 0.0, 0.0, 1.0];
+            }
+
+            let scale = 2.0f32.powi(texel[3] as i32 - EXPONENT_BIAS);
+            [
+                texel[0] as f32 * scale,
+                texel[1] as f32 * scale,
+                texel[2] as f32 * scale,
+                1.0,
+            ]
+        })
+        .collect();
+
+    Some(HdrImage {
+        width: width as _,
+        height: height as _,
+        pixels,
+    })
+}
+
+/// Interleaved RGBE bytes of every scanline.
+fn decode_scanlines(mut data: &[u8], width: usize, height: usize) -> Option<Vec<u8>> {
+    let mut rgbe = vec![0u8; width * height * 4];
+
+    for scanline in rgbe.chunks_exact_mut(width * 4) {
+        let is_rle = RLE_WIDTHS.contains(&width)
+            && data.len() >= 4
+            && data[0] == 2
+            && data[1] == 2
+            && ((data[2] as usize) << 8 | data[3] as usize) == width;
+
+        if !is_rle {
+            scanline.copy_from_slice(data.get(..width * 4)?);
+            data = &data[width * 4..];
+            continue;
+        }
+
+        // Every channel of the scanline is encoded separately, as runs or literal bytes.
+        data = &data[4..];
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let (&count, rest) = data.split_first()?;
+                data = rest;
+
+                if count > 128 {
+                    let count = count as usize - 128;
+                    let (&value, rest) = data.split_first()?;
+                    data = rest;
+                    if x + count > width {
+                        return None;
+                    }
+                    for offset in x..x + count {
+                        scanline[offset * 4 + channel] = value;
+                    }
+                    x += count;
+                } else {
+                    let count = count as usize;
+                    if count == 0 || x + count > width {
+                        return None;
+                    }
+                    let values = data.get(..count)?;
+                    data = &data[count..];
+                    for (offset, &value) in (x..x + count).zip(values) {
+                        scanline[offset * 4 + channel] = value;
+                    }
+                    x += count;
+                }
+            }
+        }
+    }
+
+    Some(rgbe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `header` followed by `scanlines` to a file named after the test.
+    fn write_hdr(name: &str, header: &str, scanlines: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("no_engine_{name}.hdr"));
+        let mut data = header.as_bytes().to_vec();
+        data.extend_from_slice(scanlines);
+        std::fs::write(&path, data).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn loads_flat_scanlines() {
+        let path = write_hdr(
+            "hdr_flat",
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n-Y 1 +X 2\n",
+            &[128, 64, 0, 129, 255, 255, 255, 0],
+        );
+
+        let image = load_hdr(path).unwrap();
+
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(
+            image.pixels,
+            vec![[1.0, 0.5, 0.0, 1.0], [0.0, 0.0, 0.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn accepts_missing_format() {
+        let path = write_hdr(
+            "hdr_no_format",
+            "#?RGBE\n\n-Y 1 +X 1\n",
+            &[128, 128, 128, 136],
+        );
+
+        let image = load_hdr(path).unwrap();
+
+        assert_eq!(image.pixels, vec![[128.0, 128.0, 128.0, 1.0]]);
+    }
+
+    #[test]
+    fn rejects_unsupported_headers() {
+        let headers = [
+            ("hdr_signature", "#?PNG\n\n-Y 1 +X 1\n"),
+            (
+                "hdr_format",
+                "#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n",
+            ),
+            ("hdr_orientation", "#?RADIANCE\n\n+Y 1 +X 1\n"),
+            ("hdr_empty", "#?RADIANCE\n\n-Y 0 +X 1\n"),
+        ];
+
+        for (name, header) in headers {
+            assert!(
+                load_hdr(write_hdr(name, header, &[0; 4])).is_none(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_scanlines() {
+        let path = write_hdr("hdr_truncated", "#?RADIANCE\n\n-Y 2 +X 1\n", &[0; 4]);
+
+        assert!(load_hdr(path).is_none());
+    }
+
+    #[test]
+    fn decodes_run_length_scanlines() {
+        let mut data = vec![2, 2, 0, 8];
+        // Red as a single run, green as literals, blue as two runs, exponent as a run.
+        data.extend([128 + 8, 10]);
+        data.push(8);
+        data.extend(0..8);
+        data.extend([128 + 4, 1, 128 + 4, 2]);
+        data.extend([128 + 8, 128]);
+
+        let rgbe = decode_scanlines(&data, 8, 1).unwrap();
+
+        let expected = (0..8u8)
+            .flat_map(|x| [10, x, if x < 4 { 1 } else { 2 }, 128])
+            .collect::<Vec<_>>();
+        assert_eq!(rgbe, expected);
+    }
+
+    #[test]
+    fn rejects_runs_past_the_scanline() {
+        let mut data = vec![2, 2, 0, 8];
+        data.extend([128 + 9, 10]);
+
+        assert!(decode_scanlines(&data, 8, 1).is_none());
+    }
+
+    #[test]
+    fn reads_narrow_scanlines_flat() {
+        // Widths below the run length range are never encoded, even if they look like it.
+        let data = [2, 2, 0, 4].repeat(4);
+
+        assert_eq!(decode_scanlines(&data, 4, 1).unwrap(), data);
+    }
+}
@@ -1,17 +1,29 @@
-use glam::Vec3;
+use glam::{Vec2, Vec3};
 
+use super::{lod, optimizer};
 use crate::no_engine::{
     id::Id,
-    objects::mesh::{Mesh, Vertex},
+    objects::{
+        mesh::{Mesh, Vertex},
+        vertex_format::VertexFormat,
+    },
 };
 
-pub struct ObjectsLoader;
+pub struct ObjectsLoader {
+    /// Deduplicates vertices and reorders them and the triangles of every level of detail
+    /// for the GPU caches.
+    pub is_optimizing: bool,
+    pub vertex_format: VertexFormat,
+}
 
 impl ObjectsLoader {
     const VERTICIES_PER_TRIANGLE: usize = 3;
 
     pub fn new() -> Self {
-        Self
+        Self {
+            is_optimizing: true,
+            vertex_format: Default::default(),
+        }
     }
 
     pub fn load_obj_mesh(&self, path: std::path::PathBuf, id: Id) -> Option<Mesh> {
@@ -26,22 +38,53 @@ impl ObjectsLoader {
             if let Some(model) = models.first() {
                 let mesh = &model.mesh;
 
+                // Missing normals, colors or UVs fall back to up, white and zero.
                 let vertices = mesh
                     .positions
                     .chunks(Self::VERTICIES_PER_TRIANGLE)
-                    .zip(mesh.normals.chunks(3))
-                    .map(|(position, normal)| {
-                        let position = Vec3::from_slice(position);
-                        let normal = Vec3::from_slice(normal);
+                    .enumerate()
+                    .map(|(vertex_index, position)| {
+                        let vec3_range = vertex_index * 3..vertex_index * 3 + 3;
+                        let uv_range = vertex_index * 2..vertex_index * 2 + 2;
+
                         Vertex {
-                            position,
-                            normal,
-                            color: normal,
+                            position: Vec3::from_slice(position),
+                            normal: mesh
+                                .normals
+                                .get(vec3_range.clone())
+                                .map_or(Vec3::Y, Vec3::from_slice),
+                            color: mesh.vertex_color.get(vec3_range).map_or([1.0; 4], |color| {
+                                Vec3::from_slice(color).extend(1.0).to_array()
+                            }),
+                            uv: mesh
+                                .texcoords
+                                .get(uv_range)
+                                .map_or(Vec2::ZERO, Vec2::from_slice),
                         }
                     })
-                    .collect();
+                    .collect::<Vec<_>>();
+                let (mut vertices, indices) = if self.is_optimizing {
+                    optimizer::deduplicate(&vertices, &mesh.indices)
+                } else {
+                    (vertices, mesh.indices.clone())
+                };
+
+                let (mut indices, lods) = lod::generate_lods(&vertices, indices);
+                if self.is_optimizing {
+                    for lod in &lods {
+                        let first_index = lod.first_index as usize;
+                        optimizer::optimize_triangles(
+                            &vertices,
+                            &mut indices[first_index..first_index + lod.indices_count as usize],
+                        );
+                    }
+                    optimizer::optimize_vertex_fetch(&mut vertices, &mut indices);
+                }
+
+                let mut mesh = Mesh::with_lods(id, vertices, indices, &lods);
+                mesh.metadata.vertex_format = self.vertex_format;
 
-                return Some(Mesh::new(id, vertices, mesh.indices.clone()));
+                return Some(mesh);
             }
         }
 
@@ -0,0 +1,121 @@
+use super::optimizer;
+use crate::no_engine::objects::mesh::{MeshLod, MeshMetadata, Vertex};
+
+/// Fractions of the original indices each level of detail aims for.
+const TARGET_RATIOS: [f32; MeshMetadata::MAX_LODS] = [1.0, 0.5, 0.25, 0.125];
+/// Relative to the mesh extents.
+const TARGET_ERROR: f32 = 0.02;
+/// A level that doesn't drop at least this much of the previous one isn't worth keeping.
+const MIN_REDUCTION: f32 = 0.9;
+
+/// Simplifies `indices` at every target ratio and returns the index lists of all kept levels
+/// one after another, the original indices first.
+pub fn generate_lods(vertices: &[Vertex], indices: Vec<u32>) -> (Vec<u32>, Vec<MeshLod>) {
+    let adapter = optimizer::vertex_adapter(vertices);
+
+    let mut lods = vec![MeshLod {
+        first_index: Default::default(),
+        indices_count: indices.len() as _,
+    }];
+    let mut lod_indices = indices.clone();
+    for ratio in TARGET_RATIOS.into_iter().skip(1) {
+        let previous_count = lods.last().unwrap().indices_count;
+        let target_count = (indices.len() as f32 * ratio) as usize / 3 * 3;
+
+        let simplified = meshopt::simplify(&indices, &adapter, target_count, TARGET_ERROR);
+        if simplified.is_empty() || simplified.len() as f32 > previous_count as f32 * MIN_REDUCTION
+        {
+            break;
+        }
+
+        lods.push(MeshLod {
+            first_index: lod_indices.len() as _,
+            indices_count: simplified.len() as _,
+        });
+        lod_indices.extend_from_slice(&simplified);
+    }
+
+    (lod_indices, lods)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    /// Flat square of `size` by `size` quads, which simplifies down to any ratio.
+    fn grid(size: u32) -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = (0..=size)
+            .flat_map(|z| (0..=size).map(move |x| (x, z)))
+            .map(|(x, z)| Vertex {
+                position: Vec3::new(x as f32, 0.0, z as f32) / size as f32,
+                normal: Vec3::Y,
+                ..Default::default()
+            })
+            .collect();
+        let indices = (0..size)
+            .flat_map(|z| (0..size).map(move |x| z * (size + 1) + x))
+            .flat_map(|corner| {
+                let below = corner + size + 1;
+                [corner, below, corner + 1, corner + 1, below, below + 1]
+            })
+            .collect();
+
+        (vertices, indices)
+    }
+
+    #[test]
+    fn keeps_original_indices_first() {
+        let (vertices, indices) = grid(32);
+
+        let (lod_indices, lods) = generate_lods(&vertices, indices.clone());
+
+        assert_eq!(lods[0].first_index, 0);
+        assert_eq!(lods[0].indices_count as usize, indices.len());
+        assert_eq!(lod_indices[..indices.len()], indices[..]);
+    }
+
+    #[test]
+    fn reduces_every_level_by_its_ratio() {
+        let (vertices, indices) = grid(32);
+
+        let (lod_indices, lods) = generate_lods(&vertices, indices.clone());
+
+        assert!(lods.len() > 1);
+        assert!(lods.len() <= MeshMetadata::MAX_LODS);
+        for (index, pair) in lods.windows(2).enumerate() {
+            let (previous, lod) = (pair[0], pair[1]);
+            assert_eq!(
+                lod.first_index,
+                previous.first_index + previous.indices_count
+            );
+            assert_eq!(lod.indices_count % 3, 0);
+            assert!(lod.indices_count as f32 <= previous.indices_count as f32 * MIN_REDUCTION);
+            assert!(lod.indices_count as f32 <= indices.len() as f32 * TARGET_RATIOS[index + 1]);
+        }
+
+        let last = lods.last().unwrap();
+        assert_eq!(
+            lod_indices.len(),
+            (last.first_index + last.indices_count) as usize
+        );
+        assert!(lod_indices
+            .iter()
+            .all(|&index| (index as usize) < vertices.len()));
+    }
+
+    #[test]
+    fn keeps_single_level_for_tiny_meshes() {
+        let vertices = [Vec3::ZERO, Vec3::X, Vec3::Z].map(|position| Vertex {
+            position,
+            normal: Vec3::Y,
+            ..Default::default()
+        });
+
+        let (lod_indices, lods) = generate_lods(&vertices, vec![0, 1, 2]);
+
+        assert_eq!(lods.len(), 1);
+        assert_eq!(lod_indices, [0, 1, 2]);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::no_engine::{objects::mesh::Vertex, utils};
+
+/// Keeps the reordering from adding more than this fraction to the vertex cache misses.
+const OVERDRAW_THRESHOLD: f32 = 1.05;
+
+/// Merges vertices that are equal byte for byte, unused ones are dropped.
+pub fn deduplicate(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let (vertices_count, remap) = meshopt::generate_vertex_remap(vertices, Some(indices));
+
+    (
+        meshopt::remap_vertex_buffer(vertices, vertices_count, &remap),
+        meshopt::remap_index_buffer(Some(indices), vertices_count, &remap),
+    )
+}
+
+/// Reorders triangles for the post-transform vertex cache first, then trades a little
+/// of it for less overdraw.
+pub fn optimize_triangles(vertices: &[Vertex], indices: &mut [u32]) {
+    meshopt::optimize_vertex_cache_in_place(indices, vertices.len());
+
+    let adapter = vertex_adapter(vertices);
+    meshopt::optimize_overdraw_in_place(indices, &adapter, OVERDRAW_THRESHOLD);
+}
+
+/// Reorders vertices by first use in `indices` and rewrites them to match,
+/// so it must run after the triangle order is final.
+pub fn optimize_vertex_fetch(vertices: &mut Vec<Vertex>, indices: &mut [u32]) {
+    let vertices_count = meshopt::optimize_vertex_fetch_in_place(indices, vertices);
+    vertices.truncate(vertices_count);
+}
+
+#[inline(always)]
+pub fn vertex_adapter(vertices: &[Vertex]) -> meshopt::VertexDataAdapter {
+    meshopt::VertexDataAdapter::new(
+        utils::slice_as_bytes(vertices),
+        std::mem::size_of::<Vertex>(),
+        std::mem::offset_of!(Vertex, position),
+    )
+    .unwrap()
+}
@@ -0,0 +1,166 @@
+use ash::vk;
+use glam::{Vec3, Vec4};
+
+use super::{
+    environment::Environment, gpu_data::GradientPushConstants, render_state::RenderState,
+    shader::ShaderManager, utils,
+};
+
+/// What the scene shows where nothing was drawn. Colors are linear and go through exposure and
+/// tonemapping like the rest of the scene.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    Solid(Vec3),
+    /// Blends vertically across the screen.
+    Gradient {
+        top: Vec3,
+        bottom: Vec3,
+    },
+    /// The loaded environment, a solid default color until there is one.
+    Skybox,
+    /// Cleared to zero alpha, so the window can be composited over what is behind it. Needs a
+    /// transparent window and a surface that supports premultiplied alpha, otherwise black.
+    Transparent,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(Self::DEFAULT_COLOR)
+    }
+}
+
+impl Background {
+    pub const DEFAULT_COLOR: Vec3 = Vec3::new(0.0, 0.0, 0.5);
+    pub const DEFAULT_GRADIENT_TOP: Vec3 = Vec3::new(0.25, 0.45, 0.8);
+    pub const DEFAULT_GRADIENT_BOTTOM: Vec3 = Vec3::new(0.8, 0.8, 0.85);
+
+    pub const GRADIENT_SHADER_NAME: &'static str = "gradient";
+
+    /// Cycles through the kinds, with their default colors.
+    #[inline(always)]
+    pub fn next(self) -> Self {
+        match self {
+            Background::Solid(_) => Background::Gradient {
+                top: Self::DEFAULT_GRADIENT_TOP,
+                bottom: Self::DEFAULT_GRADIENT_BOTTOM,
+            },
+            Background::Gradient { .. } => Background::Skybox,
+            Background::Skybox => Background::Transparent,
+            Background::Transparent => Background::Solid(Self::DEFAULT_COLOR),
+        }
+    }
+
+    /// The scene and the G-buffer albedo are cleared to it. Backgrounds drawn over the clear
+    /// use the solid default, which is what shows in the debug views.
+    #[inline(always)]
+    pub fn clear_value(self) -> vk::ClearValue {
+        let color = match self {
+            Background::Solid(color) => color.extend(1.0),
+            Background::Gradient { .. } | Background::Skybox => Self::DEFAULT_COLOR.extend(1.0),
+            Background::Transparent => Vec4::ZERO,
+        };
+
+        vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: color.to_array(),
+            },
+        }
+    }
+
+    /// Drawn first into the scene pass on the far plane, with the descriptors already pushed.
+    pub fn render(
+        self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+        environment: &Environment,
+    ) {
+        match self {
+            Background::Gradient { top, bottom } => {
+                let render_state = RenderState {
+                    cull_mode: vk::CullModeFlags::NONE,
+                    depth_write: false,
+                    blend_equation: None,
+                    extra_write_mask: vk::ColorComponentFlags::empty(),
+                    ..*render_state
+                };
+                render_state.apply(
+                    device,
+                    &shader_manager.shader_object,
+                    command_buffer,
+                    extent,
+                );
+                shader_manager.bind_graphics_program(command_buffer, Self::GRADIENT_SHADER_NAME);
+
+                let push_constants = GradientPushConstants {
+                    top: top.extend(1.0),
+                    bottom: bottom.extend(1.0),
+                };
+
+                unsafe {
+                    device.cmd_push_constants(
+                        command_buffer,
+                        shader_manager.pipeline_layout,
+                        vk::ShaderStageFlags::ALL,
+                        Default::default(),
+                        utils::as_bytes(&push_constants),
+                    );
+                    device.cmd_draw(command_buffer, 3, 1, Default::default(), Default::default());
+                }
+            }
+            Background::Skybox => environment.render_skybox(
+                device,
+                shader_manager,
+                command_buffer,
+                render_state,
+                extent,
+            ),
+            Background::Solid(_) | Background::Transparent => (),
+        }
+    }
+}
+
+/// `solid r g b`, `gradient r g b r g b` with the top first, `skybox` or `transparent`.
+impl std::fmt::Display for Background {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Background::Solid(color) => {
+                write!(formatter, "solid {} {} {}", color.x, color.y, color.z)
+            }
+            Background::Gradient { top, bottom } => write!(
+                formatter,
+                "gradient {} {} {} {} {} {}",
+                top.x, top.y, top.z, bottom.x, bottom.y, bottom.z
+            ),
+            Background::Skybox => write!(formatter, "skybox"),
+            Background::Transparent => write!(formatter, "transparent"),
+        }
+    }
+}
+
+impl std::str::FromStr for Background {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut words = value.split_whitespace();
+        let kind = words.next().ok_or(())?;
+        let components = words
+            .map(|word| word.parse::<f32>().map_err(|_| ()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match (kind, &components[..]) {
+            ("solid", &[r, g, b]) => Ok(Background::Solid(Vec3::new(r, g, b))),
+            ("gradient", &[top_r, top_g, top_b, bottom_r, bottom_g, bottom_b]) => {
+                Ok(Background::Gradient {
+                    top: Vec3::new(top_r, top_g, top_b),
+                    bottom: Vec3::new(bottom_r, bottom_g, bottom_b),
+                })
+            }
+            ("skybox", []) => Ok(Background::Skybox),
+            ("transparent", []) => Ok(Background::Transparent),
+            _ => Err(()),
+        }
+    }
+}
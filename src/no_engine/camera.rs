@@ -0,0 +1,103 @@
+use glam::{Mat4, Vec3, Vec4};
+
+pub struct Camera {
+    pub position: Vec3,
+    /// Rotation around the world Y axis in radians.
+    pub yaw: f32,
+    /// Rotation around the camera right axis in radians.
+    pub pitch: f32,
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+    pub aspect_ratio: f32,
+}
+
+impl Camera {
+    pub const DEFAULT_FOV_Y: f32 = std::f32::consts::FRAC_PI_3;
+    pub const DEFAULT_NEAR: f32 = 0.1;
+    pub const DEFAULT_FAR: f32 = 200.0;
+
+    pub fn new(aspect_ratio: f32) -> Self {
+        Self {
+            position: Vec3::new(0.0, 1.0, 3.0),
+            yaw: -std::f32::consts::FRAC_PI_2,
+            pitch: -0.2,
+            fov_y: Self::DEFAULT_FOV_Y,
+            near: Self::DEFAULT_NEAR,
+            far: Self::DEFAULT_FAR,
+            aspect_ratio,
+        }
+    }
+
+    #[inline(always)]
+    pub fn forward(&self) -> Vec3 {
+        Vec3::new(
+            self.yaw.cos() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.sin() * self.pitch.cos(),
+        )
+        .normalize()
+    }
+
+    #[inline(always)]
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_to_rh(self.position, self.forward(), Vec3::Y)
+    }
+
+    /// Vulkan has Y pointing down in clip space, so the projection is flipped here.
+    #[inline(always)]
+    pub fn projection(&self) -> Mat4 {
+        let mut projection =
+            Mat4::perspective_rh(self.fov_y, self.aspect_ratio, self.near, self.far);
+        projection.y_axis.y *= -1.0;
+
+        projection
+    }
+
+    #[inline(always)]
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+
+    /// World space planes facing inwards: left, right, bottom, top, near, far.
+    /// A point is inside when `plane.dot(point.extend(1.0)) >= 0.0` for all of them.
+    pub fn frustum_planes(&self) -> [Vec4; 6] {
+        let view_projection = self.view_projection();
+        let (row_x, row_y, row_z, row_w) = (
+            view_projection.row(0),
+            view_projection.row(1),
+            view_projection.row(2),
+            view_projection.row(3),
+        );
+
+        // Depth is in 0..1, so the near plane is the third row alone.
+        [
+            row_w + row_x,
+            row_w - row_x,
+            row_w + row_y,
+            row_w - row_y,
+            row_z,
+            row_w - row_z,
+        ]
+        .map(|plane| plane / plane.truncate().length())
+    }
+
+    /// World space corners of the view frustum slice between `near` and `far`,
+    /// near plane corners go first.
+    pub fn frustum_corners(&self, near: f32, far: f32) -> [Vec3; 8] {
+        let projection = Mat4::perspective_rh(self.fov_y, self.aspect_ratio, near, far);
+        let inverse_view_projection = (projection * self.view()).inverse();
+
+        let mut corners = [Vec3::ZERO; 8];
+        for (index, corner) in corners.iter_mut().enumerate() {
+            let ndc = Vec3::new(
+                if index & 1 == 0 { -1.0 } else { 1.0 },
+                if index & 2 == 0 { -1.0 } else { 1.0 },
+                if index & 4 == 0 { 0.0 } else { 1.0 },
+            );
+            *corner = inverse_view_projection.project_point3(ndc);
+        }
+
+        corners
+    }
+}
@@ -0,0 +1,191 @@
+use std::borrow::Cow;
+
+use arrayvec::ArrayVec;
+use ash::vk;
+
+use super::{
+    shader::{
+        descriptors::{DescriptorSlots, DescriptorWriter},
+        ShaderManager,
+    },
+    utils,
+};
+
+/// Memory dependency recorded after a dispatch so later work sees what it wrote.
+#[derive(Clone, Copy, Debug)]
+pub struct ComputeBarrier {
+    pub src_stage_mask: vk::PipelineStageFlags2,
+    pub src_access_mask: vk::AccessFlags2,
+    pub dst_stage_mask: vk::PipelineStageFlags2,
+    pub dst_access_mask: vk::AccessFlags2,
+}
+
+impl ComputeBarrier {
+    /// Storage written by a dispatch is read or written again by the next one.
+    pub const COMPUTE_TO_COMPUTE: Self = Self::from_compute(
+        vk::PipelineStageFlags2::COMPUTE_SHADER,
+        vk::AccessFlags2::from_raw(
+            vk::AccessFlags2::SHADER_STORAGE_READ.as_raw()
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE.as_raw()
+                | vk::AccessFlags2::SHADER_SAMPLED_READ.as_raw(),
+        ),
+    );
+    /// Storage buffers or images written by a dispatch are read by fragment shaders.
+    pub const COMPUTE_TO_FRAGMENT: Self = Self::from_compute(
+        vk::PipelineStageFlags2::FRAGMENT_SHADER,
+        vk::AccessFlags2::from_raw(
+            vk::AccessFlags2::SHADER_STORAGE_READ.as_raw()
+                | vk::AccessFlags2::SHADER_SAMPLED_READ.as_raw(),
+        ),
+    );
+    /// Draw commands and counts written by a dispatch are consumed by indirect draws.
+    pub const COMPUTE_TO_INDIRECT: Self = Self::from_compute(
+        vk::PipelineStageFlags2::DRAW_INDIRECT,
+        vk::AccessFlags2::INDIRECT_COMMAND_READ,
+    );
+    /// Anything written by a dispatch is visible to every later graphics or compute stage.
+    pub const COMPUTE_TO_ALL: Self = Self::from_compute(
+        vk::PipelineStageFlags2::ALL_COMMANDS,
+        vk::AccessFlags2::MEMORY_READ,
+    );
+
+    #[inline(always)]
+    pub const fn from_compute(
+        dst_stage_mask: vk::PipelineStageFlags2,
+        dst_access_mask: vk::AccessFlags2,
+    ) -> Self {
+        Self {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            dst_stage_mask,
+            dst_access_mask,
+        }
+    }
+
+    pub fn record(self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        let memory_barriers = [vk::MemoryBarrier2::default()
+            .src_stage_mask(self.src_stage_mask)
+            .src_access_mask(self.src_access_mask)
+            .dst_stage_mask(self.dst_stage_mask)
+            .dst_access_mask(self.dst_access_mask)];
+        let dependency_info = vk::DependencyInfo::default().memory_barriers(&memory_barriers);
+        unsafe { device.cmd_pipeline_barrier2(command_buffer, &dependency_info) };
+    }
+}
+
+/// A dispatch names a compute shader that was never compiled.
+#[derive(Debug)]
+pub struct UnknownComputeShader {
+    pub name: String,
+}
+
+impl std::fmt::Display for UnknownComputeShader {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Compute shader not found: {}", self.name)
+    }
+}
+
+impl std::error::Error for UnknownComputeShader {}
+
+/// Single compute shader invocation with the resources it uses.
+pub struct ComputeDispatch {
+    shader_name: Cow<'static, str>,
+    group_counts: [u32; 3],
+    descriptor_writer: DescriptorWriter,
+    push_constants: ArrayVec<u8, { DescriptorSlots::PUSH_CONSTANTS_SIZE as usize }>,
+    barrier: Option<ComputeBarrier>,
+}
+
+impl ComputeDispatch {
+    pub fn new(shader_name: impl Into<Cow<'static, str>>, group_counts: [u32; 3]) -> Self {
+        Self {
+            shader_name: shader_name.into(),
+            group_counts,
+            descriptor_writer: Default::default(),
+            push_constants: Default::default(),
+            barrier: Default::default(),
+        }
+    }
+
+    /// Enough workgroups of `workgroup_size` to cover `extent` once.
+    #[inline(always)]
+    pub fn covering(
+        shader_name: impl Into<Cow<'static, str>>,
+        extent: vk::Extent3D,
+        workgroup_size: [u32; 3],
+    ) -> Self {
+        Self::new(
+            shader_name,
+            [
+                extent.width.div_ceil(workgroup_size[0]),
+                extent.height.div_ceil(workgroup_size[1]),
+                extent.depth.div_ceil(workgroup_size[2]),
+            ],
+        )
+    }
+
+    #[inline(always)]
+    pub fn shader_name(&self) -> &str {
+        &self.shader_name
+    }
+
+    #[inline(always)]
+    pub fn descriptors(mut self, descriptor_writer: DescriptorWriter) -> Self {
+        self.descriptor_writer = descriptor_writer;
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn push_constants<T>(mut self, push_constants: &T) -> Self {
+        self.push_constants.clear();
+        self.push_constants
+            .try_extend_from_slice(utils::as_bytes(push_constants))
+            .expect("Push constants are too big");
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn barrier(mut self, barrier: ComputeBarrier) -> Self {
+        self.barrier = Some(barrier);
+
+        self
+    }
+
+    /// Nothing is recorded, not even the barrier, if the shader isn't compiled.
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        if !shader_manager.bind_compute_shader(command_buffer, &self.shader_name) {
+            return;
+        }
+        shader_manager.push_descriptors(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            &self.descriptor_writer,
+        );
+
+        unsafe {
+            if !self.push_constants.is_empty() {
+                device.cmd_push_constants(
+                    command_buffer,
+                    shader_manager.pipeline_layout,
+                    vk::ShaderStageFlags::ALL,
+                    Default::default(),
+                    &self.push_constants,
+                );
+            }
+
+            let [x, y, z] = self.group_counts;
+            device.cmd_dispatch(command_buffer, x, y, z);
+        }
+
+        if let Some(barrier) = self.barrier {
+            barrier.record(device, command_buffer);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use super::background::Background;
+
+/// Settings kept between runs, as `key = value` lines.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Config {
+    pub background: Background,
+}
+
+impl Config {
+    pub const PATH: &'static str = "no_engine.cfg";
+
+    /// Defaults for a missing file, unknown keys and values that can't be parsed.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let mut config = Self::default();
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return config;
+        };
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            if key.trim() == "background" {
+                if let Ok(background) = value.parse() {
+                    config.background = background;
+                }
+            }
+        }
+
+        config
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, format!("background = {}\n", self.background))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    /// Writes `text` to a config file named after the test.
+    fn write_config(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("no_engine_{name}.cfg"));
+        std::fs::write(&path, text).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn keeps_defaults_for_missing_file() {
+        let path = std::env::temp_dir().join("no_engine_config_missing.cfg");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(Config::load(path).background, Background::default());
+    }
+
+    #[test]
+    fn keeps_defaults_for_malformed_lines() {
+        let path = write_config(
+            "config_malformed",
+            "background\n\
+             = skybox\n\
+             backdrop = skybox\n\
+             background = solid 1 2\n\
+             background = solid 1 two 3\n\
+             background = sky box\n",
+        );
+
+        assert_eq!(Config::load(path).background, Background::default());
+    }
+
+    #[test]
+    fn parses_valid_lines_among_malformed_ones() {
+        let path = write_config(
+            "config_valid",
+            "# comment\n\
+             background=gradient 1 0 0 0 0 1\n\
+             background = rainbow\n\
+             \n\
+             \tbackground  =  solid 0.5 0.25 1  \n",
+        );
+
+        assert_eq!(
+            Config::load(path).background,
+            Background::Solid(Vec3::new(0.5, 0.25, 1.0))
+        );
+    }
+
+    #[test]
+    fn loads_what_was_saved() {
+        let path = std::env::temp_dir().join("no_engine_config_round_trip.cfg");
+        let config = Config {
+            background: Background::Gradient {
+                top: Vec3::new(0.1, 0.2, 0.3),
+                bottom: Vec3::ONE,
+            },
+        };
+
+        config.save(&path).unwrap();
+
+        assert_eq!(Config::load(path).background, config.background);
+    }
+}
@@ -0,0 +1,269 @@
+use ash::vk;
+use glam::{Mat4, Vec3, Vec4};
+
+use super::{
+    allocator::{AllocatedBuffer, Allocator},
+    objects::ObjectType,
+    render_state::RenderState,
+    shader::ShaderManager,
+};
+
+/// Layout must match the `debug_lines` vertex input.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DebugVertex {
+    pub position: Vec3,
+    /// RGBA8.
+    pub color: [u8; 4],
+}
+
+/// Immediate-mode lines in world space. Shapes are collected until the next frame is
+/// drawn and then discarded, so they have to be added again every frame.
+pub struct DebugDraw {
+    vertex_buffer: AllocatedBuffer,
+    vertices: Vec<DebugVertex>,
+    /// Drawn on top of everything.
+    overlay_vertices: Vec<DebugVertex>,
+    is_depth_tested: bool,
+}
+
+impl DebugDraw {
+    pub const MAX_VERTICES: usize = 131072;
+    pub const SHADER_NAME: &'static str = "debug_lines";
+    pub const SPHERE_SEGMENTS: usize = 32;
+
+    pub fn new(allocator: &Allocator) -> Self {
+        let vertex_buffer = allocator.allocate_uninit_buffer(
+            (Self::MAX_VERTICES * std::mem::size_of::<DebugVertex>()) as _,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            ObjectType::Debug,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+
+        Self {
+            vertex_buffer,
+            vertices: Default::default(),
+            overlay_vertices: Default::default(),
+            is_depth_tested: true,
+        }
+    }
+
+    /// Applies to the shapes added afterwards, they are hidden behind meshes by default.
+    #[inline(always)]
+    pub fn set_depth_test(&mut self, is_depth_tested: bool) -> &mut Self {
+        self.is_depth_tested = is_depth_tested;
+
+        self
+    }
+
+    /// Lines past `MAX_VERTICES` in a frame are dropped.
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec4) -> &mut Self {
+        let vertices = if self.is_depth_tested {
+            &mut self.vertices
+        } else {
+            &mut self.overlay_vertices
+        };
+
+        let color = (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0)
+            .round()
+            .to_array()
+            .map(|channel| channel as u8);
+        vertices.push(DebugVertex {
+            position: start,
+            color,
+        });
+        vertices.push(DebugVertex {
+            position: end,
+            color,
+        });
+
+        self
+    }
+
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: Vec4) -> &mut Self {
+        self.box_edges(
+            |index| {
+                Vec3::select(
+                    glam::BVec3::new(index & 1 != 0, index & 2 != 0, index & 4 != 0),
+                    max,
+                    min,
+                )
+            },
+            color,
+        )
+    }
+
+    /// Three circles around the axes.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec4) -> &mut Self {
+        let point = |angle: f32, axis: usize| {
+            let (sin, cos) = angle.sin_cos();
+            let offset = match axis {
+                0 => Vec3::new(0.0, cos, sin),
+                1 => Vec3::new(cos, 0.0, sin),
+                _ => Vec3::new(cos, sin, 0.0),
+            };
+
+            center + offset * radius
+        };
+
+        let step = std::f32::consts::TAU / Self::SPHERE_SEGMENTS as f32;
+        for axis in 0..3 {
+            for segment in 0..Self::SPHERE_SEGMENTS {
+                let angle = segment as f32 * step;
+                self.line(point(angle, axis), point(angle + step, axis), color);
+            }
+        }
+
+        self
+    }
+
+    /// Edges of the volume `view_projection` maps to clip space, with depth in 0..1.
+    pub fn frustum(&mut self, view_projection: &Mat4, color: Vec4) -> &mut Self {
+        let inverse = view_projection.inverse();
+        self.box_edges(
+            |index| {
+                inverse.project_point3(Vec3::new(
+                    if index & 1 != 0 { 1.0 } else { -1.0 },
+                    if index & 2 != 0 { 1.0 } else { -1.0 },
+                    if index & 4 != 0 { 1.0 } else { 0.0 },
+                ))
+            },
+            color,
+        )
+    }
+
+    /// X, Y and Z axes of `transform` in red, green and blue.
+    pub fn axis(&mut self, transform: &Mat4, size: f32) -> &mut Self {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [
+            (Vec3::X, Vec4::new(1.0, 0.0, 0.0, 1.0)),
+            (Vec3::Y, Vec4::new(0.0, 1.0, 0.0, 1.0)),
+            (Vec3::Z, Vec4::new(0.0, 0.0, 1.0, 1.0)),
+        ] {
+            self.line(origin, transform.transform_point3(axis * size), color);
+        }
+
+        self
+    }
+
+    /// `cells_count` cells along X and Z, centered on `center`.
+    pub fn grid(
+        &mut self,
+        center: Vec3,
+        cell_size: f32,
+        cells_count: u32,
+        color: Vec4,
+    ) -> &mut Self {
+        let half_size = cell_size * cells_count as f32 * 0.5;
+        for line_index in 0..=cells_count {
+            let offset = line_index as f32 * cell_size - half_size;
+            self.line(
+                center + Vec3::new(offset, 0.0, -half_size),
+                center + Vec3::new(offset, 0.0, half_size),
+                color,
+            );
+            self.line(
+                center + Vec3::new(-half_size, 0.0, offset),
+                center + Vec3::new(half_size, 0.0, offset),
+                color,
+            );
+        }
+
+        self
+    }
+
+    /// Records inside a pass with the depth attachment bound and the frame data descriptors
+    /// pushed, then forgets the shapes.
+    pub fn render(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+    ) {
+        let depth_tested_count = self.vertices.len().min(Self::MAX_VERTICES);
+        self.vertices.truncate(depth_tested_count);
+        self.vertices.extend_from_slice(
+            &self.overlay_vertices[..self
+                .overlay_vertices
+                .len()
+                .min(Self::MAX_VERTICES - depth_tested_count)],
+        );
+        self.overlay_vertices.clear();
+
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        allocator.write_buffer(&self.vertex_buffer, &self.vertices);
+
+        let render_state = RenderState {
+            topology: vk::PrimitiveTopology::LINE_LIST,
+            cull_mode: vk::CullModeFlags::NONE,
+            depth_write: false,
+            extra_write_mask: vk::ColorComponentFlags::empty(),
+            ..*render_state
+        };
+        render_state.apply(
+            device,
+            &shader_manager.shader_object,
+            command_buffer,
+            extent,
+        );
+        shader_manager.bind_graphics_program(command_buffer, Self::SHADER_NAME);
+
+        let overlay_count = self.vertices.len() - depth_tested_count;
+        unsafe {
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                Default::default(),
+                &[self.vertex_buffer.buffer],
+                &[Default::default()],
+            );
+
+            if depth_tested_count > 0 {
+                device.cmd_draw(
+                    command_buffer,
+                    depth_tested_count as _,
+                    1,
+                    Default::default(),
+                    Default::default(),
+                );
+            }
+            if overlay_count > 0 {
+                shader_manager
+                    .shader_object
+                    .cmd_set_depth_test_enable(command_buffer, false);
+                device.cmd_draw(
+                    command_buffer,
+                    overlay_count as _,
+                    1,
+                    depth_tested_count as _,
+                    Default::default(),
+                );
+            }
+        }
+
+        self.vertices.clear();
+    }
+
+    /// Corner bits are x, y, z. Every corner connects to the ones that differ in a single axis.
+    fn box_edges(&mut self, corner: impl Fn(usize) -> Vec3, color: Vec4) -> &mut Self {
+        for index in 0..8 {
+            for axis_bit in [1, 2, 4] {
+                if index & axis_bit == 0 {
+                    self.line(corner(index), corner(index | axis_bit), color);
+                }
+            }
+        }
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn destroy(&self, allocator: &Allocator) {
+        allocator.destroy_buffer(&self.vertex_buffer);
+    }
+}
@@ -0,0 +1,277 @@
+use ash::vk;
+use glam::Vec4;
+
+use super::{
+    gpu_data::DebugPushConstants,
+    instances::InstanceManager,
+    register::Register,
+    render_state::RenderState,
+    shader::{layouts::ShaderLayout, ShaderManager},
+    utils,
+};
+
+/// Replaces the shading of the scene pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugView {
+    #[default]
+    Lit,
+    Wireframe,
+    /// Brighter where more fragments land on the same pixel, depth testing is off.
+    Overdraw,
+    /// Linear depth, white at the camera.
+    Depth,
+}
+
+impl DebugView {
+    #[inline(always)]
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Lit => DebugView::Wireframe,
+            DebugView::Wireframe => DebugView::Overdraw,
+            DebugView::Overdraw => DebugView::Depth,
+            DebugView::Depth => DebugView::Lit,
+        }
+    }
+}
+
+/// Debug shading of the scene and line overlays of vertex normals and mesh bounds.
+pub struct DebugViews {
+    pub view: DebugView,
+    pub does_show_normals: bool,
+    pub does_show_bounds: bool,
+}
+
+impl DebugViews {
+    pub const MESH_SHADER_NAME: &'static str = "debug_mesh";
+    pub const OVERDRAW_SHADER_NAME: &'static str = "debug_overdraw";
+    pub const DEPTH_SHADER_NAME: &'static str = "debug_depth";
+    pub const NORMALS_SHADER_NAME: &'static str = "debug_normals";
+    pub const BOUNDS_SHADER_NAME: &'static str = "debug_bounds";
+    pub const COLOR_SHADER_NAME: &'static str = "debug_color";
+
+    pub const NORMALS_COLOR: Vec4 = Vec4::new(0.2, 0.6, 1.0, 1.0);
+    pub const BOUNDS_COLOR: Vec4 = Vec4::new(1.0, 0.8, 0.1, 1.0);
+    pub const BOX_EDGES_VERTICES_COUNT: u32 = 24;
+
+    pub fn new() -> Self {
+        Self {
+            view: Default::default(),
+            does_show_normals: Default::default(),
+            does_show_bounds: Default::default(),
+        }
+    }
+
+    /// Applies the state of the current view on top of `render_state` and binds its shaders,
+    /// the lit view binds `program_name` as is.
+    pub fn bind_scene(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+        program_name: &str,
+    ) {
+        let (render_state, fragment_name) = match self.view {
+            DebugView::Lit => (*render_state, None),
+            DebugView::Wireframe => (
+                RenderState {
+                    polygon_mode: vk::PolygonMode::LINE,
+                    cull_mode: vk::CullModeFlags::NONE,
+                    ..*render_state
+                },
+                None,
+            ),
+            DebugView::Overdraw => (
+                RenderState {
+                    cull_mode: vk::CullModeFlags::NONE,
+                    depth_test: false,
+                    depth_write: false,
+                    blend_equation: Some(Self::additive_blend_equation()),
+                    ..*render_state
+                },
+                Some(Self::OVERDRAW_SHADER_NAME),
+            ),
+            DebugView::Depth => (*render_state, Some(Self::DEPTH_SHADER_NAME)),
+        };
+
+        render_state.apply(
+            device,
+            &shader_manager.shader_object,
+            command_buffer,
+            extent,
+        );
+        match fragment_name {
+            Some(fragment_name) => shader_manager.bind_graphics_shaders(
+                command_buffer,
+                Self::MESH_SHADER_NAME,
+                fragment_name,
+            ),
+            None => shader_manager.bind_graphics_program(command_buffer, program_name),
+        }
+    }
+
+    /// Recorded inside the scene pass after the meshes, relies on the frame data
+    /// and instance descriptors being bound.
+    pub fn render_overlays(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+        instance_manager: &InstanceManager,
+        register: &Register,
+    ) {
+        if !self.does_show_normals && !self.does_show_bounds {
+            return;
+        }
+
+        let render_state = RenderState {
+            topology: vk::PrimitiveTopology::LINE_LIST,
+            cull_mode: vk::CullModeFlags::NONE,
+            depth_write: false,
+            extra_write_mask: vk::ColorComponentFlags::empty(),
+            ..*render_state
+        };
+        render_state.apply(
+            device,
+            &shader_manager.shader_object,
+            command_buffer,
+            extent,
+        );
+
+        if self.does_show_normals {
+            self.render_normals(
+                device,
+                shader_manager,
+                command_buffer,
+                instance_manager,
+                register,
+            );
+        }
+        if self.does_show_bounds {
+            self.render_bounds(
+                device,
+                shader_manager,
+                command_buffer,
+                instance_manager,
+                register,
+            );
+        }
+    }
+
+    /// One line per vertex, the mesh vertices are fed per instance of a two vertex draw.
+    fn render_normals(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        instance_manager: &InstanceManager,
+        register: &Register,
+    ) {
+        shader_manager.bind_graphics_shaders(
+            command_buffer,
+            Self::NORMALS_SHADER_NAME,
+            Self::COLOR_SHADER_NAME,
+        );
+
+        let meshes = register.get_meshes();
+        for (mesh_index, instance_index, _) in instance_manager.visible_instances() {
+            let mesh = &meshes[mesh_index];
+            ShaderLayout::for_vertex_format(mesh.metadata.vertex_format)
+                .with_input_rate(vk::VertexInputRate::INSTANCE)
+                .set_vertex_input(&shader_manager.shader_object, command_buffer);
+
+            let push_constants = DebugPushConstants {
+                color: Self::NORMALS_COLOR,
+                instance_index,
+                ..Default::default()
+            };
+
+            unsafe {
+                device.cmd_bind_vertex_buffers(
+                    command_buffer,
+                    Default::default(),
+                    &register.get_buffers()[mesh_index..=mesh_index],
+                    &register.get_offsets()[mesh_index..=mesh_index],
+                );
+                Self::push_constants(device, shader_manager, command_buffer, &push_constants);
+                device.cmd_draw(
+                    command_buffer,
+                    2,
+                    mesh.metadata.vertices_count,
+                    Default::default(),
+                    Default::default(),
+                );
+            }
+        }
+    }
+
+    fn render_bounds(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        instance_manager: &InstanceManager,
+        register: &Register,
+    ) {
+        shader_manager.bind_graphics_shaders(
+            command_buffer,
+            Self::BOUNDS_SHADER_NAME,
+            Self::COLOR_SHADER_NAME,
+        );
+
+        let meshes = register.get_meshes();
+        for (mesh_index, _, transform) in instance_manager.visible_instances() {
+            let aabb = meshes[mesh_index].metadata.aabb.transformed(&transform);
+            let push_constants = DebugPushConstants {
+                color: Self::BOUNDS_COLOR,
+                bounds_min: aabb.min.extend(1.0),
+                bounds_max: aabb.max.extend(1.0),
+                ..Default::default()
+            };
+
+            unsafe {
+                Self::push_constants(device, shader_manager, command_buffer, &push_constants);
+                device.cmd_draw(
+                    command_buffer,
+                    Self::BOX_EDGES_VERTICES_COUNT,
+                    1,
+                    Default::default(),
+                    Default::default(),
+                );
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn push_constants(
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        push_constants: &DebugPushConstants,
+    ) {
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                shader_manager.pipeline_layout,
+                vk::ShaderStageFlags::ALL,
+                Default::default(),
+                utils::as_bytes(push_constants),
+            );
+        }
+    }
+
+    #[inline(always)]
+    fn additive_blend_equation() -> vk::ColorBlendEquationEXT {
+        vk::ColorBlendEquationEXT {
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: vk::BlendFactor::ONE,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+        }
+    }
+}
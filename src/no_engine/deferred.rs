@@ -0,0 +1,349 @@
+use ash::vk;
+
+use super::{
+    fullscreen::FullscreenPass,
+    instances::InstanceManager,
+    register::Register,
+    render_graph::{
+        BufferAccess, BufferHandle, CompiledGraph, ImageAccess, ImageHandle, PassHandle,
+        RenderGraph, TransientImage, TransientPool,
+    },
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    NoEngine,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Opaque instances are shaded while they are drawn.
+    #[default]
+    Forward,
+    /// Opaque instances fill a G-buffer, lit by one fullscreen pass afterwards so every
+    /// pixel is shaded once. Runs without MSAA.
+    Deferred,
+}
+
+impl RenderPath {
+    #[inline(always)]
+    pub fn next(self) -> Self {
+        match self {
+            RenderPath::Forward => RenderPath::Deferred,
+            RenderPath::Deferred => RenderPath::Forward,
+        }
+    }
+}
+
+/// Graph resources of the G-buffer pass, the images are transient.
+pub struct GBuffer {
+    pass: PassHandle,
+    /// Linear albedo, the clear color where nothing was drawn.
+    albedo: ImageHandle,
+    /// World space, `w` unused.
+    pub normal: ImageHandle,
+    /// `x` - specular intensity, `y` - shininess relative to the maximum.
+    material: ImageHandle,
+}
+
+impl GBuffer {
+    /// Albedo, normal and material views, `None` if the graph culled the passes using them.
+    #[inline(always)]
+    fn views(&self, graph: &CompiledGraph, pool: &TransientPool) -> Option<[vk::ImageView; 3]> {
+        Some([
+            graph.image_view(self.albedo, pool)?,
+            graph.image_view(self.normal, pool)?,
+            graph.image_view(self.material, pool)?,
+        ])
+    }
+}
+
+/// Graph resources of the deferred passes.
+pub struct DeferredPasses {
+    gbuffer: GBuffer,
+    lighting_pass: PassHandle,
+}
+
+/// G-buffer and lighting pass of the deferred path. The G-buffer shares the scene depth,
+/// which is what the lighting pass reconstructs positions from.
+pub struct Deferred {
+    path: RenderPath,
+    sampler: vk::Sampler,
+    lighting_pass: FullscreenPass,
+}
+
+impl Deferred {
+    pub const GBUFFER_SHADER_NAME: &'static str = "gbuffer";
+    pub const LIGHTING_SHADER_NAME: &'static str = "deferred_lighting";
+
+    pub const ALBEDO_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+    pub const NORMAL_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+    pub const MATERIAL_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+    /// Slot 0 is taken by the shadow map.
+    pub const ALBEDO_SLOT: u32 = 1;
+    pub const NORMAL_SLOT: u32 = 2;
+    pub const MATERIAL_SLOT: u32 = 3;
+    pub const DEPTH_SLOT: u32 = 4;
+    /// Albedo, picking ids at the location they have in the forward pass, normal, material.
+    pub const ATTACHMENTS_COUNT: u32 = 4;
+
+    pub fn new(device: &ash::Device) -> Self {
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        Self {
+            path: Default::default(),
+            sampler,
+            lighting_pass: FullscreenPass::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn path(&self) -> RenderPath {
+        self.path
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.path == RenderPath::Deferred
+    }
+
+    #[inline(always)]
+    pub fn set_path(&mut self, path: RenderPath) {
+        self.path = path;
+    }
+
+    /// Declares the G-buffer pass, writing the depth and ids. `depth` must be single sampled.
+    /// `draw_buffers` are read in GPU-driven mode.
+    pub fn add_gbuffer_pass(
+        graph: &mut RenderGraph,
+        extent: vk::Extent2D,
+        depth: ImageHandle,
+        ids: Option<ImageHandle>,
+        draw_buffers: &[BufferHandle],
+    ) -> GBuffer {
+        let mut create_image = |name, format| {
+            graph.create_image(
+                name,
+                TransientImage {
+                    format,
+                    extent,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                    usage: vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+                },
+            )
+        };
+        let albedo = create_image("gbuffer_albedo", Self::ALBEDO_FORMAT);
+        let normal = create_image("gbuffer_normal", Self::NORMAL_FORMAT);
+        let material = create_image("gbuffer_material", Self::MATERIAL_FORMAT);
+
+        let mut pass = graph
+            .add_pass("gbuffer")
+            .image(albedo, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .image(normal, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .image(material, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .image(depth, ImageAccess::DEPTH_ATTACHMENT)
+            .buffers(draw_buffers, BufferAccess::INDIRECT_READ);
+        if let Some(ids) = ids {
+            pass = pass.image(ids, ImageAccess::COLOR_ATTACHMENT_WRITE);
+        }
+
+        GBuffer {
+            pass: pass.build(),
+            albedo,
+            normal,
+            material,
+        }
+    }
+
+    /// Declares the lighting pass writing `hdr`. Passes reading the G-buffer in between, like
+    /// the ambient occlusion ones, are declared before it.
+    pub fn add_lighting_pass(
+        graph: &mut RenderGraph,
+        gbuffer: GBuffer,
+        depth: ImageHandle,
+        hdr: ImageHandle,
+        shadow_map: ImageHandle,
+        tile_lights: BufferHandle,
+        ambient_occlusion: Option<ImageHandle>,
+    ) -> DeferredPasses {
+        let mut lighting_pass = graph
+            .add_pass("deferred_lighting")
+            .image(gbuffer.albedo, ImageAccess::FRAGMENT_SAMPLED)
+            .image(gbuffer.normal, ImageAccess::FRAGMENT_SAMPLED)
+            .image(gbuffer.material, ImageAccess::FRAGMENT_SAMPLED)
+            .image(depth, ImageAccess::DEPTH_SAMPLED)
+            .image(shadow_map, ImageAccess::DEPTH_SAMPLED)
+            .buffer(tile_lights, BufferAccess::FRAGMENT_STORAGE_READ)
+            .image(hdr, ImageAccess::COLOR_ATTACHMENT_WRITE);
+        if let Some(ambient_occlusion) = ambient_occlusion {
+            lighting_pass = lighting_pass.image(ambient_occlusion, ImageAccess::FRAGMENT_SAMPLED);
+        }
+
+        DeferredPasses {
+            gbuffer,
+            lighting_pass: lighting_pass.build(),
+        }
+    }
+
+    /// Draws the opaque instances into the G-buffer. `descriptor_writer` holds the scene
+    /// descriptors. Nothing is recorded if the graph culled the passes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_gbuffer(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+        instance_manager: &InstanceManager,
+        register: &Register,
+        descriptor_writer: &DescriptorWriter,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        passes: &DeferredPasses,
+        id_attachment: Option<vk::RenderingAttachmentInfo<'static>>,
+        clear_value: vk::ClearValue,
+        depth_view: vk::ImageView,
+    ) {
+        let Some([albedo_view, normal_view, material_view]) = passes.gbuffer.views(graph, pool)
+        else {
+            return;
+        };
+
+        let attachment = |image_view| {
+            vk::RenderingAttachmentInfo::default()
+                .image_view(image_view)
+                .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+        };
+        let color_attachments = [
+            attachment(albedo_view).clear_value(clear_value),
+            id_attachment.unwrap_or_default(),
+            attachment(normal_view),
+            attachment(material_view),
+        ];
+        let depth_attachment = vk::RenderingAttachmentInfo::default()
+            .image_view(depth_view)
+            .image_layout(vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .clear_value(vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            });
+        let rendering_info = vk::RenderingInfo::default()
+            .color_attachments(&color_attachments)
+            .depth_attachment(&depth_attachment)
+            .render_area(vk::Rect2D {
+                offset: Default::default(),
+                extent,
+            })
+            .layer_count(1);
+
+        let render_state = RenderState {
+            blend_equation: None,
+            extra_blend_equation: None,
+            extra_write_mask: vk::ColorComponentFlags::RGBA,
+            color_attachments_count: Self::ATTACHMENTS_COUNT,
+            ..*render_state
+        };
+
+        graph.begin_pass(device, command_buffer, passes.gbuffer.pass, pool);
+        unsafe {
+            device.cmd_begin_rendering(command_buffer, &rendering_info);
+
+            render_state.apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_shaders(
+                command_buffer,
+                NoEngine::MESH_SHADER_NAME,
+                Self::GBUFFER_SHADER_NAME,
+            );
+            shader_manager.push_descriptors(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                descriptor_writer,
+            );
+            instance_manager.draw_visible(device, shader_manager, command_buffer, register);
+
+            device.cmd_end_rendering(command_buffer);
+        }
+    }
+
+    /// Lights the G-buffer into `hdr_view`. The scene pass follows with the depth, ids and lit
+    /// color loaded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_lighting(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        descriptor_writer: &DescriptorWriter,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        passes: &DeferredPasses,
+        hdr_view: vk::ImageView,
+        depth_view: vk::ImageView,
+    ) {
+        let Some([albedo_view, normal_view, material_view]) = passes.gbuffer.views(graph, pool)
+        else {
+            return;
+        };
+
+        let lighting_descriptors = descriptor_writer
+            .clone()
+            .sampled_image(
+                Self::ALBEDO_SLOT,
+                albedo_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::NORMAL_SLOT,
+                normal_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::MATERIAL_SLOT,
+                material_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::DEPTH_SLOT,
+                depth_view,
+                self.sampler,
+                vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+            );
+        graph.begin_pass(device, command_buffer, passes.lighting_pass, pool);
+        self.lighting_pass.draw(
+            device,
+            shader_manager,
+            command_buffer,
+            Self::LIGHTING_SHADER_NAME,
+            hdr_view,
+            extent,
+            &lighting_descriptors,
+            &[],
+        );
+    }
+
+    #[inline(always)]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
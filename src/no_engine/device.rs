@@ -1,18 +1,40 @@
 use ash::vk;
 
+use super::tonemapping::DisplayMode;
+
+/// Enabled where the device supports them, the engine falls back without each.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OptionalFeatures {
+    /// GPU-driven drawing, instances stay culled on the CPU without it.
+    pub draw_indirect_count: bool,
+    /// Shadow casters in front of a cascade are clipped without it.
+    pub depth_clamp: bool,
+    /// The wireframe debug view is unavailable without it.
+    pub fill_mode_non_solid: bool,
+    /// Weighted blended transparency blends its two targets differently, transparency stays
+    /// sorted without it.
+    pub independent_blend: bool,
+}
+
 pub struct DeviceManager {
     pub physical_device: vk::PhysicalDevice,
     pub device: ash::Device,
     pub queue_family_index: u32,
     pub surface_format: vk::SurfaceFormatKHR,
     pub present_mode: vk::PresentModeKHR,
+    /// Premultiplied where the surface supports it, so a transparent background shows what is
+    /// behind the window.
+    pub composite_alpha: vk::CompositeAlphaFlagsKHR,
     pub graphics_queue: vk::Queue,
+    pub device_properties: vk::PhysicalDeviceProperties,
+    pub features: OptionalFeatures,
 }
 
 impl DeviceManager {
     pub unsafe fn new(
         instance: &ash::Instance,
         surface_manager: &super::surface::SurfaceManager,
+        display_mode: DisplayMode,
     ) -> Self {
         let surface_loader = &surface_manager.surface_loader;
         let surface = surface_manager.surface;
@@ -22,9 +44,18 @@ impl DeviceManager {
             ash::extensions::khr::DynamicRendering::NAME.as_ptr(),
             ash::extensions::ext::ShaderObject::NAME.as_ptr(),
             ash::extensions::ext::ExtendedDynamicState::NAME.as_ptr(),
+            ash::extensions::khr::PushDescriptor::NAME.as_ptr(),
         ];
 
-        let (physical_device, queue_family_index, device_properties, present_mode, surface_format) = unsafe {
+        let (
+            physical_device,
+            queue_family_index,
+            device_properties,
+            present_mode,
+            surface_format,
+            composite_alpha,
+            features,
+        ) = unsafe {
             instance
                 .enumerate_physical_devices()
                 .unwrap()
@@ -68,13 +99,23 @@ impl DeviceManager {
                     let surface_formats = surface_loader
                         .get_physical_device_surface_formats(physical_device, surface)
                         .unwrap();
-                    let Some(&surface_format) = surface_formats.iter().find(|surface_format| {
+                    let Some(&sdr_surface_format) = surface_formats.iter().find(|surface_format| {
                         surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
                             && (surface_format.format == vk::Format::B8G8R8A8_SRGB
                                 || surface_format.format == vk::Format::R8G8B8A8_SRGB)
                     }) else {
                         return None;
                     };
+                    // HDR is optional, SDR is used whenever the surface doesn't expose it.
+                    let surface_format = display_mode
+                        .preferred_surface_format()
+                        .and_then(|preferred_format| {
+                            surface_formats.iter().copied().find(|surface_format| {
+                                surface_format.format == preferred_format.format
+                                    && surface_format.color_space == preferred_format.color_space
+                            })
+                        })
+                        .unwrap_or(sdr_surface_format);
 
                     let device_capabilities = surface_loader
                         .get_physical_device_surface_capabilities(physical_device, surface)
@@ -84,6 +125,14 @@ impl DeviceManager {
                     {
                         return None;
                     }
+                    let composite_alpha = if device_capabilities
+                        .supported_composite_alpha
+                        .contains(vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED)
+                    {
+                        vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED
+                    } else {
+                        vk::CompositeAlphaFlagsKHR::OPAQUE
+                    };
 
                     let device_extensions = instance
                         .enumerate_device_extension_properties(physical_device)
@@ -102,16 +151,31 @@ impl DeviceManager {
                     let device_properties =
                         instance.get_physical_device_properties(physical_device);
 
+                    let mut vulkan_12_features = vk::PhysicalDeviceVulkan12Features::default();
+                    let mut supported_features =
+                        vk::PhysicalDeviceFeatures2::default().push_next(&mut vulkan_12_features);
+                    instance
+                        .get_physical_device_features2(physical_device, &mut supported_features);
+                    let supported_features = supported_features.features;
+                    let features = OptionalFeatures {
+                        draw_indirect_count: vulkan_12_features.draw_indirect_count == vk::TRUE,
+                        depth_clamp: supported_features.depth_clamp == vk::TRUE,
+                        fill_mode_non_solid: supported_features.fill_mode_non_solid == vk::TRUE,
+                        independent_blend: supported_features.independent_blend == vk::TRUE,
+                    };
+
                     Some((
                         physical_device,
                         queue_family_index,
                         device_properties,
                         present_mode,
                         surface_format,
+                        composite_alpha,
+                        features,
                     ))
                 })
                 .max_by_key(
-                    |(_, _, device_properties, _, _)| match device_properties.device_type {
+                    |(_, _, device_properties, ..)| match device_properties.device_type {
                         vk::PhysicalDeviceType::DISCRETE_GPU => 2,
                         vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
                         _ => Default::default(),
@@ -128,17 +192,24 @@ impl DeviceManager {
             .queue_family_index(queue_family_index as _)
             .queue_priorities(&[1.0])];
 
-        let physical_device_features = vk::PhysicalDeviceFeatures::default();
+        let physical_device_features = vk::PhysicalDeviceFeatures::default()
+            .fill_mode_non_solid(features.fill_mode_non_solid)
+            .depth_clamp(features.depth_clamp)
+            .independent_blend(features.independent_blend);
 
         let mut shader_object =
             ash::vk::PhysicalDeviceShaderObjectFeaturesEXT::default().shader_object(true);
 
+        let mut physical_device_vulkan_12_features = vk::PhysicalDeviceVulkan12Features::default()
+            .draw_indirect_count(features.draw_indirect_count);
+
         let mut physical_device_vulkan_13_features = vk::PhysicalDeviceVulkan13Features::default()
             .dynamic_rendering(true)
             .synchronization2(true);
 
         let mut physical_device_features = vk::PhysicalDeviceFeatures2::default()
             .features(physical_device_features)
+            .push_next(&mut physical_device_vulkan_12_features)
             .push_next(&mut physical_device_vulkan_13_features)
             .push_next(&mut shader_object);
 
@@ -162,7 +233,28 @@ impl DeviceManager {
             queue_family_index: queue_family_index as _,
             surface_format,
             present_mode,
+            composite_alpha,
             graphics_queue,
+            device_properties,
+            features,
         }
     }
+
+    /// Falls back to the highest sample count both color and depth attachments support.
+    pub fn clamp_sample_count(&self, requested: vk::SampleCountFlags) -> vk::SampleCountFlags {
+        let limits = &self.device_properties.limits;
+        let supported =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+
+        [
+            vk::SampleCountFlags::TYPE_8,
+            vk::SampleCountFlags::TYPE_4,
+            vk::SampleCountFlags::TYPE_2,
+        ]
+        .into_iter()
+        .find(|&sample_count| {
+            sample_count.as_raw() <= requested.as_raw() && supported.contains(sample_count)
+        })
+        .unwrap_or(vk::SampleCountFlags::TYPE_1)
+    }
 }
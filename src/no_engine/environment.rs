@@ -0,0 +1,720 @@
+use ash::vk;
+
+use super::{
+    allocator::{AllocatedBuffer, AllocatedImage, Allocator},
+    asset::hdr::HdrImage,
+    compute::{ComputeBarrier, ComputeDispatch},
+    gpu_data::PrefilterPushConstants,
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    swapchain::{ColorTarget, SwapchainManager},
+};
+
+/// Cube with a view to sample it and a layered view of every mip for compute to write.
+struct CubeTarget {
+    allocated_image: AllocatedImage,
+    cube_view: vk::ImageView,
+    mip_views: Vec<vk::ImageView>,
+}
+
+impl CubeTarget {
+    fn new(
+        device: &ash::Device,
+        allocator: &Allocator,
+        format: vk::Format,
+        size: u32,
+        mip_levels: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> Self {
+        let allocated_image = allocator.allocate_cube_image(format, size, mip_levels, usage);
+
+        let create_view = |view_type, base_mip_level, level_count| {
+            let image_view_info = vk::ImageViewCreateInfo {
+                image: allocated_image.image,
+                view_type,
+                format,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level,
+                    level_count,
+                    layer_count: 6,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            unsafe { device.create_image_view(&image_view_info, None).unwrap() }
+        };
+        let cube_view = create_view(vk::ImageViewType::CUBE, 0, mip_levels);
+        let mip_views = (0..mip_levels)
+            .map(|mip_level| create_view(vk::ImageViewType::TYPE_2D_ARRAY, mip_level, 1))
+            .collect();
+
+        Self {
+            allocated_image,
+            cube_view,
+            mip_views,
+        }
+    }
+
+    #[inline(always)]
+    fn subresource_range(base_mip_level: u32, level_count: u32) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level,
+            level_count,
+            layer_count: 6,
+            ..Default::default()
+        }
+    }
+
+    fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        unsafe {
+            device.destroy_image_view(self.cube_view, None);
+            self.mip_views
+                .iter()
+                .for_each(|&mip_view| device.destroy_image_view(mip_view, None));
+        }
+        allocator.destroy_image(&self.allocated_image);
+    }
+}
+
+/// Equirectangular image waiting in a staging buffer for the next `update`.
+struct PendingUpload {
+    staging_buffer: AllocatedBuffer,
+    equirectangular: ColorTarget,
+    extent: vk::Extent2D,
+    /// The conversion was recorded, the next `update` destroys the upload.
+    is_converted: bool,
+}
+
+/// HDR environment drawn as the skybox and lighting the scene. Loaded images are converted
+/// into a cubemap, which is convolved into diffuse irradiance and prefiltered by roughness
+/// into the mips of a specular cubemap. With the BRDF LUT they form the split sum
+/// approximation the lit shaders evaluate for the ambient light.
+pub struct Environment {
+    /// Scales the skybox and the light coming from the environment.
+    pub intensity: f32,
+    sampler: vk::Sampler,
+    environment: CubeTarget,
+    irradiance: CubeTarget,
+    prefiltered: CubeTarget,
+    brdf_lut: ColorTarget,
+    pending_upload: Option<PendingUpload>,
+    /// Targets are in their sampled layouts and the BRDF LUT is generated.
+    is_initialized: bool,
+    is_loaded: bool,
+}
+
+impl Environment {
+    pub const EQUIRECTANGULAR_SHADER_NAME: &'static str = "equirectangular_to_cube";
+    pub const IRRADIANCE_SHADER_NAME: &'static str = "irradiance";
+    pub const PREFILTER_SHADER_NAME: &'static str = "prefilter";
+    pub const BRDF_LUT_SHADER_NAME: &'static str = "brdf_lut";
+    pub const SKYBOX_SHADER_NAME: &'static str = "skybox";
+
+    pub const EQUIRECTANGULAR_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+    pub const BRDF_LUT_FORMAT: vk::Format = vk::Format::R16G16_SFLOAT;
+    pub const ENVIRONMENT_SIZE: u32 = 512;
+    pub const ENVIRONMENT_MIP_LEVELS: u32 = Self::ENVIRONMENT_SIZE.ilog2() + 1;
+    pub const IRRADIANCE_SIZE: u32 = 32;
+    pub const PREFILTERED_SIZE: u32 = 128;
+    /// The last mip is for the roughest surfaces.
+    pub const PREFILTERED_MIP_LEVELS: u32 = 5;
+    pub const BRDF_LUT_SIZE: u32 = 256;
+    pub const WORKGROUP_SIZE: u32 = 8;
+
+    /// Scene descriptor slots, after the ambient occlusion.
+    pub const ENVIRONMENT_SLOT: u32 = 6;
+    pub const IRRADIANCE_SLOT: u32 = 7;
+    pub const PREFILTERED_SLOT: u32 = 8;
+    pub const BRDF_LUT_SLOT: u32 = 9;
+    pub const INPUT_SLOT: u32 = 1;
+    pub const OUTPUT_SLOT: u32 = 0;
+
+    pub fn new(device: &ash::Device, allocator: &Allocator) -> Self {
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .max_lod(vk::LOD_CLAMP_NONE)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        let storage_usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED;
+        let environment = CubeTarget::new(
+            device,
+            allocator,
+            Self::FORMAT,
+            Self::ENVIRONMENT_SIZE,
+            Self::ENVIRONMENT_MIP_LEVELS,
+            storage_usage | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST,
+        );
+        let irradiance = CubeTarget::new(
+            device,
+            allocator,
+            Self::FORMAT,
+            Self::IRRADIANCE_SIZE,
+            1,
+            storage_usage,
+        );
+        let prefiltered = CubeTarget::new(
+            device,
+            allocator,
+            Self::FORMAT,
+            Self::PREFILTERED_SIZE,
+            Self::PREFILTERED_MIP_LEVELS,
+            storage_usage,
+        );
+        let brdf_lut = SwapchainManager::create_color_target(
+            device,
+            allocator,
+            Self::BRDF_LUT_FORMAT,
+            vk::Extent2D {
+                width: Self::BRDF_LUT_SIZE,
+                height: Self::BRDF_LUT_SIZE,
+            },
+            vk::SampleCountFlags::TYPE_1,
+            storage_usage,
+        );
+
+        Self {
+            intensity: 1.0,
+            sampler,
+            environment,
+            irradiance,
+            prefiltered,
+            brdf_lut,
+            pending_upload: None,
+            is_initialized: false,
+            is_loaded: false,
+        }
+    }
+
+    /// `x` - whether an environment was converted, until then the lit shaders fall back to a
+    /// constant ambient light and there's no skybox, `y` - the highest prefiltered mip,
+    /// `z` - intensity bits. Goes after the lights count in `FrameData::light_info`.
+    #[inline(always)]
+    pub fn light_info(&self) -> glam::UVec3 {
+        glam::UVec3::new(
+            self.is_loaded as _,
+            Self::PREFILTERED_MIP_LEVELS - 1,
+            self.intensity.to_bits(),
+        )
+    }
+
+    /// Converted by the next `update`, replacing the current environment.
+    pub fn load(&mut self, device: &ash::Device, allocator: &Allocator, image: HdrImage) {
+        if let Some(pending_upload) = self.pending_upload.take() {
+            Self::destroy_upload(device, allocator, &pending_upload);
+        }
+
+        let extent = vk::Extent2D {
+            width: image.width,
+            height: image.height,
+        };
+        let staging_buffer = allocator.allocate_buffer(
+            &image.pixels,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::SharingMode::EXCLUSIVE,
+        );
+        let equirectangular = SwapchainManager::create_color_target(
+            device,
+            allocator,
+            Self::EQUIRECTANGULAR_FORMAT,
+            extent,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        );
+
+        self.pending_upload = Some(PendingUpload {
+            staging_buffer,
+            equirectangular,
+            extent,
+            is_converted: false,
+        });
+    }
+
+    /// Recorded at the start of the frame, outside of rendering. Generates the BRDF LUT the
+    /// first time and converts a loaded environment.
+    pub fn update(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        if !self.is_initialized {
+            self.initialize(device, shader_manager, command_buffer);
+            self.is_initialized = true;
+        }
+
+        if let Some(pending_upload) = self.pending_upload.take() {
+            if pending_upload.is_converted {
+                Self::destroy_upload(device, allocator, &pending_upload);
+            } else {
+                self.convert(device, shader_manager, command_buffer, &pending_upload);
+                self.is_loaded = true;
+                self.pending_upload = Some(PendingUpload {
+                    is_converted: true,
+                    ..pending_upload
+                });
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn write_descriptors(&self, descriptor_writer: DescriptorWriter) -> DescriptorWriter {
+        descriptor_writer
+            .sampled_image(
+                Self::ENVIRONMENT_SLOT,
+                self.environment.cube_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::IRRADIANCE_SLOT,
+                self.irradiance.cube_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::PREFILTERED_SLOT,
+                self.prefiltered.cube_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::BRDF_LUT_SLOT,
+                self.brdf_lut.image_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+    }
+
+    /// Records inside the scene pass with the scene descriptors pushed, before anything else
+    /// is drawn. Only passes where the depth is still cleared, so the deferred path keeps its
+    /// lit pixels.
+    pub fn render_skybox(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+    ) {
+        if !self.is_loaded {
+            return;
+        }
+
+        let render_state = RenderState {
+            cull_mode: vk::CullModeFlags::NONE,
+            depth_write: false,
+            blend_equation: None,
+            extra_write_mask: vk::ColorComponentFlags::empty(),
+            ..*render_state
+        };
+        render_state.apply(
+            device,
+            &shader_manager.shader_object,
+            command_buffer,
+            extent,
+        );
+        shader_manager.bind_graphics_program(command_buffer, Self::SKYBOX_SHADER_NAME);
+
+        unsafe {
+            device.cmd_draw(command_buffer, 3, 1, Default::default(), Default::default());
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        if let Some(pending_upload) = &self.pending_upload {
+            Self::destroy_upload(device, allocator, pending_upload);
+        }
+        self.environment.destroy(device, allocator);
+        self.irradiance.destroy(device, allocator);
+        self.prefiltered.destroy(device, allocator);
+        self.brdf_lut.destroy(device, allocator);
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+
+    /// The cubemaps are only sampled once an environment is loaded, they just need a valid
+    /// layout until then.
+    fn initialize(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        let sampled_barrier = |image, subresource_range| {
+            vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::NONE)
+                .src_access_mask(vk::AccessFlags2::NONE)
+                .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image(image)
+                .subresource_range(subresource_range)
+        };
+        let lut_subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            level_count: 1,
+            layer_count: 1,
+            ..Default::default()
+        };
+        let lut_image = self.brdf_lut.allocated_image.image;
+
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[
+                sampled_barrier(
+                    self.environment.allocated_image.image,
+                    CubeTarget::subresource_range(0, Self::ENVIRONMENT_MIP_LEVELS),
+                ),
+                sampled_barrier(
+                    self.irradiance.allocated_image.image,
+                    CubeTarget::subresource_range(0, 1),
+                ),
+                sampled_barrier(
+                    self.prefiltered.allocated_image.image,
+                    CubeTarget::subresource_range(0, Self::PREFILTERED_MIP_LEVELS),
+                ),
+                Self::storage_barrier(lut_image, lut_subresource_range),
+            ],
+        );
+
+        ComputeDispatch::covering(
+            Self::BRDF_LUT_SHADER_NAME,
+            vk::Extent3D {
+                width: Self::BRDF_LUT_SIZE,
+                height: Self::BRDF_LUT_SIZE,
+                depth: 1,
+            },
+            [Self::WORKGROUP_SIZE, Self::WORKGROUP_SIZE, 1],
+        )
+        .descriptors(
+            DescriptorWriter::new().storage_image(Self::OUTPUT_SLOT, self.brdf_lut.image_view),
+        )
+        .record(device, shader_manager, command_buffer);
+
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[Self::sampling_barrier(lut_image, lut_subresource_range)],
+        );
+    }
+
+    /// Uploads the equirectangular image, projects it onto the environment cube, blits its
+    /// mips and convolves them into the irradiance and prefiltered cubes.
+    fn convert(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        pending_upload: &PendingUpload,
+    ) {
+        let equirectangular_image = pending_upload.equirectangular.allocated_image.image;
+        let equirectangular_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            level_count: 1,
+            layer_count: 1,
+            ..Default::default()
+        };
+        let copy_regions = [vk::BufferImageCopy::default()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                layer_count: 1,
+                ..Default::default()
+            })
+            .image_extent(vk::Extent3D {
+                width: pending_upload.extent.width,
+                height: pending_upload.extent.height,
+                depth: 1,
+            })];
+
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::NONE)
+                .src_access_mask(vk::AccessFlags2::NONE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .image(equirectangular_image)
+                .subresource_range(equirectangular_range)],
+        );
+        unsafe {
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                pending_upload.staging_buffer.buffer,
+                equirectangular_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &copy_regions,
+            );
+        }
+
+        // The previous environment may still be sampled by the last frame's fragments.
+        let environment_image = self.environment.allocated_image.image;
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[
+                vk::ImageMemoryBarrier2::default()
+                    .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                    .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .image(equirectangular_image)
+                    .subresource_range(equirectangular_range),
+                Self::storage_barrier(environment_image, CubeTarget::subresource_range(0, 1)),
+            ],
+        );
+
+        let cube_extent = |size| vk::Extent3D {
+            width: size,
+            height: size,
+            depth: 6,
+        };
+        let workgroup_size = [Self::WORKGROUP_SIZE, Self::WORKGROUP_SIZE, 1];
+        ComputeDispatch::covering(
+            Self::EQUIRECTANGULAR_SHADER_NAME,
+            cube_extent(Self::ENVIRONMENT_SIZE),
+            workgroup_size,
+        )
+        .descriptors(
+            DescriptorWriter::new()
+                .sampled_image(
+                    Self::INPUT_SLOT,
+                    pending_upload.equirectangular.image_view,
+                    self.sampler,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                )
+                .storage_image(Self::OUTPUT_SLOT, self.environment.mip_views[0]),
+        )
+        .record(device, shader_manager, command_buffer);
+
+        self.generate_environment_mips(device, command_buffer);
+
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[
+                Self::storage_barrier(
+                    self.irradiance.allocated_image.image,
+                    CubeTarget::subresource_range(0, 1),
+                ),
+                Self::storage_barrier(
+                    self.prefiltered.allocated_image.image,
+                    CubeTarget::subresource_range(0, Self::PREFILTERED_MIP_LEVELS),
+                ),
+            ],
+        );
+
+        let input_descriptors = DescriptorWriter::new().sampled_image(
+            Self::INPUT_SLOT,
+            self.environment.cube_view,
+            self.sampler,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+        ComputeDispatch::covering(
+            Self::IRRADIANCE_SHADER_NAME,
+            cube_extent(Self::IRRADIANCE_SIZE),
+            workgroup_size,
+        )
+        .descriptors(
+            input_descriptors
+                .clone()
+                .storage_image(Self::OUTPUT_SLOT, self.irradiance.mip_views[0]),
+        )
+        .record(device, shader_manager, command_buffer);
+
+        for (mip_level, &mip_view) in self.prefiltered.mip_views.iter().enumerate() {
+            let push_constants = PrefilterPushConstants {
+                roughness: mip_level as f32 / (Self::PREFILTERED_MIP_LEVELS - 1) as f32,
+                source_size: Self::ENVIRONMENT_SIZE as _,
+            };
+            ComputeDispatch::covering(
+                Self::PREFILTER_SHADER_NAME,
+                cube_extent((Self::PREFILTERED_SIZE >> mip_level).max(1)),
+                workgroup_size,
+            )
+            .descriptors(
+                input_descriptors
+                    .clone()
+                    .storage_image(Self::OUTPUT_SLOT, mip_view),
+            )
+            .push_constants(&push_constants)
+            .record(device, shader_manager, command_buffer);
+        }
+
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[
+                Self::sampling_barrier(
+                    self.irradiance.allocated_image.image,
+                    CubeTarget::subresource_range(0, 1),
+                ),
+                Self::sampling_barrier(
+                    self.prefiltered.allocated_image.image,
+                    CubeTarget::subresource_range(0, Self::PREFILTERED_MIP_LEVELS),
+                ),
+            ],
+        );
+    }
+
+    /// Downsamples the environment cube from the mip the projection wrote and leaves every
+    /// mip ready for sampling.
+    fn generate_environment_mips(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        let image = self.environment.allocated_image.image;
+        let barrier = |mip_level, level_count| {
+            vk::ImageMemoryBarrier2::default()
+                .image(image)
+                .subresource_range(CubeTarget::subresource_range(mip_level, level_count))
+        };
+
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[
+                barrier(0, 1)
+                    .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                    .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+                    .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+                    .old_layout(vk::ImageLayout::GENERAL)
+                    .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+                barrier(1, Self::ENVIRONMENT_MIP_LEVELS - 1)
+                    .src_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                    .src_access_mask(vk::AccessFlags2::NONE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+                    .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+            ],
+        );
+
+        let layers = |mip_level| vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level,
+            base_array_layer: 0,
+            layer_count: 6,
+        };
+        let corner = |mip_level: u32| {
+            let size = (Self::ENVIRONMENT_SIZE >> mip_level).max(1) as i32;
+            vk::Offset3D {
+                x: size,
+                y: size,
+                z: 1,
+            }
+        };
+        for mip_level in 1..Self::ENVIRONMENT_MIP_LEVELS {
+            let blit_regions = [vk::ImageBlit::default()
+                .src_subresource(layers(mip_level - 1))
+                .src_offsets([Default::default(), corner(mip_level - 1)])
+                .dst_subresource(layers(mip_level))
+                .dst_offsets([Default::default(), corner(mip_level)])];
+            unsafe {
+                device.cmd_blit_image(
+                    command_buffer,
+                    image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &blit_regions,
+                    vk::Filter::LINEAR,
+                );
+            }
+
+            Self::record_barriers(
+                device,
+                command_buffer,
+                &[barrier(mip_level, 1)
+                    .src_stage_mask(vk::PipelineStageFlags2::BLIT)
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+                    .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)],
+            );
+        }
+
+        Self::record_barriers(
+            device,
+            command_buffer,
+            &[barrier(0, Self::ENVIRONMENT_MIP_LEVELS)
+                .src_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(
+                    vk::PipelineStageFlags2::COMPUTE_SHADER
+                        | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                )
+                .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+                .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)],
+        );
+    }
+
+    /// Discards the contents for compute to write, after any fragment shader sampled them.
+    #[inline(always)]
+    fn storage_barrier(
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+    ) -> vk::ImageMemoryBarrier2<'static> {
+        vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+            .src_access_mask(vk::AccessFlags2::NONE)
+            .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .image(image)
+            .subresource_range(subresource_range)
+    }
+
+    #[inline(always)]
+    fn sampling_barrier(
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+    ) -> vk::ImageMemoryBarrier2<'static> {
+        let ComputeBarrier {
+            src_stage_mask,
+            src_access_mask,
+            dst_stage_mask,
+            dst_access_mask,
+        } = ComputeBarrier::COMPUTE_TO_FRAGMENT;
+
+        vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(src_stage_mask)
+            .src_access_mask(src_access_mask)
+            .dst_stage_mask(dst_stage_mask)
+            .dst_access_mask(dst_access_mask)
+            .old_layout(vk::ImageLayout::GENERAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image(image)
+            .subresource_range(subresource_range)
+    }
+
+    #[inline(always)]
+    fn record_barriers(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_barriers: &[vk::ImageMemoryBarrier2],
+    ) {
+        let dependency_info = vk::DependencyInfo::default().image_memory_barriers(image_barriers);
+        unsafe { device.cmd_pipeline_barrier2(command_buffer, &dependency_info) };
+    }
+
+    fn destroy_upload(device: &ash::Device, allocator: &Allocator, pending_upload: &PendingUpload) {
+        allocator.destroy_buffer(&pending_upload.staging_buffer);
+        pending_upload.equirectangular.destroy(device, allocator);
+    }
+}
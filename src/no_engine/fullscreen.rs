@@ -0,0 +1,97 @@
+use ash::vk;
+
+use super::{
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+};
+
+/// Draws a single triangle over the whole output with the given fragment shader.
+pub struct FullscreenPass {
+    render_state: RenderState,
+}
+
+impl FullscreenPass {
+    pub const VERTEX_SHADER_NAME: &'static str = "fullscreen";
+
+    pub fn new() -> Self {
+        Self {
+            render_state: RenderState::fullscreen(),
+        }
+    }
+
+    /// Blends over the previous content of the output instead of replacing it.
+    pub fn blended(blend_equation: vk::ColorBlendEquationEXT) -> Self {
+        Self {
+            render_state: RenderState {
+                blend_equation: Some(blend_equation),
+                ..RenderState::fullscreen()
+            },
+        }
+    }
+
+    /// `output_view` must be in `COLOR_ATTACHMENT_OPTIMAL`, its previous content is discarded
+    /// unless the pass blends.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        fragment_shader_name: &str,
+        output_view: vk::ImageView,
+        extent: vk::Extent2D,
+        descriptor_writer: &DescriptorWriter,
+        push_constants: &[u8],
+    ) {
+        let load_op = if self.render_state.blend_equation.is_some() {
+            vk::AttachmentLoadOp::LOAD
+        } else {
+            vk::AttachmentLoadOp::DONT_CARE
+        };
+        let color_attachments = [vk::RenderingAttachmentInfo::default()
+            .image_view(output_view)
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(load_op)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        let rendering_info = vk::RenderingInfo::default()
+            .color_attachments(&color_attachments)
+            .render_area(vk::Rect2D {
+                offset: Default::default(),
+                extent,
+            })
+            .layer_count(1);
+
+        unsafe {
+            device.cmd_begin_rendering(command_buffer, &rendering_info);
+
+            self.render_state.apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_shaders(
+                command_buffer,
+                Self::VERTEX_SHADER_NAME,
+                fragment_shader_name,
+            );
+            shader_manager.push_descriptors(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                descriptor_writer,
+            );
+            if !push_constants.is_empty() {
+                device.cmd_push_constants(
+                    command_buffer,
+                    shader_manager.pipeline_layout,
+                    vk::ShaderStageFlags::ALL,
+                    Default::default(),
+                    push_constants,
+                );
+            }
+            device.cmd_draw(command_buffer, 3, 1, Default::default(), Default::default());
+
+            device.cmd_end_rendering(command_buffer);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use ash::vk;
+use glam::Vec4;
+
+use super::{gpu_data::GridPushConstants, render_state::RenderState, shader::ShaderManager, utils};
+
+/// Infinite ground grid on the XZ plane and a world axis gizmo in the bottom left corner.
+pub struct Gizmos {
+    pub does_show_grid: bool,
+    pub does_show_axis: bool,
+    pub grid_cell_size: f32,
+}
+
+impl Gizmos {
+    pub const GRID_SHADER_NAME: &'static str = "grid";
+    pub const AXIS_SHADER_NAME: &'static str = "axis_gizmo";
+
+    pub const GRID_MINOR_COLOR: Vec4 = Vec4::new(0.5, 0.5, 0.5, 0.35);
+    pub const GRID_MAJOR_COLOR: Vec4 = Vec4::new(0.7, 0.7, 0.7, 0.6);
+    pub const GRID_MAJOR_EVERY: f32 = 10.0;
+    /// The grid is fully transparent this far from the camera.
+    pub const GRID_FADE_DISTANCE: f32 = 100.0;
+    pub const AXIS_VERTICES_COUNT: u32 = 6;
+
+    pub fn new() -> Self {
+        Self {
+            does_show_grid: true,
+            does_show_axis: true,
+            grid_cell_size: 1.0,
+        }
+    }
+
+    /// Records inside the scene pass after the opaque meshes, the grid is depth tested
+    /// against them and the gizmo is drawn on top.
+    pub fn render(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+    ) {
+        if self.does_show_grid {
+            let render_state = RenderState {
+                cull_mode: vk::CullModeFlags::NONE,
+                depth_write: false,
+                blend_equation: Some(Self::alpha_blend_equation()),
+                extra_write_mask: vk::ColorComponentFlags::empty(),
+                ..*render_state
+            };
+            render_state.apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_program(command_buffer, Self::GRID_SHADER_NAME);
+
+            let push_constants = GridPushConstants {
+                minor_color: Self::GRID_MINOR_COLOR,
+                major_color: Self::GRID_MAJOR_COLOR,
+                parameters: Vec4::new(
+                    self.grid_cell_size,
+                    Self::GRID_MAJOR_EVERY,
+                    Self::GRID_FADE_DISTANCE,
+                    Default::default(),
+                ),
+            };
+
+            unsafe {
+                device.cmd_push_constants(
+                    command_buffer,
+                    shader_manager.pipeline_layout,
+                    vk::ShaderStageFlags::ALL,
+                    Default::default(),
+                    utils::as_bytes(&push_constants),
+                );
+                device.cmd_draw(command_buffer, 3, 1, Default::default(), Default::default());
+            }
+        }
+
+        if self.does_show_axis {
+            let render_state = RenderState {
+                topology: vk::PrimitiveTopology::LINE_LIST,
+                cull_mode: vk::CullModeFlags::NONE,
+                depth_test: false,
+                depth_write: false,
+                extra_write_mask: vk::ColorComponentFlags::empty(),
+                ..*render_state
+            };
+            render_state.apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_program(command_buffer, Self::AXIS_SHADER_NAME);
+
+            unsafe {
+                device.cmd_draw(
+                    command_buffer,
+                    Self::AXIS_VERTICES_COUNT,
+                    1,
+                    Default::default(),
+                    Default::default(),
+                );
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn alpha_blend_equation() -> vk::ColorBlendEquationEXT {
+        vk::ColorBlendEquationEXT {
+            src_color_blend_factor: vk::BlendFactor::SRC_ALPHA,
+            dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            alpha_blend_op: vk::BlendOp::ADD,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use glam::{Mat4, UVec4, Vec2, Vec4};
+
+use super::{camera::Camera, shadows::ShadowManager};
+
+/// Layout must match `FrameData` in the shaders (std140).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct FrameData {
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub view_projection: Mat4,
+    pub inverse_projection: Mat4,
+    pub camera_position: Vec4,
+    /// `xy` - screen size, `zw` - light tiles count.
+    pub screen: UVec4,
+    /// `x` - lights count, `yzw` - see `Environment::light_info`.
+    pub light_info: UVec4,
+}
+
+impl FrameData {
+    pub fn new(camera: &Camera, screen: UVec4, light_info: UVec4) -> Self {
+        let view = camera.view();
+        let projection = camera.projection();
+
+        Self {
+            view,
+            projection,
+            view_projection: projection * view,
+            inverse_projection: projection.inverse(),
+            camera_position: camera.position.extend(1.0),
+            screen,
+            light_info,
+        }
+    }
+}
+
+/// Layout must match `Instance` in the shaders (std430).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct GpuInstance {
+    pub model: Mat4,
+    /// Object space, `w` - radius.
+    pub bounding_sphere: Vec4,
+    /// `x` - indices count, `y` - batch index, `z` - first draw command of the batch,
+    /// `w` - first index of the selected level of detail.
+    pub draw: UVec4,
+    /// See `VertexFormat::dequantization`.
+    pub dequantization: Vec4,
+    /// See `VertexFormat::to_gpu`.
+    pub vertex_format: UVec4,
+    /// See `Material::base_color`.
+    pub base_color: Vec4,
+    /// `x` - fragment output, see `BlendMode::to_gpu`.
+    pub material: UVec4,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CullingPushConstants {
+    pub frustum_planes: [Vec4; 6],
+    pub instances_count: u32,
+    _padding: [u32; 3],
+}
+
+impl CullingPushConstants {
+    pub fn new(frustum_planes: [Vec4; 6], instances_count: u32) -> Self {
+        Self {
+            frustum_planes,
+            instances_count,
+            _padding: Default::default(),
+        }
+    }
+}
+
+/// Layout must match `ShadowData` in the shaders (std430).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ShadowData {
+    pub light_view_projections: [Mat4; ShadowManager::CASCADES_COUNT],
+    /// View space distances where every cascade ends.
+    pub split_depths: Vec4,
+    /// `x` - cascades count, `y` - debug view enabled, `z` - index of the shadow casting light.
+    pub info: UVec4,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ShadowPushConstants {
+    pub cascade_index: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TonemapPushConstants {
+    pub exposure: f32,
+    pub tonemapper: u32,
+    pub display_mode: u32,
+    pub paper_white_nits: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct PostPushConstants {
+    /// Meaning depends on the effect, see `PostEffectKind`.
+    pub parameters: Vec4,
+    pub texel_size: Vec2,
+    /// Blur direction, unused by the other passes.
+    pub direction: Vec2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct DebugPushConstants {
+    pub color: Vec4,
+    /// World space box of the bounds view.
+    pub bounds_min: Vec4,
+    pub bounds_max: Vec4,
+    /// Instance the normals view draws.
+    pub instance_index: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GridPushConstants {
+    pub minor_color: Vec4,
+    pub major_color: Vec4,
+    /// `x` - cell size, `y` - cells between major lines, `z` - fade distance.
+    pub parameters: Vec4,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct UiPushConstants {
+    /// In the units of the vertex positions, points for egui and pixels for text.
+    pub screen_size: Vec2,
+    /// `DisplayMode` the swapchain is encoded for.
+    pub display_mode: u32,
+    pub paper_white_nits: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct OutlinePushConstants {
+    /// Distance in pixels to the neighbours a jump flood step looks at.
+    pub step: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SsaoPushConstants {
+    /// View space distance the hemisphere samples reach.
+    pub radius: f32,
+    pub intensity: f32,
+    /// Depth difference below which samples don't occlude, against self-occlusion.
+    pub bias: f32,
+    pub samples_count: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct PrefilterPushConstants {
+    /// Of the mip being written, 0 to 1.
+    pub roughness: f32,
+    /// Face size of the environment mip 0, to pick the mip every sample reads from.
+    pub source_size: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct GradientPushConstants {
+    pub top: Vec4,
+    pub bottom: Vec4,
+}
@@ -0,0 +1,685 @@
+use std::collections::HashMap;
+
+use ash::vk;
+use glam::{Mat4, UVec4, Vec4};
+
+use super::{
+    allocator::{AllocatedBuffer, Allocator},
+    camera::Camera,
+    compute::ComputeDispatch,
+    gpu_data::{CullingPushConstants, GpuInstance},
+    objects::{instance::MeshInstance, material::BlendMode, mesh::MeshMetadata, ObjectType},
+    register::Register,
+    render_graph::{
+        BufferAccess, BufferHandle, CompiledGraph, PassHandle, RenderGraph, TransientPool,
+    },
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    transparency::TransparencyMode,
+    Id,
+};
+
+/// Instances of one mesh drawn at the same level of detail and blend mode, consecutive
+/// in the instance buffer with the visible ones first.
+#[derive(Clone, Copy)]
+struct DrawBatch {
+    mesh_index: usize,
+    lod_index: usize,
+    blend_mode: BlendMode,
+    first_index: u32,
+    indices_count: u32,
+    first_instance: u32,
+    instances_count: u32,
+    visible_count: u32,
+}
+
+/// Instance with what `pack` orders it by.
+struct SortedInstance<'a> {
+    mesh_index: usize,
+    lod_index: usize,
+    is_visible: bool,
+    /// Zero unless the instance is sorted back to front.
+    camera_distance: f32,
+    instance: &'a MeshInstance,
+}
+
+impl SortedInstance<'_> {
+    #[inline(always)]
+    fn is_transparent(&self) -> bool {
+        self.instance.material.blend_mode.is_transparent()
+    }
+
+    /// Same mesh, level of detail and blend mode, visibility only orders them in the batch.
+    #[inline(always)]
+    fn shares_batch(&self, other: &Self) -> bool {
+        self.mesh_index == other.mesh_index
+            && self.lod_index == other.lod_index
+            && self.instance.material.blend_mode == other.instance.material.blend_mode
+    }
+
+    /// Groups instances which can share a batch, visible ones first.
+    #[inline(always)]
+    fn batch_key(&self) -> (BlendMode, usize, usize, bool) {
+        (
+            self.instance.material.blend_mode,
+            self.mesh_index,
+            self.lod_index,
+            !self.is_visible,
+        )
+    }
+}
+
+/// Results of the last packed frame. In GPU-driven mode visibility is only known
+/// on the GPU, so every instance counts as drawn.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CullingStats {
+    pub instances: u32,
+    pub drawn: u32,
+    pub culled: u32,
+    pub draw_calls: u32,
+    /// Over `MAX_INSTANCES` or `MAX_BATCHES`, not drawn at all.
+    pub dropped: u32,
+}
+
+/// Graph resources of the culling pass. Passes drawing the visible instances read
+/// `draw_buffers` indirectly.
+pub struct InstanceCulling {
+    pass: PassHandle,
+    /// Draw commands and counts.
+    pub draw_buffers: [BufferHandle; 2],
+}
+
+/// Uploads instance transforms grouped by mesh every frame and draws them, either
+/// straight from the CPU or GPU-driven through compute frustum culling and indirect draws.
+/// Transparent instances are packed after the opaque ones. In the sorted mode every one
+/// of them gets its own batch, back to front from the camera.
+pub struct InstanceManager {
+    instance_buffer: AllocatedBuffer,
+    /// One `VkDrawIndexedIndirectCommand` slot per instance, batches start at their first instance.
+    draw_command_buffer: AllocatedBuffer,
+    /// Visible instances of every batch, written by the culling shader.
+    draw_count_buffer: AllocatedBuffer,
+    gpu_instances: Vec<GpuInstance>,
+    /// Scene ids of `gpu_instances`, in the same order.
+    instance_ids: Vec<Id>,
+    batches: Vec<DrawBatch>,
+    frustum_planes: [Vec4; 6],
+    stats: CullingStats,
+    pub is_gpu_driven: bool,
+}
+
+impl InstanceManager {
+    pub const MAX_INSTANCES: usize = 65536;
+    pub const MAX_BATCHES: usize = 4096;
+    pub const WORKGROUP_SIZE: u32 = 64;
+    pub const CULLING_SHADER_NAME: &'static str = "instance_culling";
+
+    pub const INSTANCES_SLOT: u32 = 3;
+    pub const DRAW_COMMANDS_SLOT: u32 = 4;
+    pub const DRAW_COUNTS_SLOT: u32 = 5;
+
+    /// Projected radius relative to half the screen height below which the next
+    /// level of detail is used.
+    pub const LOD_SCREEN_SIZES: [f32; MeshMetadata::MAX_LODS - 1] = [0.5, 0.25, 0.1];
+
+    pub fn new(allocator: &Allocator) -> Self {
+        let instance_buffer = allocator.allocate_uninit_buffer(
+            (Self::MAX_INSTANCES * std::mem::size_of::<GpuInstance>()) as _,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            ObjectType::Instance,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+        let draw_command_buffer = allocator.allocate_uninit_buffer(
+            (Self::MAX_INSTANCES * std::mem::size_of::<vk::DrawIndexedIndirectCommand>()) as _,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER,
+            ObjectType::Instance,
+            Default::default(),
+        );
+        let draw_count_buffer = allocator.allocate_uninit_buffer(
+            (Self::MAX_BATCHES * std::mem::size_of::<u32>()) as _,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::INDIRECT_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            ObjectType::Instance,
+            Default::default(),
+        );
+
+        Self {
+            instance_buffer,
+            draw_command_buffer,
+            draw_count_buffer,
+            gpu_instances: Vec::with_capacity(Self::MAX_INSTANCES),
+            instance_ids: Vec::with_capacity(Self::MAX_INSTANCES),
+            batches: Default::default(),
+            frustum_planes: Default::default(),
+            stats: Default::default(),
+            is_gpu_driven: Default::default(),
+        }
+    }
+
+    /// Instances of meshes that aren't uploaded yet are skipped. Every instance gets a level
+    /// of detail by its size on screen, without GPU-driven mode they are also culled here.
+    pub fn pack(
+        &mut self,
+        allocator: &Allocator,
+        register: &Register,
+        camera: &Camera,
+        transparency_mode: TransparencyMode,
+    ) -> CullingStats {
+        self.frustum_planes = camera.frustum_planes();
+        let is_sorted = transparency_mode == TransparencyMode::Sorted;
+
+        let meshes = register.get_meshes();
+        let mesh_indices = meshes
+            .iter()
+            .enumerate()
+            .map(|(mesh_index, mesh)| (mesh.id, mesh_index))
+            .collect::<HashMap<Id, usize>>();
+
+        let mut sorted_instances = register
+            .get_instances()
+            .iter()
+            .filter_map(|instance| {
+                let mesh_index = *mesh_indices.get(&instance.mesh_id)?;
+                let metadata = &meshes[mesh_index].metadata;
+                let is_visible = self.is_gpu_driven
+                    || Self::is_visible(metadata, instance, &self.frustum_planes);
+                let lod_index = Self::select_lod(metadata, instance, camera);
+                let blend_mode = instance.material.blend_mode;
+                // Only sorted transparency cares about the order of the instances.
+                let camera_distance = if is_sorted && blend_mode.is_transparent() {
+                    metadata
+                        .bounding_sphere
+                        .transformed(&instance.transform)
+                        .center
+                        .distance(camera.position)
+                } else {
+                    Default::default()
+                };
+
+                Some(SortedInstance {
+                    mesh_index,
+                    lod_index,
+                    is_visible,
+                    camera_distance,
+                    instance,
+                })
+            })
+            .collect::<Vec<_>>();
+        sorted_instances.sort_unstable_by(|first, second| {
+            first
+                .is_transparent()
+                .cmp(&second.is_transparent())
+                .then_with(|| second.camera_distance.total_cmp(&first.camera_distance))
+                .then_with(|| first.batch_key().cmp(&second.batch_key()))
+        });
+        let packed_count = sorted_instances.len();
+
+        // Sorted, every transparent instance is a batch of its own. Whatever doesn't fit is
+        // dropped from the farthest ones, which cover the least.
+        if is_sorted {
+            let opaque_count = sorted_instances
+                .iter()
+                .take_while(|sorted_instance| !sorted_instance.is_transparent())
+                .count();
+            let opaque_batches_count = sorted_instances[..opaque_count]
+                .windows(2)
+                .filter(|pair| !pair[0].shares_batch(&pair[1]))
+                .count()
+                + (opaque_count > 0) as usize;
+            let transparent_budget = Self::MAX_INSTANCES
+                .saturating_sub(opaque_count)
+                .min(Self::MAX_BATCHES.saturating_sub(opaque_batches_count));
+            let farthest_count = (packed_count - opaque_count).saturating_sub(transparent_budget);
+            sorted_instances.drain(opaque_count..opaque_count + farthest_count);
+        }
+        sorted_instances.truncate(Self::MAX_INSTANCES);
+
+        self.gpu_instances.clear();
+        self.instance_ids.clear();
+        self.batches.clear();
+        for SortedInstance {
+            mesh_index,
+            lod_index,
+            is_visible,
+            instance,
+            ..
+        } in sorted_instances
+        {
+            let metadata = meshes[mesh_index].metadata;
+            let lod = metadata.lods()[lod_index];
+            let blend_mode = instance.material.blend_mode;
+
+            let is_new_batch = self.batches.last().map_or(true, |batch| {
+                batch.mesh_index != mesh_index
+                    || batch.lod_index != lod_index
+                    || batch.blend_mode != blend_mode
+                    || (is_sorted && blend_mode.is_transparent())
+            });
+            if is_new_batch {
+                if self.batches.len() == Self::MAX_BATCHES {
+                    break;
+                }
+
+                self.batches.push(DrawBatch {
+                    mesh_index,
+                    lod_index,
+                    blend_mode,
+                    first_index: lod.first_index,
+                    indices_count: lod.indices_count,
+                    first_instance: self.gpu_instances.len() as _,
+                    instances_count: Default::default(),
+                    visible_count: Default::default(),
+                });
+            }
+
+            let batch_index = self.batches.len() - 1;
+            let batch = &mut self.batches[batch_index];
+            batch.instances_count += 1;
+            batch.visible_count += is_visible as u32;
+
+            self.gpu_instances.push(GpuInstance {
+                model: instance.transform,
+                bounding_sphere: metadata.bounding_sphere.to_gpu(),
+                draw: UVec4::new(
+                    lod.indices_count,
+                    batch_index as _,
+                    batch.first_instance,
+                    lod.first_index,
+                ),
+                dequantization: metadata.dequantization(),
+                vertex_format: metadata.vertex_format.to_gpu(),
+                base_color: instance.material.base_color,
+                material: UVec4::new(blend_mode.to_gpu(transparency_mode), 0, 0, 0),
+            });
+            self.instance_ids.push(instance.id);
+        }
+
+        allocator.write_buffer(&self.instance_buffer, &self.gpu_instances);
+
+        let instances_count = self.gpu_instances.len() as u32;
+        let drawn = self.batches.iter().map(|batch| batch.visible_count).sum();
+        self.stats = CullingStats {
+            instances: instances_count,
+            drawn,
+            culled: instances_count - drawn,
+            draw_calls: self
+                .batches
+                .iter()
+                .filter(|batch| batch.visible_count > 0)
+                .count() as _,
+            dropped: (packed_count - self.gpu_instances.len()) as _,
+        };
+
+        self.stats
+    }
+
+    #[inline(always)]
+    pub fn stats(&self) -> CullingStats {
+        self.stats
+    }
+
+    /// Mesh index, index in the instance buffer and transform of every instance
+    /// `draw_visible` and `draw_visible_transparent` draw.
+    pub fn visible_instances(&self) -> impl Iterator<Item = (usize, u32, Mat4)> + '_ {
+        self.batches.iter().flat_map(|batch| {
+            (batch.first_instance..batch.first_instance + batch.visible_count).map(
+                |instance_index| {
+                    (
+                        batch.mesh_index,
+                        instance_index,
+                        self.gpu_instances[instance_index as usize].model,
+                    )
+                },
+            )
+        })
+    }
+
+    /// Scene ids in the order of the instance buffer of the last packed frame.
+    #[inline(always)]
+    pub fn instance_ids(&self) -> &[Id] {
+        &self.instance_ids
+    }
+
+    /// Scene id of the instance at `instance_index` in the buffer of the last packed frame.
+    #[inline(always)]
+    pub fn instance_id(&self, instance_index: u32) -> Option<Id> {
+        self.instance_ids.get(instance_index as usize).copied()
+    }
+
+    #[inline(always)]
+    pub fn write_descriptors(&self, descriptor_writer: DescriptorWriter) -> DescriptorWriter {
+        descriptor_writer.storage_buffer(Self::INSTANCES_SLOT, &self.instance_buffer)
+    }
+
+    /// Declares the culling pass, `None` outside of GPU-driven mode where nothing is culled
+    /// on the GPU.
+    pub fn add_culling_pass(&self, graph: &mut RenderGraph) -> Option<InstanceCulling> {
+        if !self.is_gpu_driven {
+            return None;
+        }
+
+        let draw_commands = graph.import_buffer("draw_commands", self.draw_command_buffer.buffer);
+        let draw_counts = graph.import_buffer("draw_counts", self.draw_count_buffer.buffer);
+        let pass = graph
+            .add_pass("instance_culling")
+            .buffer(draw_commands, BufferAccess::COMPUTE_STORAGE_WRITE)
+            .buffer(draw_counts, BufferAccess::FILL_THEN_COMPUTE_WRITE)
+            .build();
+
+        Some(InstanceCulling {
+            pass,
+            draw_buffers: [draw_commands, draw_counts],
+        })
+    }
+
+    /// Must be recorded outside of rendering. Nothing is recorded without `culling` or
+    /// instances.
+    pub fn cull(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        culling: Option<&InstanceCulling>,
+    ) {
+        let Some(culling) = culling else {
+            return;
+        };
+        graph.begin_pass(device, command_buffer, culling.pass, pool);
+        if self.gpu_instances.is_empty() {
+            return;
+        }
+
+        unsafe {
+            device.cmd_fill_buffer(
+                command_buffer,
+                self.draw_count_buffer.buffer,
+                Default::default(),
+                vk::WHOLE_SIZE,
+                Default::default(),
+            );
+
+            let memory_barriers = [vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::TRANSFER)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                )];
+            let dependency_info = vk::DependencyInfo::default().memory_barriers(&memory_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+        }
+
+        let instances_count = self.gpu_instances.len() as u32;
+        ComputeDispatch::new(
+            Self::CULLING_SHADER_NAME,
+            [instances_count.div_ceil(Self::WORKGROUP_SIZE), 1, 1],
+        )
+        .descriptors(
+            self.write_descriptors(DescriptorWriter::new())
+                .storage_buffer(Self::DRAW_COMMANDS_SLOT, &self.draw_command_buffer)
+                .storage_buffer(Self::DRAW_COUNTS_SLOT, &self.draw_count_buffer),
+        )
+        .push_constants(&CullingPushConstants::new(
+            self.frustum_planes,
+            instances_count,
+        ))
+        .record(device, shader_manager, command_buffer);
+    }
+
+    /// Draws the opaque batches, what survived culling in GPU-driven mode and everything
+    /// otherwise. Shaders and descriptors must be bound by the caller.
+    #[inline(always)]
+    pub fn draw_visible(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        register: &Register,
+    ) {
+        self.draw_visible_batches(
+            device,
+            shader_manager,
+            command_buffer,
+            register,
+            false,
+            |_| (),
+        );
+    }
+
+    /// Same as `draw_visible` for the transparent batches, which follow in packed order.
+    /// `set_blend_mode` is called before the first batch and whenever the mode changes.
+    #[inline(always)]
+    pub fn draw_visible_transparent(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        register: &Register,
+        set_blend_mode: impl FnMut(BlendMode),
+    ) {
+        self.draw_visible_batches(
+            device,
+            shader_manager,
+            command_buffer,
+            register,
+            true,
+            set_blend_mode,
+        );
+    }
+
+    /// Draws every packed instance with one instanced call per batch, for passes
+    /// that don't look through the camera.
+    pub fn draw(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        register: &Register,
+    ) {
+        self.batches.iter().for_each(|batch| unsafe {
+            Self::bind_geometry(device, shader_manager, command_buffer, register, batch);
+
+            device.cmd_draw_indexed(
+                command_buffer,
+                batch.indices_count,
+                batch.instances_count,
+                batch.first_index,
+                Default::default(),
+                batch.first_instance,
+            );
+        });
+    }
+
+    #[inline(always)]
+    pub fn destroy(&self, allocator: &Allocator) {
+        allocator.destroy_buffer(&self.instance_buffer);
+        allocator.destroy_buffer(&self.draw_command_buffer);
+        allocator.destroy_buffer(&self.draw_count_buffer);
+    }
+
+    /// Visible instances come first in a batch, so drawing a prefix skips the culled ones.
+    /// In GPU-driven mode the prefix length comes from the culling shader.
+    fn draw_visible_batches(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        register: &Register,
+        is_transparent: bool,
+        mut set_blend_mode: impl FnMut(BlendMode),
+    ) {
+        let stride = std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32;
+        let mut bound_blend_mode = None;
+
+        self.batches
+            .iter()
+            .enumerate()
+            .filter(|(_, batch)| batch.blend_mode.is_transparent() == is_transparent)
+            .filter(|(_, batch)| self.is_gpu_driven || batch.visible_count > 0)
+            .for_each(|(batch_index, batch)| unsafe {
+                if bound_blend_mode != Some(batch.blend_mode) {
+                    bound_blend_mode = Some(batch.blend_mode);
+                    set_blend_mode(batch.blend_mode);
+                }
+                Self::bind_geometry(device, shader_manager, command_buffer, register, batch);
+
+                if self.is_gpu_driven {
+                    device.cmd_draw_indexed_indirect_count(
+                        command_buffer,
+                        self.draw_command_buffer.buffer,
+                        (batch.first_instance * stride) as _,
+                        self.draw_count_buffer.buffer,
+                        (batch_index * std::mem::size_of::<u32>()) as _,
+                        batch.instances_count,
+                        stride,
+                    );
+                } else {
+                    device.cmd_draw_indexed(
+                        command_buffer,
+                        batch.indices_count,
+                        batch.visible_count,
+                        batch.first_index,
+                        Default::default(),
+                        batch.first_instance,
+                    );
+                }
+            });
+    }
+
+    /// Sphere first as it's cheaper, the box is tighter for long and flat meshes.
+    #[inline(always)]
+    fn is_visible(
+        metadata: &MeshMetadata,
+        instance: &MeshInstance,
+        frustum_planes: &[Vec4; 6],
+    ) -> bool {
+        metadata
+            .bounding_sphere
+            .transformed(&instance.transform)
+            .intersects_frustum(frustum_planes)
+            && metadata
+                .aabb
+                .transformed(&instance.transform)
+                .intersects_frustum(frustum_planes)
+    }
+
+    /// Level 0 when the camera is inside the bounds, then by `LOD_SCREEN_SIZES`
+    /// clamped to the levels the mesh has.
+    #[inline(always)]
+    fn select_lod(metadata: &MeshMetadata, instance: &MeshInstance, camera: &Camera) -> usize {
+        let bounding_sphere = metadata.bounding_sphere.transformed(&instance.transform);
+        let distance = bounding_sphere.center.distance(camera.position);
+        if distance <= bounding_sphere.radius {
+            return Default::default();
+        }
+
+        let screen_size = bounding_sphere.radius / (distance * (camera.fov_y * 0.5).tan());
+        Self::LOD_SCREEN_SIZES
+            .iter()
+            .take_while(|&&lod_screen_size| screen_size < lod_screen_size)
+            .count()
+            .min(metadata.lods().len() - 1)
+    }
+
+    /// Also sets the vertex input, meshes can differ in vertex format.
+    #[inline(always)]
+    fn bind_geometry(
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        register: &Register,
+        batch: &DrawBatch,
+    ) {
+        let mesh_index = batch.mesh_index;
+        let mesh = &register.get_meshes()[mesh_index];
+        mesh.shader_layout
+            .set_vertex_input(&shader_manager.shader_object, command_buffer);
+
+        unsafe {
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                Default::default(),
+                &register.get_buffers()[mesh_index..=mesh_index],
+                &register.get_offsets()[mesh_index..=mesh_index],
+            );
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                mesh.index_buffer.buffer,
+                Default::default(),
+                mesh.metadata.index_type(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::no_engine::objects::{
+        bounds::{Aabb, BoundingSphere},
+        mesh::MeshLod,
+    };
+
+    /// Unit sphere at the origin with `lods_count` levels of detail.
+    fn metadata(lods_count: usize) -> MeshMetadata {
+        let lods = vec![MeshLod::default(); lods_count];
+
+        MeshMetadata::new(
+            Id::new(),
+            Default::default(),
+            Default::default(),
+            &lods,
+            Aabb::default(),
+            BoundingSphere {
+                center: Vec3::ZERO,
+                radius: 1.0,
+            },
+        )
+    }
+
+    fn camera_at(distance: f32) -> Camera {
+        Camera {
+            position: Vec3::new(0.0, 0.0, distance),
+            ..Camera::new(1.0)
+        }
+    }
+
+    #[test]
+    fn selects_coarser_levels_with_distance() {
+        let metadata = metadata(MeshMetadata::MAX_LODS);
+        let instance = MeshInstance::new(metadata.id, Mat4::IDENTITY);
+
+        // Projected radius at the default field of view is about 1.73 over the distance.
+        let selected = [0.5, 3.0, 5.0, 10.0, 100.0].map(|distance| {
+            InstanceManager::select_lod(&metadata, &instance, &camera_at(distance))
+        });
+
+        assert_eq!(selected, [0, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn scales_the_bounds_with_the_transform() {
+        let metadata = metadata(MeshMetadata::MAX_LODS);
+        let instance = MeshInstance::new(metadata.id, Mat4::from_scale(Vec3::splat(10.0)));
+
+        assert_eq!(
+            InstanceManager::select_lod(&metadata, &instance, &camera_at(10.0)),
+            0
+        );
+    }
+
+    #[test]
+    fn clamps_to_the_levels_of_the_mesh() {
+        let metadata = metadata(2);
+        let instance = MeshInstance::new(metadata.id, Mat4::IDENTITY);
+
+        assert_eq!(
+            InstanceManager::select_lod(&metadata, &instance, &camera_at(100.0)),
+            1
+        );
+    }
+}
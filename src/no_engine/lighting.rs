@@ -0,0 +1,121 @@
+use ash::vk;
+
+use super::{
+    allocator::{AllocatedBuffer, Allocator},
+    compute::ComputeDispatch,
+    objects::{
+        light::{GpuLight, Light},
+        ObjectType,
+    },
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+};
+
+/// Packs scene lights into a GPU buffer every frame and bins them into screen
+/// tiles with a compute pass, so fragments only walk the lights touching them.
+pub struct LightManager {
+    light_buffer: AllocatedBuffer,
+    tile_light_buffer: AllocatedBuffer,
+    tiles_count: vk::Extent2D,
+    gpu_lights: Vec<GpuLight>,
+}
+
+impl LightManager {
+    pub const MAX_LIGHTS: usize = 1024;
+    pub const TILE_SIZE: u32 = 16;
+    /// First slot of every tile holds the count, so a tile occupies 256 indices.
+    pub const MAX_LIGHTS_PER_TILE: u32 = 255;
+    pub const CULLING_SHADER_NAME: &'static str = "light_culling";
+
+    pub const LIGHTS_SLOT: u32 = 0;
+    pub const TILE_LIGHTS_SLOT: u32 = 1;
+
+    pub fn new(allocator: &Allocator, extent: vk::Extent2D) -> Self {
+        let light_buffer = allocator.allocate_uninit_buffer(
+            (std::mem::size_of::<GpuLight>() * Self::MAX_LIGHTS) as _,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            ObjectType::Light,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+
+        let tiles_count = vk::Extent2D {
+            width: extent.width.div_ceil(Self::TILE_SIZE),
+            height: extent.height.div_ceil(Self::TILE_SIZE),
+        };
+        let tile_light_buffer = allocator.allocate_uninit_buffer(
+            (tiles_count.width
+                * tiles_count.height
+                * (Self::MAX_LIGHTS_PER_TILE + 1)
+                * std::mem::size_of::<u32>() as u32) as _,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            ObjectType::Light,
+            Default::default(),
+        );
+
+        Self {
+            light_buffer,
+            tile_light_buffer,
+            tiles_count,
+            gpu_lights: Vec::with_capacity(Self::MAX_LIGHTS),
+        }
+    }
+
+    #[inline(always)]
+    pub fn tiles_count(&self) -> vk::Extent2D {
+        self.tiles_count
+    }
+
+    #[inline(always)]
+    pub fn tile_light_buffer(&self) -> &AllocatedBuffer {
+        &self.tile_light_buffer
+    }
+
+    /// Returns the amount of lights written, everything above `MAX_LIGHTS` is dropped.
+    #[inline(always)]
+    pub fn pack(&mut self, allocator: &Allocator, lights: &[Light]) -> u32 {
+        self.gpu_lights.clear();
+        self.gpu_lights.extend(
+            lights
+                .iter()
+                .take(Self::MAX_LIGHTS)
+                .map(|light| light.to_gpu()),
+        );
+
+        if !self.gpu_lights.is_empty() {
+            allocator.write_buffer(&self.light_buffer, &self.gpu_lights);
+        }
+
+        self.gpu_lights.len() as _
+    }
+
+    #[inline(always)]
+    pub fn write_descriptors(&self, descriptor_writer: DescriptorWriter) -> DescriptorWriter {
+        descriptor_writer
+            .storage_buffer(Self::LIGHTS_SLOT, &self.light_buffer)
+            .storage_buffer(Self::TILE_LIGHTS_SLOT, &self.tile_light_buffer)
+    }
+
+    /// Must be recorded outside of rendering. Writes the tile lists, the frame graph makes
+    /// them visible to the passes reading them.
+    pub fn cull(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        frame_data_buffer: &AllocatedBuffer,
+    ) {
+        ComputeDispatch::new(
+            Self::CULLING_SHADER_NAME,
+            [self.tiles_count.width, self.tiles_count.height, 1],
+        )
+        .descriptors(
+            self.write_descriptors(DescriptorWriter::new().uniform_buffer(frame_data_buffer)),
+        )
+        .record(device, shader_manager, command_buffer);
+    }
+
+    #[inline(always)]
+    pub fn destroy(&self, allocator: &Allocator) {
+        allocator.destroy_buffer(&self.light_buffer);
+        allocator.destroy_buffer(&self.tile_light_buffer);
+    }
+}
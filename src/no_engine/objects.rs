@@ -1,5 +1,16 @@
 pub enum ObjectType {
     Mesh,
+    Light,
+    Uniform,
+    Storage,
+    Instance,
+    Debug,
+    Ui,
 }
 
+pub mod bounds;
+pub mod instance;
+pub mod light;
+pub mod material;
 pub mod mesh;
+pub mod vertex_format;
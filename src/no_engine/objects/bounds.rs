@@ -0,0 +1,105 @@
+use glam::{Mat4, Vec3, Vec4};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Empty input gives a degenerate box at the origin.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let (min, max) = points.into_iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), point| (min.min(point), max.max(point)),
+        );
+
+        if min.cmpgt(max).any() {
+            return Default::default();
+        }
+
+        Self { min, max }
+    }
+
+    #[inline(always)]
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    #[inline(always)]
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Box enclosing the transformed one, stays axis aligned so it can grow under rotation.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        let center = transform.transform_point3(self.center());
+        let half_extents = self.half_extents();
+        let half_extents = transform.x_axis.truncate().abs() * half_extents.x
+            + transform.y_axis.truncate().abs() * half_extents.y
+            + transform.z_axis.truncate().abs() * half_extents.z;
+
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    /// Tests the corner furthest along each plane normal, see `Camera::frustum_planes`.
+    pub fn intersects_frustum(&self, frustum_planes: &[Vec4; 6]) -> bool {
+        frustum_planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), self.max, self.min);
+
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Centered on the box of the points, not minimal but cheap and stable.
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let center = Aabb::from_points(points.iter().copied()).center();
+        let radius = points
+            .iter()
+            .map(|point| point.distance_squared(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+
+        Self { center, radius }
+    }
+
+    /// Non-uniform scales are covered by the largest axis.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        let scale = transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(transform.y_axis.truncate().length())
+            .max(transform.z_axis.truncate().length());
+
+        Self {
+            center: transform.transform_point3(self.center),
+            radius: self.radius * scale,
+        }
+    }
+
+    #[inline(always)]
+    pub fn intersects_frustum(&self, frustum_planes: &[Vec4; 6]) -> bool {
+        frustum_planes
+            .iter()
+            .all(|plane| plane.truncate().dot(self.center) + plane.w >= -self.radius)
+    }
+
+    /// `w` - radius, the layout the shaders expect.
+    #[inline(always)]
+    pub fn to_gpu(self) -> Vec4 {
+        self.center.extend(self.radius)
+    }
+}
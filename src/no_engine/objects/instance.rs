@@ -0,0 +1,30 @@
+use glam::Mat4;
+
+use super::material::Material;
+use crate::no_engine::Id;
+
+/// Placement of an uploaded mesh in the scene, many of them can share one mesh.
+#[derive(Clone, Copy)]
+pub struct MeshInstance {
+    pub id: Id,
+    pub mesh_id: Id,
+    pub transform: Mat4,
+    pub material: Material,
+}
+
+impl MeshInstance {
+    #[inline(always)]
+    pub fn new(mesh_id: Id, transform: Mat4) -> Self {
+        Self {
+            id: Id::new(),
+            mesh_id,
+            transform,
+            material: Default::default(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+}
@@ -0,0 +1,110 @@
+use glam::{Vec3, Vec4};
+
+use crate::no_engine::Id;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LightType {
+    Directional,
+    Point,
+    Spot,
+}
+
+#[derive(Clone, Copy)]
+pub struct Light {
+    pub id: Id,
+    pub light_type: LightType,
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    /// Angles are in radians and measured from the light direction.
+    pub inner_cone_angle: f32,
+    pub outer_cone_angle: f32,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Vec3, intensity: f32) -> Self {
+        Self {
+            id: Id::new(),
+            light_type: LightType::Directional,
+            position: Vec3::ZERO,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            range: f32::INFINITY,
+            inner_cone_angle: Default::default(),
+            outer_cone_angle: Default::default(),
+        }
+    }
+
+    pub fn point(position: Vec3, color: Vec3, intensity: f32, range: f32) -> Self {
+        Self {
+            id: Id::new(),
+            light_type: LightType::Point,
+            position,
+            direction: Vec3::NEG_Y,
+            color,
+            intensity,
+            range,
+            inner_cone_angle: Default::default(),
+            outer_cone_angle: Default::default(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+        range: f32,
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    ) -> Self {
+        Self {
+            id: Id::new(),
+            light_type: LightType::Spot,
+            position,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            range,
+            inner_cone_angle: inner_cone_angle.min(outer_cone_angle),
+            outer_cone_angle,
+        }
+    }
+
+    #[inline(always)]
+    pub fn to_gpu(&self) -> GpuLight {
+        // Directional lights have no range, the shaders never read it for them.
+        let range = if self.range.is_finite() {
+            self.range
+        } else {
+            f32::MAX
+        };
+
+        GpuLight {
+            position_range: self.position.extend(range),
+            direction_type: self.direction.extend(self.light_type as u32 as f32),
+            color_intensity: self.color.extend(self.intensity),
+            cone_cosines: Vec4::new(
+                self.inner_cone_angle.cos(),
+                self.outer_cone_angle.cos(),
+                Default::default(),
+                Default::default(),
+            ),
+        }
+    }
+}
+
+/// Layout must match `Light` in the lighting shaders (std430).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct GpuLight {
+    pub position_range: Vec4,
+    pub direction_type: Vec4,
+    pub color_intensity: Vec4,
+    pub cone_cosines: Vec4,
+}
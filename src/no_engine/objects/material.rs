@@ -0,0 +1,90 @@
+use ash::vk;
+use glam::Vec4;
+
+use crate::no_engine::transparency::TransparencyMode;
+
+/// How the fragments of a material combine with what's behind them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    /// Over operator with the base color alpha as opacity.
+    AlphaBlend,
+    /// Adds the color weighted by alpha, for glows and particles.
+    Additive,
+}
+
+impl BlendMode {
+    /// Fragment shader writes the color as is.
+    pub const OUTPUT_OPAQUE: u32 = 0;
+    /// Fragment shader premultiplies the color by alpha.
+    pub const OUTPUT_PREMULTIPLIED: u32 = 1;
+    /// Fragment shader writes weighted accumulation and revealage.
+    pub const OUTPUT_WEIGHTED_BLENDED: u32 = 2;
+
+    #[inline(always)]
+    pub fn is_transparent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+
+    /// Equation for the premultiplied colors of the sorted mode, `None` when opaque.
+    pub fn blend_equation(self) -> Option<vk::ColorBlendEquationEXT> {
+        let destination_factor = match self {
+            BlendMode::Opaque => return None,
+            BlendMode::AlphaBlend => vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            BlendMode::Additive => vk::BlendFactor::ONE,
+        };
+
+        Some(vk::ColorBlendEquationEXT {
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: destination_factor,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE,
+            dst_alpha_blend_factor: destination_factor,
+            alpha_blend_op: vk::BlendOp::ADD,
+        })
+    }
+
+    /// What the lit fragment shader writes for this mode. Weighted blending accumulates
+    /// alpha blended and additive materials the same way.
+    #[inline(always)]
+    pub fn to_gpu(self, transparency_mode: TransparencyMode) -> u32 {
+        match (self, transparency_mode) {
+            (BlendMode::Opaque, _) => Self::OUTPUT_OPAQUE,
+            (_, TransparencyMode::Sorted) => Self::OUTPUT_PREMULTIPLIED,
+            (_, TransparencyMode::WeightedBlended) => Self::OUTPUT_WEIGHTED_BLENDED,
+        }
+    }
+}
+
+/// Surface properties of an instance, the base color multiplies the vertex colors.
+#[derive(Clone, Copy, Debug)]
+pub struct Material {
+    /// Linear RGB, `w` - opacity, ignored by opaque materials.
+    pub base_color: Vec4,
+    pub blend_mode: BlendMode,
+}
+
+impl Material {
+    #[inline(always)]
+    pub fn opaque(base_color: Vec4) -> Self {
+        Self {
+            base_color,
+            blend_mode: BlendMode::Opaque,
+        }
+    }
+
+    #[inline(always)]
+    pub fn transparent(base_color: Vec4, blend_mode: BlendMode) -> Self {
+        Self {
+            base_color,
+            blend_mode,
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::opaque(Vec4::ONE)
+    }
+}
@@ -1,28 +1,92 @@
-use glam::Vec3;
+use ash::vk;
+use glam::{Vec2, Vec3, Vec4};
 
+use super::{
+    bounds::{Aabb, BoundingSphere},
+    vertex_format::VertexFormat,
+};
 use crate::no_engine::Id;
 
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+/// Full precision, what gets uploaded depends on the mesh `VertexFormat`. Free of padding,
+/// the optimizer compares and hashes vertices as bytes, which is why the color isn't a `Vec4`.
 pub struct Vertex {
     pub position: Vec3,
     pub normal: Vec3,
-    pub color: Vec3,
+    pub color: [f32; 4],
+    pub uv: Vec2,
+}
+
+/// Range of the index buffer with one level of detail.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MeshLod {
+    pub first_index: u32,
+    pub indices_count: u32,
 }
 
 #[derive(Clone, Copy)]
 pub struct MeshMetadata {
     pub id: Id,
     pub vertices_count: u32,
+    /// Indices of all levels of detail together.
     pub indices_count: u32,
+    lods: [MeshLod; Self::MAX_LODS],
+    lods_count: u32,
+    pub vertex_format: VertexFormat,
+    /// Bounds are in object space.
+    pub aabb: Aabb,
+    pub bounding_sphere: BoundingSphere,
 }
 
 impl MeshMetadata {
-    pub fn new(id: Id, vertices_count: u32, indices_count: u32) -> Self {
-        Self {
+    pub const MAX_LODS: usize = 4;
+
+    /// Extra levels of detail past `MAX_LODS` are dropped.
+    pub fn new(
+        id: Id,
+        vertices_count: u32,
+        indices_count: u32,
+        lods: &[MeshLod],
+        aabb: Aabb,
+        bounding_sphere: BoundingSphere,
+    ) -> Self {
+        let mut metadata = Self {
             id,
             vertices_count,
             indices_count,
+            lods: Default::default(),
+            lods_count: lods.len().min(Self::MAX_LODS) as _,
+            vertex_format: Default::default(),
+            aabb,
+            bounding_sphere,
+        };
+        metadata.lods[..metadata.lods_count as usize]
+            .copy_from_slice(&lods[..metadata.lods_count as usize]);
+
+        metadata
+    }
+
+    /// 16-bit indices are enough to address every vertex of smaller meshes.
+    #[inline(always)]
+    pub fn index_type(&self) -> vk::IndexType {
+        if self.vertices_count < u16::MAX as u32 + 1 {
+            vk::IndexType::UINT16
+        } else {
+            vk::IndexType::UINT32
         }
     }
+
+    #[inline(always)]
+    pub fn dequantization(&self) -> Vec4 {
+        self.vertex_format.dequantization(&self.aabb)
+    }
+
+    /// Most detailed first.
+    #[inline(always)]
+    pub fn lods(&self) -> &[MeshLod] {
+        &self.lods[..self.lods_count as usize]
+    }
 }
 
 pub struct Mesh {
@@ -33,8 +97,31 @@ pub struct Mesh {
 }
 
 impl Mesh {
+    /// Whole index buffer is the only level of detail.
+    #[inline(always)]
     pub fn new(id: Id, vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
-        let metadata = MeshMetadata::new(id, vertices.len() as u32, indices.len() as u32);
+        let lods = [MeshLod {
+            first_index: Default::default(),
+            indices_count: indices.len() as _,
+        }];
+
+        Self::with_lods(id, vertices, indices, &lods)
+    }
+
+    /// `indices` holds the index lists of every level in `lods` one after another.
+    pub fn with_lods(id: Id, vertices: Vec<Vertex>, indices: Vec<u32>, lods: &[MeshLod]) -> Self {
+        let positions = vertices
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect::<Vec<_>>();
+        let metadata = MeshMetadata::new(
+            id,
+            vertices.len() as u32,
+            indices.len() as u32,
+            lods,
+            Aabb::from_points(positions.iter().copied()),
+            BoundingSphere::from_points(&positions),
+        );
         Self {
             metadata,
             vertices,
@@ -0,0 +1,305 @@
+use ash::vk;
+use glam::{UVec4, Vec2, Vec3, Vec4};
+
+use super::{bounds::Aabb, mesh::Vertex};
+
+/// Stored components are converted to floats by the vertex input.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PositionFormat {
+    #[default]
+    Float,
+    Half,
+    /// 16-bit signed normalized within the mesh bounds, dequantized per mesh in the shaders.
+    Normalized,
+}
+
+/// Values match `NORMAL_*` in the shaders.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalFormat {
+    #[default]
+    Float,
+    /// 16-bit signed normalized octahedral mapping.
+    Octahedral,
+}
+
+/// Layout of the uploaded vertices: position, normal, RGBA8 color and UV in that order.
+/// UVs are half floats unless positions are full floats.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VertexFormat {
+    pub position: PositionFormat,
+    pub normal: NormalFormat,
+}
+
+impl VertexFormat {
+    pub const FULL: Self = Self {
+        position: PositionFormat::Float,
+        normal: NormalFormat::Float,
+    };
+    pub const COMPACT: Self = Self {
+        position: PositionFormat::Normalized,
+        normal: NormalFormat::Octahedral,
+    };
+
+    pub const ATTRIBUTES_COUNT: usize = 4;
+
+    /// Formats and offsets of the position, normal, color and UV attributes.
+    pub fn attributes(&self) -> [(vk::Format, u32); Self::ATTRIBUTES_COUNT] {
+        let position_format = match self.position {
+            PositionFormat::Float => vk::Format::R32G32B32_SFLOAT,
+            PositionFormat::Half => vk::Format::R16G16B16A16_SFLOAT,
+            PositionFormat::Normalized => vk::Format::R16G16B16A16_SNORM,
+        };
+        let normal_format = match self.normal {
+            NormalFormat::Float => vk::Format::R32G32B32_SFLOAT,
+            NormalFormat::Octahedral => vk::Format::R16G16_SNORM,
+        };
+        let uv_format = match self.position {
+            PositionFormat::Float => vk::Format::R32G32_SFLOAT,
+            _ => vk::Format::R16G16_SFLOAT,
+        };
+
+        let mut offset = 0;
+        [
+            position_format,
+            normal_format,
+            vk::Format::R8G8B8A8_UNORM,
+            uv_format,
+        ]
+        .map(|format| {
+            let attribute = (format, offset);
+            offset += Self::format_size(format);
+
+            attribute
+        })
+    }
+
+    #[inline(always)]
+    pub fn stride(&self) -> u32 {
+        self.attributes()
+            .iter()
+            .map(|&(format, _)| Self::format_size(format))
+            .sum()
+    }
+
+    /// `xyz` - offset, `w` - scale applied to stored positions, identity unless normalized.
+    pub fn dequantization(&self, aabb: &Aabb) -> Vec4 {
+        match self.position {
+            PositionFormat::Normalized => aabb
+                .center()
+                .extend(aabb.half_extents().max_element().max(f32::EPSILON)),
+            _ => Vec4::W,
+        }
+    }
+
+    /// `x` - position format, `y` - normal format, the layout the shaders expect.
+    #[inline(always)]
+    pub fn to_gpu(self) -> UVec4 {
+        UVec4::new(
+            self.position as _,
+            self.normal as _,
+            Default::default(),
+            Default::default(),
+        )
+    }
+
+    /// `dequantization` must come from `dequantization` with the same bounds the
+    /// vertices are in.
+    pub fn encode(&self, vertices: &[Vertex], dequantization: Vec4) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(vertices.len() * self.stride() as usize);
+
+        for vertex in vertices {
+            match self.position {
+                PositionFormat::Float => {
+                    Self::write_floats(&mut bytes, &vertex.position.to_array())
+                }
+                PositionFormat::Half => {
+                    Self::write_halfs(&mut bytes, &vertex.position.extend(1.0).to_array())
+                }
+                PositionFormat::Normalized => {
+                    let position = (vertex.position - dequantization.truncate()) / dequantization.w;
+                    Self::write_snorms(&mut bytes, &position.extend(1.0).to_array());
+                }
+            }
+
+            match self.normal {
+                NormalFormat::Float => Self::write_floats(&mut bytes, &vertex.normal.to_array()),
+                NormalFormat::Octahedral => Self::write_snorms(
+                    &mut bytes,
+                    &Self::encode_octahedral(vertex.normal).to_array(),
+                ),
+            }
+
+            bytes.extend(
+                vertex
+                    .color
+                    .map(|channel| meshopt::quantize_unorm(channel, 8) as u8),
+            );
+
+            match self.position {
+                PositionFormat::Float => Self::write_floats(&mut bytes, &vertex.uv.to_array()),
+                _ => Self::write_halfs(&mut bytes, &vertex.uv.to_array()),
+            }
+        }
+
+        bytes
+    }
+
+    #[inline(always)]
+    fn format_size(format: vk::Format) -> u32 {
+        match format {
+            vk::Format::R32G32B32_SFLOAT => 12,
+            vk::Format::R16G16B16A16_SFLOAT
+            | vk::Format::R16G16B16A16_SNORM
+            | vk::Format::R32G32_SFLOAT => 8,
+            _ => 4,
+        }
+    }
+
+    /// Zero normals end up pointing along Z.
+    #[inline(always)]
+    fn encode_octahedral(normal: Vec3) -> Vec2 {
+        let length = normal.x.abs() + normal.y.abs() + normal.z.abs();
+        if length == 0.0 {
+            return Vec2::ZERO;
+        }
+
+        let normal = normal / length;
+        if normal.z >= 0.0 {
+            return normal.truncate();
+        }
+
+        (Vec2::ONE - Vec2::new(normal.y, normal.x).abs())
+            * Vec2::new(normal.x.signum(), normal.y.signum())
+    }
+
+    #[inline(always)]
+    fn write_floats(bytes: &mut Vec<u8>, values: &[f32]) {
+        values
+            .iter()
+            .for_each(|value| bytes.extend(value.to_le_bytes()));
+    }
+
+    #[inline(always)]
+    fn write_halfs(bytes: &mut Vec<u8>, values: &[f32]) {
+        values
+            .iter()
+            .for_each(|&value| bytes.extend(meshopt::quantize_half(value).to_le_bytes()));
+    }
+
+    #[inline(always)]
+    fn write_snorms(bytes: &mut Vec<u8>, values: &[f32]) {
+        values.iter().for_each(|&value| {
+            bytes.extend((meshopt::quantize_snorm(value, 16) as i16).to_le_bytes())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mirrors `decodeOctahedral` in the shaders.
+    fn decode_octahedral(encoded: Vec2) -> Vec3 {
+        let normal = encoded.extend(1.0 - encoded.x.abs() - encoded.y.abs());
+        let fold = (-normal.z).max(0.0);
+        let offset = Vec2::new(
+            if normal.x >= 0.0 { -fold } else { fold },
+            if normal.y >= 0.0 { -fold } else { fold },
+        );
+
+        (normal.truncate() + offset).extend(normal.z).normalize()
+    }
+
+    fn vertex(position: Vec3, normal: Vec3) -> Vertex {
+        Vertex {
+            position,
+            normal,
+            color: [1.0; 4],
+            uv: Vec2::new(0.25, 0.75),
+        }
+    }
+
+    #[test]
+    fn encodes_stride_bytes_per_vertex() {
+        let vertices = [
+            vertex(Vec3::ZERO, Vec3::Y),
+            vertex(Vec3::ONE, Vec3::NEG_Z),
+            vertex(Vec3::NEG_ONE, Vec3::X),
+        ];
+        let half = VertexFormat {
+            position: PositionFormat::Half,
+            normal: NormalFormat::Float,
+        };
+
+        for format in [VertexFormat::FULL, half, VertexFormat::COMPACT] {
+            let aabb = Aabb::from_points(vertices.iter().map(|vertex| vertex.position));
+            let bytes = format.encode(&vertices, format.dequantization(&aabb));
+
+            assert_eq!(bytes.len(), vertices.len() * format.stride() as usize);
+        }
+        assert_eq!(VertexFormat::FULL.stride(), 36);
+        assert_eq!(VertexFormat::COMPACT.stride(), 20);
+    }
+
+    #[test]
+    fn full_format_stores_position_as_floats() {
+        let position = Vec3::new(1.5, -2.0, 3.25);
+        let bytes = VertexFormat::FULL.encode(&[vertex(position, Vec3::Y)], Vec4::W);
+
+        let stored = bytes[..12]
+            .chunks(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(stored, position.to_array());
+    }
+
+    #[test]
+    fn normalizes_positions_within_bounds() {
+        let vertices = [
+            vertex(Vec3::new(-1.0, 2.0, 0.0), Vec3::Y),
+            vertex(Vec3::new(3.0, 4.0, 1.0), Vec3::Y),
+        ];
+        let aabb = Aabb::from_points(vertices.iter().map(|vertex| vertex.position));
+        let dequantization = VertexFormat::COMPACT.dequantization(&aabb);
+
+        assert_eq!(dequantization, Vec4::new(1.0, 3.0, 0.5, 2.0));
+
+        let bytes = VertexFormat::COMPACT.encode(&vertices, dequantization);
+        let first_x = i16::from_le_bytes([bytes[0], bytes[1]]);
+        assert_eq!(first_x, -i16::MAX);
+    }
+
+    #[test]
+    fn octahedral_normals_round_trip() {
+        let normals = [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(-1.0, 2.0, -3.0),
+            Vec3::new(0.3, -0.8, -0.1),
+            Vec3::new(-0.5, -0.5, -0.7),
+        ];
+
+        for normal in normals.map(Vec3::normalize) {
+            let encoded = VertexFormat::encode_octahedral(normal);
+            assert!(encoded.abs().max_element() <= 1.0, "{normal} -> {encoded}");
+
+            let decoded = decode_octahedral(encoded);
+            assert!(decoded.abs_diff_eq(normal, 1e-5), "{normal} -> {decoded}");
+        }
+    }
+
+    #[test]
+    fn zero_normal_encodes_along_z() {
+        let encoded = VertexFormat::encode_octahedral(Vec3::ZERO);
+
+        assert_eq!(encoded, Vec2::ZERO);
+        assert_eq!(decode_octahedral(encoded), Vec3::Z);
+    }
+}
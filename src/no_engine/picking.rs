@@ -0,0 +1,252 @@
+use ash::vk;
+
+use super::{
+    allocator::{AllocatedBuffer, Allocator},
+    objects::ObjectType,
+    swapchain::{ColorTarget, SwapchainManager},
+};
+
+/// With MSAA the ids are rendered multisampled and resolved by taking sample zero.
+struct IdTargets {
+    id: ColorTarget,
+    msaa_id: Option<ColorTarget>,
+}
+
+/// Instance ids written by the scene pass next to its color, read back under the cursor.
+/// Texels hold the index in the instance buffer plus one, zero where nothing was drawn.
+pub struct Picking {
+    targets: Option<IdTargets>,
+    readback_buffer: AllocatedBuffer,
+    /// Layout the resolved ids were left in, `UNDEFINED` until a frame fills them.
+    id_layout: vk::ImageLayout,
+}
+
+impl Picking {
+    pub const ID_FORMAT: vk::Format = vk::Format::R32_UINT;
+    pub const NO_INSTANCE: u32 = 0;
+
+    pub fn new(allocator: &Allocator) -> Self {
+        let readback_buffer = allocator.allocate_uninit_buffer(
+            std::mem::size_of::<u32>() as _,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            ObjectType::Storage,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+        );
+
+        Self {
+            targets: None,
+            readback_buffer,
+            id_layout: vk::ImageLayout::UNDEFINED,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.targets.is_some()
+    }
+
+    /// The device must be idle.
+    pub fn set_enabled(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        is_enabled: bool,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+    ) {
+        self.destroy_targets(device, allocator);
+        if is_enabled {
+            self.targets = Some(Self::create_targets(device, allocator, extent, samples));
+        }
+    }
+
+    /// Follows the scene targets when they change, does nothing while picking is off.
+    /// The device must be idle.
+    #[inline(always)]
+    pub fn recreate_targets(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+    ) {
+        if self.is_enabled() {
+            self.set_enabled(device, allocator, true, extent, samples);
+        }
+    }
+
+    /// Second color attachment of the scene pass, cleared to `NO_INSTANCE`.
+    pub fn color_attachment(&mut self) -> Option<vk::RenderingAttachmentInfo<'static>> {
+        let targets = self.targets.as_ref()?;
+        self.id_layout = vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL;
+
+        let attachment = vk::RenderingAttachmentInfo::default()
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .clear_value(vk::ClearValue {
+                color: vk::ClearColorValue {
+                    uint32: [Self::NO_INSTANCE; 4],
+                },
+            });
+
+        Some(match &targets.msaa_id {
+            Some(msaa_id) => attachment
+                .image_view(msaa_id.image_view)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .resolve_mode(vk::ResolveModeFlags::SAMPLE_ZERO)
+                .resolve_image_view(targets.id.image_view)
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            None => attachment
+                .image_view(targets.id.image_view)
+                .store_op(vk::AttachmentStoreOp::STORE),
+        })
+    }
+
+    /// Resolved ids first, followed by the multisampled ones with MSAA.
+    pub fn targets(&self) -> impl Iterator<Item = &ColorTarget> {
+        self.targets
+            .iter()
+            .flat_map(|targets| std::iter::once(&targets.id).chain(targets.msaa_id.as_ref()))
+    }
+
+    /// Copies the texel at `x`, `y` into the readback buffer and waits for the queue, so it
+    /// stalls and is meant for clicks rather than every frame. The last frame must be done.
+    /// `None` when picking is off or no frame was drawn with it yet.
+    pub fn read(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        command_buffer: vk::CommandBuffer,
+        queue: vk::Queue,
+        x: u32,
+        y: u32,
+    ) -> Option<u32> {
+        let targets = self
+            .targets
+            .as_ref()
+            .filter(|_| self.id_layout != vk::ImageLayout::UNDEFINED)?;
+        let image = targets.id.allocated_image.image;
+
+        let image_barriers = [vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)
+            .src_access_mask(vk::AccessFlags2::MEMORY_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+            .old_layout(self.id_layout)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .image(image)
+            .subresource_range(Self::subresource_range())];
+        let memory_barriers = [vk::MemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COPY)
+            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+            .dst_access_mask(vk::AccessFlags2::HOST_READ)];
+        let copy_regions = [vk::BufferImageCopy::default()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                layer_count: 1,
+                ..Default::default()
+            })
+            .image_offset(vk::Offset3D {
+                x: x as _,
+                y: y as _,
+                z: Default::default(),
+            })
+            .image_extent(vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            })];
+
+        let command_buffer_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        let command_buffers = [command_buffer];
+        let submit_infos = [vk::SubmitInfo::default().command_buffers(&command_buffers)];
+
+        unsafe {
+            device
+                .begin_command_buffer(command_buffer, &command_buffer_info)
+                .unwrap();
+
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.readback_buffer.buffer,
+                &copy_regions,
+            );
+            let dependency_info = vk::DependencyInfo::default().memory_barriers(&memory_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+
+            device.end_command_buffer(command_buffer).unwrap();
+            device
+                .queue_submit(queue, &submit_infos, Default::default())
+                .unwrap();
+            device.queue_wait_idle(queue).unwrap();
+        }
+        self.id_layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+
+        let mut value = [Self::NO_INSTANCE];
+        allocator.read_buffer(&self.readback_buffer, &mut value);
+
+        Some(value[0])
+    }
+
+    pub fn destroy(&mut self, device: &ash::Device, allocator: &Allocator) {
+        self.destroy_targets(device, allocator);
+        allocator.destroy_buffer(&self.readback_buffer);
+    }
+
+    fn create_targets(
+        device: &ash::Device,
+        allocator: &Allocator,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+    ) -> IdTargets {
+        let id = SwapchainManager::create_color_target(
+            device,
+            allocator,
+            Self::ID_FORMAT,
+            extent,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::STORAGE,
+        );
+        let msaa_id = (samples != vk::SampleCountFlags::TYPE_1).then(|| {
+            SwapchainManager::create_color_target(
+                device,
+                allocator,
+                Self::ID_FORMAT,
+                extent,
+                samples,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            )
+        });
+
+        IdTargets { id, msaa_id }
+    }
+
+    fn destroy_targets(&mut self, device: &ash::Device, allocator: &Allocator) {
+        if let Some(targets) = self.targets.take() {
+            targets.id.destroy(device, allocator);
+            if let Some(msaa_id) = targets.msaa_id {
+                msaa_id.destroy(device, allocator);
+            }
+        }
+        self.id_layout = vk::ImageLayout::UNDEFINED;
+    }
+
+    #[inline(always)]
+    fn subresource_range() -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            level_count: 1,
+            layer_count: 1,
+            ..Default::default()
+        }
+    }
+}
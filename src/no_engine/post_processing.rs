@@ -0,0 +1,597 @@
+use ash::vk;
+use glam::{Vec2, Vec4};
+
+use super::{
+    allocator::Allocator,
+    compute::{ComputeBarrier, ComputeDispatch},
+    fullscreen::FullscreenPass,
+    gpu_data::PostPushConstants,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    swapchain::{ColorTarget, SwapchainManager},
+    tonemapping::{DisplayMode, TonemapPass},
+    utils,
+};
+
+/// Every effect reads its four `parameters` differently, documented per variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostEffectKind {
+    /// `x` - threshold, `y` - soft knee, `z` - intensity.
+    Bloom,
+    /// `x` - contrast, `y` - saturation, `z` - temperature, `w` - strength.
+    ColorGrading,
+    /// `x` - channels offset at the screen edges in UV units.
+    ChromaticAberration,
+    /// `x` - intensity, `y` - smoothness.
+    Vignette,
+    /// `x` - span max, `y` - reduce multiplier, `z` - reduce minimum.
+    Fxaa,
+    /// `xyz` - display-encoded color, `w` - thickness in pixels. Outlines the selected
+    /// instances, needs picking to be enabled.
+    SelectionOutline,
+}
+
+impl PostEffectKind {
+    /// HDR effects run on the scene before tonemapping regardless of their place in the stack.
+    #[inline(always)]
+    pub fn is_hdr(self) -> bool {
+        matches!(self, PostEffectKind::Bloom)
+    }
+
+    #[inline(always)]
+    pub fn fragment_shader_name(self) -> &'static str {
+        match self {
+            PostEffectKind::Bloom => "bloom_composite",
+            PostEffectKind::ColorGrading => "color_grading",
+            PostEffectKind::ChromaticAberration => "chromatic_aberration",
+            PostEffectKind::Vignette => "vignette",
+            PostEffectKind::Fxaa => "fxaa",
+            PostEffectKind::SelectionOutline => "selection_outline",
+        }
+    }
+
+    pub fn default_parameters(self) -> Vec4 {
+        match self {
+            PostEffectKind::Bloom => Vec4::new(1.0, 0.5, 0.05, 0.0),
+            PostEffectKind::ColorGrading => Vec4::new(1.0, 1.0, 0.0, 1.0),
+            PostEffectKind::ChromaticAberration => Vec4::new(0.003, 0.0, 0.0, 0.0),
+            PostEffectKind::Vignette => Vec4::new(0.35, 0.45, 0.0, 0.0),
+            PostEffectKind::Fxaa => Vec4::new(8.0, 1.0 / 8.0, 1.0 / 128.0, 0.0),
+            PostEffectKind::SelectionOutline => Vec4::new(1.0, 0.6, 0.1, 3.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PostEffect {
+    pub kind: PostEffectKind,
+    pub is_enabled: bool,
+    pub parameters: Vec4,
+}
+
+impl PostEffect {
+    #[inline(always)]
+    pub fn new(kind: PostEffectKind, is_enabled: bool) -> Self {
+        Self {
+            kind,
+            is_enabled,
+            parameters: kind.default_parameters(),
+        }
+    }
+}
+
+/// Ordered stack of fullscreen effects around the tonemapping pass.
+///
+/// Effects ping-pong between two HDR-format targets, the last enabled one writes straight into
+/// the output image. Effects after tonemapping see display-encoded colors.
+pub struct PostProcessing {
+    effects: Vec<PostEffect>,
+    targets: [ColorTarget; 2],
+    /// Half resolution, kept in `GENERAL` layout for the compute passes.
+    bloom_targets: [ColorTarget; 2],
+    lut: ColorTarget,
+    /// Parameters the color grading LUT was last generated with.
+    lut_parameters: Option<Vec4>,
+    sampler: vk::Sampler,
+    fullscreen_pass: FullscreenPass,
+    pub tonemap_pass: TonemapPass,
+    extent: vk::Extent2D,
+    bloom_extent: vk::Extent2D,
+}
+
+impl PostProcessing {
+    pub const BLOOM_PREFILTER_SHADER_NAME: &'static str = "bloom_prefilter";
+    pub const BLOOM_BLUR_SHADER_NAME: &'static str = "bloom_blur";
+    pub const LUT_GENERATE_SHADER_NAME: &'static str = "lut_generate";
+
+    pub const LUT_SIZE: u32 = 32;
+    pub const LUT_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+    pub const WORKGROUP_SIZE: u32 = 8;
+    pub const LUT_WORKGROUP_SIZE: u32 = 4;
+
+    pub const INPUT_SLOT: u32 = 0;
+    /// Bloom texture for the composite, LUT for color grading.
+    pub const SECONDARY_INPUT_SLOT: u32 = 1;
+    pub const OUTPUT_SLOT: u32 = 0;
+    /// Jump flood result of `Selection`, loaded as a storage image.
+    pub const SELECTION_SLOT: u32 = 1;
+
+    pub fn new(device: &ash::Device, allocator: &Allocator, extent: vk::Extent2D) -> Self {
+        let bloom_extent = vk::Extent2D {
+            width: (extent.width / 2).max(1),
+            height: (extent.height / 2).max(1),
+        };
+
+        let create_target = |extent, usage| {
+            SwapchainManager::create_color_target(
+                device,
+                allocator,
+                SwapchainManager::HDR_FORMAT,
+                extent,
+                vk::SampleCountFlags::TYPE_1,
+                usage,
+            )
+        };
+        let target_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED;
+        let bloom_usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED;
+
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        Self {
+            effects: vec![
+                PostEffect::new(PostEffectKind::Bloom, true),
+                PostEffect::new(PostEffectKind::ColorGrading, false),
+                PostEffect::new(PostEffectKind::ChromaticAberration, false),
+                PostEffect::new(PostEffectKind::Vignette, true),
+                PostEffect::new(PostEffectKind::SelectionOutline, true),
+                PostEffect::new(PostEffectKind::Fxaa, true),
+            ],
+            targets: [
+                create_target(extent, target_usage),
+                create_target(extent, target_usage),
+            ],
+            bloom_targets: [
+                create_target(bloom_extent, bloom_usage),
+                create_target(bloom_extent, bloom_usage),
+            ],
+            lut: Self::create_lut(device, allocator),
+            lut_parameters: None,
+            sampler,
+            fullscreen_pass: FullscreenPass::new(),
+            tonemap_pass: TonemapPass::new(device),
+            extent,
+            bloom_extent,
+        }
+    }
+
+    #[inline(always)]
+    pub fn effects(&self) -> &[PostEffect] {
+        &self.effects
+    }
+
+    #[inline(always)]
+    pub fn effect(&self, kind: PostEffectKind) -> Option<&PostEffect> {
+        self.effects.iter().find(|effect| effect.kind == kind)
+    }
+
+    #[inline(always)]
+    pub fn effect_mut(&mut self, kind: PostEffectKind) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|effect| effect.kind == kind)
+    }
+
+    #[inline(always)]
+    pub fn toggle(&mut self, kind: PostEffectKind) {
+        if let Some(effect) = self.effect_mut(kind) {
+            effect.is_enabled = !effect.is_enabled;
+        }
+    }
+
+    /// Indices past the end move the effect to the back of the stack.
+    pub fn move_effect(&mut self, kind: PostEffectKind, index: usize) {
+        let Some(position) = self.effects.iter().position(|effect| effect.kind == kind) else {
+            return;
+        };
+
+        let effect = self.effects.remove(position);
+        self.effects.insert(index.min(self.effects.len()), effect);
+    }
+
+    /// `hdr_view` must be in `SHADER_READ_ONLY_OPTIMAL` and `output_view` in
+    /// `COLOR_ATTACHMENT_OPTIMAL`, both with the extent the stack was created with.
+    /// The selection outline is skipped without `selection_view`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        hdr_view: vk::ImageView,
+        output_view: vk::ImageView,
+        display_mode: DisplayMode,
+        selection_view: Option<vk::ImageView>,
+    ) {
+        let mut input_view = hdr_view;
+        let mut target_index = 0;
+
+        let ldr_effects = self
+            .effects
+            .iter()
+            .filter(|effect| effect.is_enabled && !effect.kind.is_hdr())
+            .filter(|effect| {
+                effect.kind != PostEffectKind::SelectionOutline || selection_view.is_some()
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        let bloom = self
+            .effects
+            .iter()
+            .find(|effect| effect.is_enabled && effect.kind == PostEffectKind::Bloom)
+            .copied();
+        if let Some(bloom) = bloom {
+            self.render_bloom(
+                device,
+                shader_manager,
+                command_buffer,
+                input_view,
+                bloom.parameters,
+            );
+
+            let descriptor_writer = self.input_descriptors(input_view).sampled_image(
+                Self::SECONDARY_INPUT_SLOT,
+                self.bloom_targets[0].image_view,
+                self.sampler,
+                vk::ImageLayout::GENERAL,
+            );
+            let push_constants = self.push_constants(bloom.parameters);
+            input_view = self.write_target(device, command_buffer, target_index, |target_view| {
+                self.fullscreen_pass.draw(
+                    device,
+                    shader_manager,
+                    command_buffer,
+                    bloom.kind.fragment_shader_name(),
+                    target_view,
+                    self.extent,
+                    &descriptor_writer,
+                    utils::as_bytes(&push_constants),
+                );
+            });
+            target_index ^= 1;
+        }
+
+        if ldr_effects.is_empty() {
+            self.tonemap_pass.render(
+                device,
+                shader_manager,
+                command_buffer,
+                input_view,
+                output_view,
+                self.extent,
+                display_mode,
+            );
+            return;
+        }
+
+        input_view = self.write_target(device, command_buffer, target_index, |target_view| {
+            self.tonemap_pass.render(
+                device,
+                shader_manager,
+                command_buffer,
+                input_view,
+                target_view,
+                self.extent,
+                display_mode,
+            );
+        });
+        target_index ^= 1;
+
+        if let Some(grading) = ldr_effects
+            .iter()
+            .find(|effect| effect.kind == PostEffectKind::ColorGrading)
+        {
+            self.update_lut(device, shader_manager, command_buffer, grading.parameters);
+        }
+
+        let last_index = ldr_effects.len() - 1;
+        for (index, effect) in ldr_effects.iter().enumerate() {
+            let mut descriptor_writer = self.input_descriptors(input_view);
+            match (effect.kind, selection_view) {
+                (PostEffectKind::ColorGrading, _) => {
+                    descriptor_writer = descriptor_writer.sampled_image(
+                        Self::SECONDARY_INPUT_SLOT,
+                        self.lut.image_view,
+                        self.sampler,
+                        vk::ImageLayout::GENERAL,
+                    );
+                }
+                (PostEffectKind::SelectionOutline, Some(selection_view)) => {
+                    descriptor_writer =
+                        descriptor_writer.storage_image(Self::SELECTION_SLOT, selection_view);
+                }
+                _ => (),
+            }
+            let push_constants = self.push_constants(effect.parameters);
+            let draw = |target_view| {
+                self.fullscreen_pass.draw(
+                    device,
+                    shader_manager,
+                    command_buffer,
+                    effect.kind.fragment_shader_name(),
+                    target_view,
+                    self.extent,
+                    &descriptor_writer,
+                    utils::as_bytes(&push_constants),
+                );
+            };
+
+            if index == last_index {
+                draw(output_view);
+            } else {
+                input_view = self.write_target(device, command_buffer, target_index, draw);
+                target_index ^= 1;
+            }
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        self.targets
+            .iter()
+            .chain(self.bloom_targets.iter())
+            .chain(std::iter::once(&self.lut))
+            .for_each(|target| target.destroy(device, allocator));
+        unsafe { device.destroy_sampler(self.sampler, None) };
+        self.tonemap_pass.destroy(device);
+    }
+
+    /// Threshold into the first bloom target, then a separable blur ending up in it again.
+    fn render_bloom(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        input_view: vk::ImageView,
+        parameters: Vec4,
+    ) {
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            level_count: 1,
+            layer_count: 1,
+            ..Default::default()
+        };
+        let image_barriers = self.bloom_targets.each_ref().map(|bloom_target| {
+            vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .src_access_mask(vk::AccessFlags2::NONE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .image(bloom_target.allocated_image.image)
+                .subresource_range(subresource_range)
+        });
+        let dependency_info = vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+        unsafe { device.cmd_pipeline_barrier2(command_buffer, &dependency_info) };
+
+        let bloom_extent = vk::Extent3D {
+            width: self.bloom_extent.width,
+            height: self.bloom_extent.height,
+            depth: 1,
+        };
+        let workgroup_size = [Self::WORKGROUP_SIZE, Self::WORKGROUP_SIZE, 1];
+        let bloom_texel_size = Vec2::new(
+            1.0 / self.bloom_extent.width as f32,
+            1.0 / self.bloom_extent.height as f32,
+        );
+
+        ComputeDispatch::covering(
+            Self::BLOOM_PREFILTER_SHADER_NAME,
+            bloom_extent,
+            workgroup_size,
+        )
+        .descriptors(
+            self.input_descriptors(input_view)
+                .storage_image(Self::OUTPUT_SLOT, self.bloom_targets[0].image_view),
+        )
+        .push_constants(&self.push_constants(parameters))
+        .barrier(ComputeBarrier::COMPUTE_TO_COMPUTE)
+        .record(device, shader_manager, command_buffer);
+
+        // The vertical pass is sampled by the composite right after.
+        let blur_passes = [
+            (
+                &self.bloom_targets[0],
+                &self.bloom_targets[1],
+                Vec2::X,
+                ComputeBarrier::COMPUTE_TO_COMPUTE,
+            ),
+            (
+                &self.bloom_targets[1],
+                &self.bloom_targets[0],
+                Vec2::Y,
+                ComputeBarrier::COMPUTE_TO_FRAGMENT,
+            ),
+        ];
+        for (source, destination, direction, barrier) in blur_passes {
+            let push_constants = PostPushConstants {
+                parameters,
+                texel_size: bloom_texel_size,
+                direction,
+            };
+            let descriptor_writer = DescriptorWriter::new()
+                .sampled_image(
+                    Self::INPUT_SLOT,
+                    source.image_view,
+                    self.sampler,
+                    vk::ImageLayout::GENERAL,
+                )
+                .storage_image(Self::OUTPUT_SLOT, destination.image_view);
+            ComputeDispatch::covering(Self::BLOOM_BLUR_SHADER_NAME, bloom_extent, workgroup_size)
+                .descriptors(descriptor_writer)
+                .push_constants(&push_constants)
+                .barrier(barrier)
+                .record(device, shader_manager, command_buffer);
+        }
+    }
+
+    /// The LUT only depends on the grading parameters, so it's regenerated when they change.
+    fn update_lut(
+        &mut self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        parameters: Vec4,
+    ) {
+        if self.lut_parameters == Some(parameters) {
+            return;
+        }
+
+        let old_layout = match self.lut_parameters {
+            Some(_) => vk::ImageLayout::GENERAL,
+            None => vk::ImageLayout::UNDEFINED,
+        };
+        let image_barriers = [vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+            .src_access_mask(vk::AccessFlags2::NONE)
+            .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .old_layout(old_layout)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .image(self.lut.allocated_image.image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                level_count: 1,
+                layer_count: 1,
+                ..Default::default()
+            })];
+        let dependency_info = vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+        unsafe { device.cmd_pipeline_barrier2(command_buffer, &dependency_info) };
+
+        let push_constants = PostPushConstants {
+            parameters,
+            texel_size: Vec2::splat(1.0 / (Self::LUT_SIZE - 1) as f32),
+            ..Default::default()
+        };
+        ComputeDispatch::new(
+            Self::LUT_GENERATE_SHADER_NAME,
+            [Self::LUT_SIZE / Self::LUT_WORKGROUP_SIZE; 3],
+        )
+        .descriptors(DescriptorWriter::new().storage_image(Self::OUTPUT_SLOT, self.lut.image_view))
+        .push_constants(&push_constants)
+        .barrier(ComputeBarrier::COMPUTE_TO_FRAGMENT)
+        .record(device, shader_manager, command_buffer);
+
+        self.lut_parameters = Some(parameters);
+    }
+
+    /// Transitions one of the ping-pong targets around `draw` and returns its view,
+    /// ready to be sampled by the next effect.
+    fn write_target(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        target_index: usize,
+        draw: impl FnOnce(vk::ImageView),
+    ) -> vk::ImageView {
+        let target = &self.targets[target_index];
+        let attachment_barrier = vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+            .src_access_mask(vk::AccessFlags2::NONE)
+            .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .image(target.allocated_image.image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                level_count: 1,
+                layer_count: 1,
+                ..Default::default()
+            });
+        let sampling_barrier = attachment_barrier
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+            .old_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        unsafe {
+            let image_barriers = [attachment_barrier];
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+        }
+
+        draw(target.image_view);
+
+        unsafe {
+            let image_barriers = [sampling_barrier];
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+        }
+
+        target.image_view
+    }
+
+    #[inline(always)]
+    fn input_descriptors(&self, input_view: vk::ImageView) -> DescriptorWriter {
+        DescriptorWriter::new().sampled_image(
+            Self::INPUT_SLOT,
+            input_view,
+            self.sampler,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )
+    }
+
+    #[inline(always)]
+    fn push_constants(&self, parameters: Vec4) -> PostPushConstants {
+        PostPushConstants {
+            parameters,
+            texel_size: Vec2::new(
+                1.0 / self.extent.width as f32,
+                1.0 / self.extent.height as f32,
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn create_lut(device: &ash::Device, allocator: &Allocator) -> ColorTarget {
+        let allocated_image = allocator.allocate_image(
+            Self::LUT_FORMAT,
+            vk::Extent3D {
+                width: Self::LUT_SIZE,
+                height: Self::LUT_SIZE,
+                depth: Self::LUT_SIZE,
+            },
+            vk::ImageType::TYPE_3D,
+            1,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            Default::default(),
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+
+        let image_view_info = vk::ImageViewCreateInfo {
+            image: allocated_image.image,
+            view_type: vk::ImageViewType::TYPE_3D,
+            format: Self::LUT_FORMAT,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                level_count: 1,
+                layer_count: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let image_view = unsafe { device.create_image_view(&image_view_info, None).unwrap() };
+
+        ColorTarget::new(image_view, allocated_image)
+    }
+}
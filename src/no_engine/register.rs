@@ -1,6 +1,10 @@
 use ash::vk;
 
-use super::allocator::mesh::AllocatedMesh;
+use super::{
+    allocator::mesh::AllocatedMesh,
+    objects::{instance::MeshInstance, light::Light},
+    Id,
+};
 
 // TODO: Maybe move to the future scene manager?
 #[derive(Default)]
@@ -8,6 +12,8 @@ pub struct Register {
     allocated_meshes: Vec<AllocatedMesh>,
     buffers: Vec<vk::Buffer>,
     offsets: Vec<u64>,
+    instances: Vec<MeshInstance>,
+    lights: Vec<Light>,
 }
 
 impl Register {
@@ -37,6 +43,71 @@ impl Register {
         &self.offsets
     }
 
+    #[inline(always)]
+    pub fn register_instance(&mut self, instance: MeshInstance) -> Id {
+        let id = instance.id;
+        self.instances.push(instance);
+
+        id
+    }
+
+    pub fn register_instances(
+        &mut self,
+        instances: impl IntoIterator<Item = MeshInstance>,
+    ) -> Vec<Id> {
+        let instances = instances.into_iter();
+        self.instances.reserve(instances.size_hint().0);
+
+        instances
+            .map(|instance| self.register_instance(instance))
+            .collect()
+    }
+
+    #[inline(always)]
+    pub fn unregister_instance(&mut self, id: Id) -> Option<MeshInstance> {
+        let index = self
+            .instances
+            .iter()
+            .position(|instance| instance.id == id)?;
+
+        Some(self.instances.swap_remove(index))
+    }
+
+    #[inline(always)]
+    pub fn get_instances(&self) -> &[MeshInstance] {
+        &self.instances
+    }
+
+    #[inline(always)]
+    pub fn get_instance_mut(&mut self, id: Id) -> Option<&mut MeshInstance> {
+        self.instances.iter_mut().find(|instance| instance.id == id)
+    }
+
+    #[inline(always)]
+    pub fn register_light(&mut self, light: Light) -> Id {
+        let id = light.id;
+        self.lights.push(light);
+
+        id
+    }
+
+    #[inline(always)]
+    pub fn unregister_light(&mut self, id: Id) -> Option<Light> {
+        let index = self.lights.iter().position(|light| light.id == id)?;
+
+        Some(self.lights.swap_remove(index))
+    }
+
+    #[inline(always)]
+    pub fn get_lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    #[inline(always)]
+    pub fn get_light_mut(&mut self, id: Id) -> Option<&mut Light> {
+        self.lights.iter_mut().find(|light| light.id == id)
+    }
+
     #[inline(always)]
     fn accumulate_data(&mut self) {
         self.buffers = self
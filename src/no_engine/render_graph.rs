@@ -0,0 +1,1365 @@
+use ash::vk;
+
+use super::allocator::Allocator;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageHandle(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferHandle(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PassHandle(usize);
+
+const WRITE_ACCESS: vk::AccessFlags2 = vk::AccessFlags2::from_raw(
+    vk::AccessFlags2::SHADER_WRITE.as_raw()
+        | vk::AccessFlags2::SHADER_STORAGE_WRITE.as_raw()
+        | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE.as_raw()
+        | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw()
+        | vk::AccessFlags2::TRANSFER_WRITE.as_raw()
+        | vk::AccessFlags2::HOST_WRITE.as_raw()
+        | vk::AccessFlags2::MEMORY_WRITE.as_raw(),
+);
+const READ_ACCESS: vk::AccessFlags2 = vk::AccessFlags2::from_raw(
+    vk::AccessFlags2::INDIRECT_COMMAND_READ.as_raw()
+        | vk::AccessFlags2::UNIFORM_READ.as_raw()
+        | vk::AccessFlags2::SHADER_READ.as_raw()
+        | vk::AccessFlags2::SHADER_SAMPLED_READ.as_raw()
+        | vk::AccessFlags2::SHADER_STORAGE_READ.as_raw()
+        | vk::AccessFlags2::COLOR_ATTACHMENT_READ.as_raw()
+        | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ.as_raw()
+        | vk::AccessFlags2::TRANSFER_READ.as_raw()
+        | vk::AccessFlags2::HOST_READ.as_raw()
+        | vk::AccessFlags2::MEMORY_READ.as_raw(),
+);
+
+/// How a pass uses an image, the graph transitions the image into it before the pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageAccess {
+    pub stage_mask: vk::PipelineStageFlags2,
+    pub access_mask: vk::AccessFlags2,
+    pub layout: vk::ImageLayout,
+}
+
+impl ImageAccess {
+    /// Previous contents are discarded, the state images start every frame in.
+    pub const UNDEFINED: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::NONE,
+        access_mask: vk::AccessFlags2::NONE,
+        layout: vk::ImageLayout::UNDEFINED,
+    };
+    /// Cleared or fully overwritten color attachment.
+    pub const COLOR_ATTACHMENT_WRITE: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+        access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+    /// Color attachment that is loaded or blended into.
+    pub const COLOR_ATTACHMENT_READ_WRITE: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+        access_mask: vk::AccessFlags2::from_raw(
+            vk::AccessFlags2::COLOR_ATTACHMENT_READ.as_raw()
+                | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE.as_raw(),
+        ),
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+    /// Depth tested and written.
+    pub const DEPTH_ATTACHMENT: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::from_raw(
+            vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS.as_raw()
+                | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS.as_raw(),
+        ),
+        access_mask: vk::AccessFlags2::from_raw(
+            vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ.as_raw()
+                | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw(),
+        ),
+        layout: vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
+    };
+    /// Depth sampled by fragment shaders.
+    pub const DEPTH_SAMPLED: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+        access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+        layout: vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+    };
+    pub const FRAGMENT_SAMPLED: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+        access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+        layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    };
+    /// Sampled by fragment or compute shaders, like the post processing chain does.
+    pub const SHADER_SAMPLED: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::from_raw(
+            vk::PipelineStageFlags2::FRAGMENT_SHADER.as_raw()
+                | vk::PipelineStageFlags2::COMPUTE_SHADER.as_raw(),
+        ),
+        access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+        layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    };
+    /// Loaded by compute shaders, like the picking ids the selection outline is seeded from.
+    pub const COMPUTE_STORAGE_READ: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+        access_mask: vk::AccessFlags2::SHADER_STORAGE_READ,
+        layout: vk::ImageLayout::GENERAL,
+    };
+    /// Loaded and stored by compute shaders, across several dispatches which synchronize
+    /// between each other.
+    pub const COMPUTE_STORAGE_READ_WRITE: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+        access_mask: vk::AccessFlags2::from_raw(
+            vk::AccessFlags2::SHADER_STORAGE_READ.as_raw()
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE.as_raw(),
+        ),
+        layout: vk::ImageLayout::GENERAL,
+    };
+    pub const FRAGMENT_STORAGE_READ: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+        access_mask: vk::AccessFlags2::SHADER_STORAGE_READ,
+        layout: vk::ImageLayout::GENERAL,
+    };
+    /// Handed to the presentation engine, which the semaphores synchronize with.
+    pub const PRESENT: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::NONE,
+        access_mask: vk::AccessFlags2::NONE,
+        layout: vk::ImageLayout::PRESENT_SRC_KHR,
+    };
+
+    #[inline(always)]
+    pub fn is_write(self) -> bool {
+        self.access_mask.intersects(WRITE_ACCESS)
+    }
+
+    /// Includes loaded attachments and depth tests, which depend on earlier writes too.
+    #[inline(always)]
+    pub fn is_read(self) -> bool {
+        self.access_mask.intersects(READ_ACCESS)
+    }
+}
+
+/// How a pass uses a buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferAccess {
+    pub stage_mask: vk::PipelineStageFlags2,
+    pub access_mask: vk::AccessFlags2,
+}
+
+impl BufferAccess {
+    pub const COMPUTE_STORAGE_WRITE: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+        access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+    };
+    pub const FRAGMENT_STORAGE_READ: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+        access_mask: vk::AccessFlags2::SHADER_STORAGE_READ,
+    };
+    /// Cleared by a transfer and then written by compute shaders, like the draw counts.
+    pub const FILL_THEN_COMPUTE_WRITE: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::from_raw(
+            vk::PipelineStageFlags2::TRANSFER.as_raw()
+                | vk::PipelineStageFlags2::COMPUTE_SHADER.as_raw(),
+        ),
+        access_mask: vk::AccessFlags2::from_raw(
+            vk::AccessFlags2::TRANSFER_WRITE.as_raw()
+                | vk::AccessFlags2::SHADER_STORAGE_READ.as_raw()
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE.as_raw(),
+        ),
+    };
+    /// Draw commands or counts consumed by indirect draws.
+    pub const INDIRECT_READ: Self = Self {
+        stage_mask: vk::PipelineStageFlags2::DRAW_INDIRECT,
+        access_mask: vk::AccessFlags2::INDIRECT_COMMAND_READ,
+    };
+
+    #[inline(always)]
+    pub fn is_write(self) -> bool {
+        self.access_mask.intersects(WRITE_ACCESS)
+    }
+
+    #[inline(always)]
+    pub fn is_read(self) -> bool {
+        self.access_mask.intersects(READ_ACCESS)
+    }
+}
+
+/// Description of an image the graph allocates for the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransientImage {
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    pub samples: vk::SampleCountFlags,
+    /// Depth attachment usage makes it a depth image.
+    pub usage: vk::ImageUsageFlags,
+}
+
+impl TransientImage {
+    #[inline(always)]
+    pub fn is_depth(&self) -> bool {
+        self.usage
+            .contains(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
+    }
+
+    #[inline(always)]
+    fn aspect_mask(&self) -> vk::ImageAspectFlags {
+        if self.is_depth() {
+            vk::ImageAspectFlags::DEPTH
+        } else {
+            vk::ImageAspectFlags::COLOR
+        }
+    }
+
+    #[inline(always)]
+    fn image_create_info(&self) -> vk::ImageCreateInfo<'static> {
+        vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(self.format)
+            .extent(vk::Extent3D {
+                width: self.extent.width,
+                height: self.extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(self.samples)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(self.usage)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ImageSource {
+    /// Owned outside of the graph, `final_access` is the state it's left in after the frame.
+    Imported {
+        image: vk::Image,
+        image_view: vk::ImageView,
+        initial_access: ImageAccess,
+        final_access: Option<ImageAccess>,
+    },
+    /// Backed by a pooled image in `slot`, assigned when compiling. `None` when only culled
+    /// passes use it.
+    Transient {
+        description: TransientImage,
+        slot: Option<usize>,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ImageResource {
+    name: &'static str,
+    source: ImageSource,
+    aspect_mask: vk::ImageAspectFlags,
+    is_output: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BufferResource {
+    name: &'static str,
+    buffer: vk::Buffer,
+    is_output: bool,
+}
+
+struct Pass {
+    name: &'static str,
+    images: Vec<(ImageHandle, ImageAccess)>,
+    buffers: Vec<(BufferHandle, BufferAccess)>,
+    has_side_effects: bool,
+}
+
+/// Passes in submission order with the images and buffers they read and write.
+///
+/// Compiling culls passes nothing needed depends on, places the barriers and layout transitions
+/// between the remaining ones and lets transient images with disjoint lifetimes share memory.
+/// Identical descriptions share one pooled image, different ones alias the same allocation. It
+/// doesn't touch the device, so its output can be inspected on its own. The caller records
+/// each pass itself after `CompiledGraph::begin_pass`.
+#[derive(Default)]
+pub struct RenderGraph {
+    images: Vec<ImageResource>,
+    buffers: Vec<BufferResource>,
+    passes: Vec<Pass>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// `initial_access` is the state the image is in when the frame starts, `final_access` the
+    /// one it has to be left in, `None` for wherever the last pass left it.
+    pub fn import_image(
+        &mut self,
+        name: &'static str,
+        image: vk::Image,
+        image_view: vk::ImageView,
+        aspect_mask: vk::ImageAspectFlags,
+        initial_access: ImageAccess,
+        final_access: Option<ImageAccess>,
+    ) -> ImageHandle {
+        self.push_image(
+            name,
+            ImageSource::Imported {
+                image,
+                image_view,
+                initial_access,
+                final_access,
+            },
+            aspect_mask,
+        )
+    }
+
+    /// Contents don't survive the frame and start undefined in the first pass using it.
+    pub fn create_image(&mut self, name: &'static str, description: TransientImage) -> ImageHandle {
+        self.push_image(
+            name,
+            ImageSource::Transient {
+                description,
+                slot: None,
+            },
+            description.aspect_mask(),
+        )
+    }
+
+    pub fn import_buffer(&mut self, name: &'static str, buffer: vk::Buffer) -> BufferHandle {
+        self.buffers.push(BufferResource {
+            name,
+            buffer,
+            is_output: false,
+        });
+
+        BufferHandle(self.buffers.len() - 1)
+    }
+
+    /// Passes writing `image` survive culling even when no other pass reads it.
+    /// Imported images with a final access are outputs already.
+    #[inline(always)]
+    pub fn mark_output(&mut self, image: ImageHandle) {
+        self.images[image.0].is_output = true;
+    }
+
+    #[inline(always)]
+    pub fn mark_buffer_output(&mut self, buffer: BufferHandle) {
+        self.buffers[buffer.0].is_output = true;
+    }
+
+    pub fn add_pass(&mut self, name: &'static str) -> PassBuilder<'_> {
+        self.passes.push(Pass {
+            name,
+            images: Default::default(),
+            buffers: Default::default(),
+            has_side_effects: false,
+        });
+        let handle = PassHandle(self.passes.len() - 1);
+
+        PassBuilder {
+            pass: self.passes.last_mut().unwrap(),
+            handle,
+        }
+    }
+
+    pub fn compile(mut self) -> CompiledGraph {
+        let is_alive = self.cull();
+        let slots = self.assign_slots(&is_alive);
+        let slot_memory = Self::assign_memory(&slots);
+        for (index, image) in self.images.iter_mut().enumerate() {
+            if let ImageSource::Transient { slot, .. } = &mut image.source {
+                *slot = slots.iter().position(|users| users.images.contains(&index));
+            }
+        }
+
+        let mut image_states = self
+            .images
+            .iter()
+            .map(|image| match image.source {
+                ImageSource::Imported { initial_access, .. } => initial_access,
+                ImageSource::Transient { .. } => ImageAccess::UNDEFINED,
+            })
+            .collect::<Vec<_>>();
+        let mut buffer_states = vec![None::<BufferAccess>; self.buffers.len()];
+        // Last access of whichever transient used a memory block before, aliases wait for it.
+        let mut memory_states =
+            vec![None::<ImageAccess>; slot_memory.iter().max().map_or(0, |&block| block + 1)];
+        let mut is_first_use = vec![true; self.images.len()];
+
+        let passes = self
+            .passes
+            .iter()
+            .zip(&is_alive)
+            .map(|(pass, &is_alive)| {
+                let mut compiled_pass = CompiledPass {
+                    name: pass.name,
+                    is_culled: !is_alive,
+                    image_barriers: Default::default(),
+                    buffer_barriers: Default::default(),
+                };
+                if !is_alive {
+                    return compiled_pass;
+                }
+
+                for &(image, access) in &pass.images {
+                    let state = &mut image_states[image.0];
+                    let block = match self.images[image.0].source {
+                        ImageSource::Transient { slot, .. } => slot.map(|slot| slot_memory[slot]),
+                        ImageSource::Imported { .. } => None,
+                    };
+                    if let Some(block) =
+                        block.filter(|_| std::mem::take(&mut is_first_use[image.0]))
+                    {
+                        if let Some(memory_state) = memory_states[block] {
+                            *state = ImageAccess {
+                                layout: vk::ImageLayout::UNDEFINED,
+                                ..memory_state
+                            };
+                        }
+                    }
+
+                    if let Some(source) = Self::transition(state, access) {
+                        compiled_pass.image_barriers.push(ImageBarrier {
+                            image,
+                            source,
+                            destination: access,
+                        });
+                    }
+                    if let Some(block) = block {
+                        memory_states[block] = Some(*state);
+                    }
+                }
+
+                for &(buffer, access) in &pass.buffers {
+                    let state = &mut buffer_states[buffer.0];
+                    let Some(previous) = *state else {
+                        *state = Some(access);
+                        continue;
+                    };
+                    if !previous.is_write() && !access.is_write() {
+                        *state = Some(BufferAccess {
+                            stage_mask: previous.stage_mask | access.stage_mask,
+                            access_mask: previous.access_mask | access.access_mask,
+                        });
+                        continue;
+                    }
+
+                    compiled_pass.buffer_barriers.push(BufferBarrier {
+                        buffer,
+                        source: BufferAccess {
+                            stage_mask: previous.stage_mask,
+                            access_mask: previous.access_mask & WRITE_ACCESS,
+                        },
+                        destination: access,
+                    });
+                    *state = Some(access);
+                }
+
+                compiled_pass
+            })
+            .collect();
+
+        let final_barriers = self
+            .images
+            .iter()
+            .enumerate()
+            .filter_map(|(index, image)| match image.source {
+                ImageSource::Imported {
+                    final_access: Some(final_access),
+                    ..
+                } => Self::transition(&mut image_states[index], final_access).map(|source| {
+                    ImageBarrier {
+                        image: ImageHandle(index),
+                        source,
+                        destination: final_access,
+                    }
+                }),
+                _ => None,
+            })
+            .collect();
+
+        CompiledGraph {
+            images: self.images,
+            buffers: self.buffers,
+            passes,
+            final_barriers,
+            slots: slots.into_iter().map(|slot| slot.description).collect(),
+            slot_memory,
+        }
+    }
+
+    #[inline(always)]
+    fn push_image(
+        &mut self,
+        name: &'static str,
+        source: ImageSource,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> ImageHandle {
+        self.images.push(ImageResource {
+            name,
+            source,
+            aspect_mask,
+            is_output: false,
+        });
+
+        ImageHandle(self.images.len() - 1)
+    }
+
+    /// Walks the passes backwards, a pass survives when it has side effects or writes
+    /// something a surviving later pass reads or the frame outputs.
+    fn cull(&self) -> Vec<bool> {
+        let mut is_image_needed = self
+            .images
+            .iter()
+            .map(|image| {
+                image.is_output
+                    || matches!(
+                        image.source,
+                        ImageSource::Imported {
+                            final_access: Some(_),
+                            ..
+                        }
+                    )
+            })
+            .collect::<Vec<_>>();
+        let mut is_buffer_needed = self
+            .buffers
+            .iter()
+            .map(|buffer| buffer.is_output)
+            .collect::<Vec<_>>();
+
+        let mut is_alive =
+            self.passes
+                .iter()
+                .rev()
+                .map(|pass| {
+                    let is_alive = pass.has_side_effects
+                        || pass
+                            .images
+                            .iter()
+                            .any(|&(image, access)| access.is_write() && is_image_needed[image.0])
+                        || pass.buffers.iter().any(|&(buffer, access)| {
+                            access.is_write() && is_buffer_needed[buffer.0]
+                        });
+                    if is_alive {
+                        pass.images
+                            .iter()
+                            .filter(|(_, access)| access.is_read())
+                            .for_each(|(image, _)| is_image_needed[image.0] = true);
+                        pass.buffers
+                            .iter()
+                            .filter(|(_, access)| access.is_read())
+                            .for_each(|(buffer, _)| is_buffer_needed[buffer.0] = true);
+                    }
+
+                    is_alive
+                })
+                .collect::<Vec<_>>();
+        is_alive.reverse();
+
+        is_alive
+    }
+
+    /// Transient images go into the first slot with the same description whose previous
+    /// user was last used before them. Images which are only used together never share, like
+    /// the input and output of one pass.
+    fn assign_slots(&self, is_alive: &[bool]) -> Vec<SlotUsers> {
+        let lifetimes = self.images.iter().enumerate().map(|(index, _)| {
+            let mut uses = self
+                .passes
+                .iter()
+                .enumerate()
+                .filter(|&(pass_index, pass)| {
+                    is_alive[pass_index] && pass.images.iter().any(|(image, _)| image.0 == index)
+                })
+                .map(|(pass_index, _)| pass_index);
+            let first = uses.next()?;
+
+            Some((first, uses.last().unwrap_or(first)))
+        });
+
+        let mut slots = Vec::<SlotUsers>::new();
+        for (index, (image, lifetime)) in self.images.iter().zip(lifetimes).enumerate() {
+            let (ImageSource::Transient { description, .. }, Some((first, last))) =
+                (image.source, lifetime)
+            else {
+                continue;
+            };
+
+            match slots
+                .iter_mut()
+                .find(|slot| slot.description == description && slot.last_use < first)
+            {
+                Some(slot) => {
+                    slot.images.push(index);
+                    slot.last_use = last;
+                }
+                None => slots.push(SlotUsers {
+                    description,
+                    images: vec![index],
+                    first_use: first,
+                    last_use: last,
+                }),
+            }
+        }
+
+        slots
+    }
+
+    /// Slots go into the first memory block whose previous slot was last used before them,
+    /// whatever their descriptions, in the order they are first used.
+    fn assign_memory(slots: &[SlotUsers]) -> Vec<usize> {
+        let mut order = (0..slots.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&slot| slots[slot].first_use);
+
+        let mut block_last_uses = Vec::<usize>::new();
+        let mut slot_memory = vec![0; slots.len()];
+        for slot in order {
+            let users = &slots[slot];
+            slot_memory[slot] = match block_last_uses
+                .iter()
+                .position(|&last_use| last_use < users.first_use)
+            {
+                Some(block) => {
+                    block_last_uses[block] = users.last_use;
+                    block
+                }
+                None => {
+                    block_last_uses.push(users.last_use);
+                    block_last_uses.len() - 1
+                }
+            };
+        }
+
+        slot_memory
+    }
+
+    /// Moves `state` to `access`, returning the access to wait on when that needs a barrier.
+    /// Reads in the same layout only widen the state, so the next write waits on all of them.
+    fn transition(state: &mut ImageAccess, access: ImageAccess) -> Option<ImageAccess> {
+        let previous = *state;
+        if previous.layout == access.layout && !previous.is_write() && !access.is_write() {
+            state.stage_mask |= access.stage_mask;
+            state.access_mask |= access.access_mask;
+            return None;
+        }
+        *state = access;
+
+        // Only writes have to be made available, reads just need the execution dependency.
+        Some(ImageAccess {
+            stage_mask: previous.stage_mask,
+            access_mask: previous.access_mask & WRITE_ACCESS,
+            layout: previous.layout,
+        })
+    }
+}
+
+struct SlotUsers {
+    description: TransientImage,
+    images: Vec<usize>,
+    first_use: usize,
+    last_use: usize,
+}
+
+pub struct PassBuilder<'a> {
+    pass: &'a mut Pass,
+    handle: PassHandle,
+}
+
+impl PassBuilder<'_> {
+    /// Declaring the same image twice merges the accesses, they must share a layout.
+    #[inline(always)]
+    pub fn image(self, image: ImageHandle, access: ImageAccess) -> Self {
+        match self
+            .pass
+            .images
+            .iter_mut()
+            .find(|(declared, _)| *declared == image)
+        {
+            Some((_, declared_access)) => {
+                debug_assert_eq!(declared_access.layout, access.layout);
+                declared_access.stage_mask |= access.stage_mask;
+                declared_access.access_mask |= access.access_mask;
+            }
+            None => self.pass.images.push((image, access)),
+        }
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn buffer(self, buffer: BufferHandle, access: BufferAccess) -> Self {
+        match self
+            .pass
+            .buffers
+            .iter_mut()
+            .find(|(declared, _)| *declared == buffer)
+        {
+            Some((_, declared_access)) => {
+                declared_access.stage_mask |= access.stage_mask;
+                declared_access.access_mask |= access.access_mask;
+            }
+            None => self.pass.buffers.push((buffer, access)),
+        }
+
+        self
+    }
+
+    /// Declares every one of `buffers` with the same access.
+    #[inline(always)]
+    pub fn buffers(self, buffers: &[BufferHandle], access: BufferAccess) -> Self {
+        buffers
+            .iter()
+            .fold(self, |pass, &buffer| pass.buffer(buffer, access))
+    }
+
+    /// Never culled, for passes whose results leave the graph some other way.
+    #[inline(always)]
+    pub fn side_effects(self) -> Self {
+        self.pass.has_side_effects = true;
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn build(self) -> PassHandle {
+        self.handle
+    }
+}
+
+/// Transition recorded before a pass, `source.layout` is the old layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageBarrier {
+    pub image: ImageHandle,
+    pub source: ImageAccess,
+    pub destination: ImageAccess,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferBarrier {
+    pub buffer: BufferHandle,
+    pub source: BufferAccess,
+    pub destination: BufferAccess,
+}
+
+pub struct CompiledPass {
+    pub name: &'static str,
+    pub is_culled: bool,
+    pub image_barriers: Vec<ImageBarrier>,
+    pub buffer_barriers: Vec<BufferBarrier>,
+}
+
+pub struct CompiledGraph {
+    images: Vec<ImageResource>,
+    buffers: Vec<BufferResource>,
+    passes: Vec<CompiledPass>,
+    final_barriers: Vec<ImageBarrier>,
+    /// Descriptions of the pooled images transient ones are backed by.
+    slots: Vec<TransientImage>,
+    /// Memory block of every slot, slots in the same block alias.
+    slot_memory: Vec<usize>,
+}
+
+impl CompiledGraph {
+    #[inline(always)]
+    pub fn pass(&self, pass: PassHandle) -> &CompiledPass {
+        &self.passes[pass.0]
+    }
+
+    #[inline(always)]
+    pub fn is_culled(&self, pass: PassHandle) -> bool {
+        self.passes[pass.0].is_culled
+    }
+
+    /// Transitions of imported images into their final access after the last pass.
+    #[inline(always)]
+    pub fn final_barriers(&self) -> &[ImageBarrier] {
+        &self.final_barriers
+    }
+
+    #[inline(always)]
+    pub fn transient_slots(&self) -> &[TransientImage] {
+        &self.slots
+    }
+
+    /// Memory block the pooled image in `slot` is bound to, shared with the slots it aliases.
+    #[inline(always)]
+    pub fn slot_memory(&self, slot: usize) -> usize {
+        self.slot_memory[slot]
+    }
+
+    /// Pooled image index backing a transient image, `None` for imported ones and transient
+    /// ones only culled passes use.
+    #[inline(always)]
+    pub fn slot(&self, image: ImageHandle) -> Option<usize> {
+        match self.images[image.0].source {
+            ImageSource::Transient { slot, .. } => slot,
+            ImageSource::Imported { .. } => None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn image_name(&self, image: ImageHandle) -> &'static str {
+        self.images[image.0].name
+    }
+
+    #[inline(always)]
+    pub fn buffer_name(&self, buffer: BufferHandle) -> &'static str {
+        self.buffers[buffer.0].name
+    }
+
+    /// `None` for transient images only culled passes use, nothing backs them.
+    #[inline(always)]
+    pub fn image_view(&self, image: ImageHandle, pool: &TransientPool) -> Option<vk::ImageView> {
+        match self.images[image.0].source {
+            ImageSource::Imported { image_view, .. } => Some(image_view),
+            ImageSource::Transient { slot, .. } => slot.map(|slot| pool.images[slot].1),
+        }
+    }
+
+    /// Records the barriers in front of `pass`, `pool` must be prepared for this graph.
+    pub fn begin_pass(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pass: PassHandle,
+        pool: &TransientPool,
+    ) {
+        let pass = &self.passes[pass.0];
+        self.record_barriers(
+            device,
+            command_buffer,
+            pool,
+            &pass.image_barriers,
+            &pass.buffer_barriers,
+        );
+    }
+
+    /// Leaves the imported images in their final access.
+    #[inline(always)]
+    pub fn finish(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pool: &TransientPool,
+    ) {
+        self.record_barriers(device, command_buffer, pool, &self.final_barriers, &[]);
+    }
+
+    fn record_barriers(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pool: &TransientPool,
+        image_barriers: &[ImageBarrier],
+        buffer_barriers: &[BufferBarrier],
+    ) {
+        if image_barriers.is_empty() && buffer_barriers.is_empty() {
+            return;
+        }
+
+        // Barriers are only placed in surviving passes, whose transient images all have a slot.
+        let image_barriers = image_barriers
+            .iter()
+            .filter_map(|barrier| {
+                let resource = &self.images[barrier.image.0];
+                let image = match resource.source {
+                    ImageSource::Imported { image, .. } => image,
+                    ImageSource::Transient { slot, .. } => pool.images[slot?].0,
+                };
+
+                Some(
+                    vk::ImageMemoryBarrier2::default()
+                        .src_stage_mask(barrier.source.stage_mask)
+                        .src_access_mask(barrier.source.access_mask)
+                        .dst_stage_mask(barrier.destination.stage_mask)
+                        .dst_access_mask(barrier.destination.access_mask)
+                        .old_layout(barrier.source.layout)
+                        .new_layout(barrier.destination.layout)
+                        .image(image)
+                        .subresource_range(vk::ImageSubresourceRange {
+                            aspect_mask: resource.aspect_mask,
+                            level_count: vk::REMAINING_MIP_LEVELS,
+                            layer_count: vk::REMAINING_ARRAY_LAYERS,
+                            ..Default::default()
+                        }),
+                )
+            })
+            .collect::<Vec<_>>();
+        let buffer_barriers = buffer_barriers
+            .iter()
+            .map(|barrier| {
+                vk::BufferMemoryBarrier2::default()
+                    .src_stage_mask(barrier.source.stage_mask)
+                    .src_access_mask(barrier.source.access_mask)
+                    .dst_stage_mask(barrier.destination.stage_mask)
+                    .dst_access_mask(barrier.destination.access_mask)
+                    .buffer(self.buffers[barrier.buffer.0].buffer)
+                    .size(vk::WHOLE_SIZE)
+            })
+            .collect::<Vec<_>>();
+
+        let dependency_info = vk::DependencyInfo::default()
+            .image_memory_barriers(&image_barriers)
+            .buffer_memory_barriers(&buffer_barriers);
+        unsafe { device.cmd_pipeline_barrier2(command_buffer, &dependency_info) };
+    }
+}
+
+/// Images backing transient ones, kept across frames while the graph asks for the same.
+#[derive(Default)]
+pub struct TransientPool {
+    /// Descriptions and memory blocks of the slots the images were created for.
+    layout: Vec<(TransientImage, usize)>,
+    images: Vec<(vk::Image, vk::ImageView)>,
+    allocations: Vec<vk_mem_alloc::Allocation>,
+}
+
+impl TransientPool {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates the images `graph` needs, all of them again when its slots or their aliasing
+    /// changed. Images of the previous frame must not be in use anymore.
+    pub fn prepare(&mut self, device: &ash::Device, allocator: &Allocator, graph: &CompiledGraph) {
+        let layout = graph
+            .slots
+            .iter()
+            .copied()
+            .zip(graph.slot_memory.iter().copied())
+            .collect::<Vec<_>>();
+        if layout == self.layout {
+            return;
+        }
+        self.destroy(device, allocator);
+
+        let mut images = vec![vk::Image::default(); layout.len()];
+        let blocks_count = graph.slot_memory.iter().max().map_or(0, |&block| block + 1);
+        for block in 0..blocks_count {
+            let slots = (0..layout.len())
+                .filter(|&slot| layout[slot].1 == block)
+                .collect::<Vec<_>>();
+            let image_create_infos = slots
+                .iter()
+                .map(|&slot| layout[slot].0.image_create_info())
+                .collect::<Vec<_>>();
+
+            let (block_images, allocations) =
+                allocator.allocate_aliased_images(device, &image_create_infos);
+            for (&slot, image) in slots.iter().zip(block_images) {
+                images[slot] = image;
+            }
+            self.allocations.extend(allocations);
+        }
+
+        self.images = images
+            .into_iter()
+            .zip(&layout)
+            .map(|(image, (description, _))| {
+                let image_view_info = vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(description.format)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::default()
+                            .aspect_mask(description.aspect_mask())
+                            .level_count(1)
+                            .layer_count(1),
+                    );
+                let image_view =
+                    unsafe { device.create_image_view(&image_view_info, None).unwrap() };
+
+                (image, image_view)
+            })
+            .collect();
+        self.layout = layout;
+    }
+
+    pub fn destroy(&mut self, device: &ash::Device, allocator: &Allocator) {
+        self.layout.clear();
+        for (image, image_view) in self.images.drain(..) {
+            unsafe {
+                device.destroy_image_view(image_view, None);
+                device.destroy_image(image, None);
+            }
+        }
+        self.allocations
+            .drain(..)
+            .for_each(|allocation| allocator.free_memory(allocation));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLOR: TransientImage = TransientImage {
+        format: vk::Format::R8G8B8A8_UNORM,
+        extent: vk::Extent2D {
+            width: 64,
+            height: 64,
+        },
+        samples: vk::SampleCountFlags::TYPE_1,
+        usage: vk::ImageUsageFlags::from_raw(
+            vk::ImageUsageFlags::COLOR_ATTACHMENT.as_raw() | vk::ImageUsageFlags::SAMPLED.as_raw(),
+        ),
+    };
+
+    /// Imported image the frame has to leave sampleable, which keeps its writers alive.
+    fn import_output(graph: &mut RenderGraph) -> ImageHandle {
+        graph.import_image(
+            "output",
+            Default::default(),
+            Default::default(),
+            vk::ImageAspectFlags::COLOR,
+            ImageAccess::UNDEFINED,
+            Some(ImageAccess::FRAGMENT_SAMPLED),
+        )
+    }
+
+    /// What a barrier waits on after `access`, only writes have to be made available.
+    fn after(access: ImageAccess) -> ImageAccess {
+        ImageAccess {
+            access_mask: access.access_mask & WRITE_ACCESS,
+            ..access
+        }
+    }
+
+    #[test]
+    fn culls_pass_whose_writes_are_never_read() {
+        let mut graph = RenderGraph::new();
+        let unused = graph.create_image("unused", COLOR);
+        let output = import_output(&mut graph);
+        let unused_pass = graph
+            .add_pass("unused")
+            .image(unused, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        let output_pass = graph
+            .add_pass("output")
+            .image(output, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert!(compiled.is_culled(unused_pass));
+        assert!(compiled.pass(unused_pass).image_barriers.is_empty());
+        assert!(!compiled.is_culled(output_pass));
+        assert_eq!(compiled.slot(unused), None);
+        assert!(compiled.transient_slots().is_empty());
+    }
+
+    #[test]
+    fn places_write_after_write_and_read_after_write_barriers() {
+        let mut graph = RenderGraph::new();
+        let color = graph.create_image("color", COLOR);
+        let output = import_output(&mut graph);
+        let clear_pass = graph
+            .add_pass("clear")
+            .image(color, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        let blend_pass = graph
+            .add_pass("blend")
+            .image(color, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .build();
+        let sample_pass = graph
+            .add_pass("sample")
+            .image(color, ImageAccess::FRAGMENT_SAMPLED)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert_eq!(
+            compiled.pass(clear_pass).image_barriers,
+            [ImageBarrier {
+                image: color,
+                source: ImageAccess::UNDEFINED,
+                destination: ImageAccess::COLOR_ATTACHMENT_WRITE,
+            }]
+        );
+        assert_eq!(
+            compiled.pass(blend_pass).image_barriers,
+            [ImageBarrier {
+                image: color,
+                source: after(ImageAccess::COLOR_ATTACHMENT_WRITE),
+                destination: ImageAccess::COLOR_ATTACHMENT_READ_WRITE,
+            }]
+        );
+        assert_eq!(
+            compiled.pass(sample_pass).image_barriers,
+            [
+                ImageBarrier {
+                    image: color,
+                    source: after(ImageAccess::COLOR_ATTACHMENT_READ_WRITE),
+                    destination: ImageAccess::FRAGMENT_SAMPLED,
+                },
+                ImageBarrier {
+                    image: output,
+                    source: ImageAccess::UNDEFINED,
+                    destination: ImageAccess::COLOR_ATTACHMENT_WRITE,
+                },
+            ]
+        );
+        assert_eq!(
+            compiled.pass(sample_pass).image_barriers[0].source.layout,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        );
+        assert_eq!(
+            compiled.pass(sample_pass).image_barriers[0]
+                .destination
+                .layout,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        );
+    }
+
+    #[test]
+    fn side_effects_keep_pass_alive() {
+        let mut graph = RenderGraph::new();
+        let unused = graph.create_image("unused", COLOR);
+        let pass = graph
+            .add_pass("side effects")
+            .image(unused, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .side_effects()
+            .build();
+
+        let compiled = graph.compile();
+
+        assert!(!compiled.is_culled(pass));
+        assert_eq!(compiled.slot(unused), Some(0));
+    }
+
+    #[test]
+    fn leaves_imported_image_in_final_access() {
+        let mut graph = RenderGraph::new();
+        let swapchain = graph.import_image(
+            "swapchain",
+            Default::default(),
+            Default::default(),
+            vk::ImageAspectFlags::COLOR,
+            ImageAccess::UNDEFINED,
+            Some(ImageAccess::PRESENT),
+        );
+        graph
+            .add_pass("tonemap")
+            .image(swapchain, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert_eq!(
+            compiled.final_barriers(),
+            [ImageBarrier {
+                image: swapchain,
+                source: after(ImageAccess::COLOR_ATTACHMENT_WRITE),
+                destination: ImageAccess::PRESENT,
+            }]
+        );
+        assert_eq!(compiled.slot(swapchain), None);
+    }
+
+    #[test]
+    fn reuses_slot_for_disjoint_lifetimes() {
+        let mut graph = RenderGraph::new();
+        let first = graph.create_image("first", COLOR);
+        let second = graph.create_image("second", COLOR);
+        let output = import_output(&mut graph);
+        graph
+            .add_pass("write first")
+            .image(first, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        graph
+            .add_pass("read first")
+            .image(first, ImageAccess::FRAGMENT_SAMPLED)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        let write_second = graph
+            .add_pass("write second")
+            .image(second, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        graph
+            .add_pass("read second")
+            .image(second, ImageAccess::FRAGMENT_SAMPLED)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert_eq!(compiled.transient_slots(), [COLOR]);
+        assert_eq!(compiled.slot(first), Some(0));
+        assert_eq!(compiled.slot(second), Some(0));
+        // The alias starts undefined, but waits for the reads of the image before it.
+        assert_eq!(
+            compiled.pass(write_second).image_barriers,
+            [ImageBarrier {
+                image: second,
+                source: ImageAccess {
+                    layout: vk::ImageLayout::UNDEFINED,
+                    ..after(ImageAccess::FRAGMENT_SAMPLED)
+                },
+                destination: ImageAccess::COLOR_ATTACHMENT_WRITE,
+            }]
+        );
+    }
+
+    #[test]
+    fn keeps_overlapping_lifetimes_apart() {
+        let mut graph = RenderGraph::new();
+        let input = graph.create_image("input", COLOR);
+        let blurred = graph.create_image("blurred", COLOR);
+        let output = import_output(&mut graph);
+        graph
+            .add_pass("write input")
+            .image(input, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        graph
+            .add_pass("blur")
+            .image(input, ImageAccess::FRAGMENT_SAMPLED)
+            .image(blurred, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        graph
+            .add_pass("composite")
+            .image(blurred, ImageAccess::FRAGMENT_SAMPLED)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert_eq!(compiled.transient_slots(), [COLOR, COLOR]);
+        assert_ne!(compiled.slot(input), compiled.slot(blurred));
+        assert_ne!(compiled.slot_memory(0), compiled.slot_memory(1));
+    }
+
+    #[test]
+    fn aliases_memory_of_different_descriptions() {
+        let hdr = TransientImage {
+            format: vk::Format::R16G16B16A16_SFLOAT,
+            extent: vk::Extent2D {
+                width: 32,
+                height: 32,
+            },
+            ..COLOR
+        };
+        let mut graph = RenderGraph::new();
+        let color = graph.create_image("color", COLOR);
+        let bright = graph.create_image("bright", hdr);
+        let output = import_output(&mut graph);
+        graph
+            .add_pass("write color")
+            .image(color, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        graph
+            .add_pass("read color")
+            .image(color, ImageAccess::FRAGMENT_SAMPLED)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        let write_bright = graph
+            .add_pass("write bright")
+            .image(bright, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        graph
+            .add_pass("read bright")
+            .image(bright, ImageAccess::FRAGMENT_SAMPLED)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert_eq!(compiled.transient_slots(), [COLOR, hdr]);
+        assert_eq!(compiled.slot_memory(0), compiled.slot_memory(1));
+        // Another image over the same memory, so it waits like a reused slot does.
+        assert_eq!(
+            compiled.pass(write_bright).image_barriers,
+            [ImageBarrier {
+                image: bright,
+                source: ImageAccess {
+                    layout: vk::ImageLayout::UNDEFINED,
+                    ..after(ImageAccess::FRAGMENT_SAMPLED)
+                },
+                destination: ImageAccess::COLOR_ATTACHMENT_WRITE,
+            }]
+        );
+    }
+
+    #[test]
+    fn waits_on_initial_access_of_imported_image() {
+        let mut graph = RenderGraph::new();
+        let sampled_last_frame = ImageAccess {
+            layout: vk::ImageLayout::UNDEFINED,
+            ..ImageAccess::DEPTH_SAMPLED
+        };
+        let shadow_map = graph.import_image(
+            "shadow_map",
+            Default::default(),
+            Default::default(),
+            vk::ImageAspectFlags::DEPTH,
+            sampled_last_frame,
+            None,
+        );
+        let output = import_output(&mut graph);
+        let shadow_pass = graph
+            .add_pass("shadows")
+            .image(shadow_map, ImageAccess::DEPTH_ATTACHMENT)
+            .build();
+        let scene_pass = graph
+            .add_pass("scene")
+            .image(shadow_map, ImageAccess::DEPTH_SAMPLED)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert_eq!(
+            compiled.pass(shadow_pass).image_barriers,
+            [ImageBarrier {
+                image: shadow_map,
+                source: after(sampled_last_frame),
+                destination: ImageAccess::DEPTH_ATTACHMENT,
+            }]
+        );
+        assert_eq!(
+            compiled.pass(shadow_pass).image_barriers[0]
+                .source
+                .stage_mask,
+            vk::PipelineStageFlags2::FRAGMENT_SHADER
+        );
+        assert_eq!(
+            compiled.pass(scene_pass).image_barriers[0],
+            ImageBarrier {
+                image: shadow_map,
+                source: after(ImageAccess::DEPTH_ATTACHMENT),
+                destination: ImageAccess::DEPTH_SAMPLED,
+            }
+        );
+    }
+
+    #[test]
+    fn places_buffer_barrier_between_write_and_indirect_reads() {
+        let mut graph = RenderGraph::new();
+        let draw_buffers = [
+            graph.import_buffer("draw_commands", Default::default()),
+            graph.import_buffer("draw_counts", Default::default()),
+        ];
+        let output = import_output(&mut graph);
+        let culling_pass = graph
+            .add_pass("culling")
+            .buffers(&draw_buffers, BufferAccess::COMPUTE_STORAGE_WRITE)
+            .build();
+        let first_draw_pass = graph
+            .add_pass("first_draw")
+            .buffers(&draw_buffers, BufferAccess::INDIRECT_READ)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        let second_draw_pass = graph
+            .add_pass("second_draw")
+            .buffers(&draw_buffers, BufferAccess::INDIRECT_READ)
+            .image(output, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .build();
+
+        let compiled = graph.compile();
+
+        assert!(!compiled.is_culled(culling_pass));
+        assert!(compiled.pass(culling_pass).buffer_barriers.is_empty());
+        assert_eq!(
+            compiled.pass(first_draw_pass).buffer_barriers,
+            draw_buffers.map(|buffer| BufferBarrier {
+                buffer,
+                source: BufferAccess::COMPUTE_STORAGE_WRITE,
+                destination: BufferAccess::INDIRECT_READ,
+            })
+        );
+        assert!(compiled.pass(second_draw_pass).buffer_barriers.is_empty());
+    }
+}
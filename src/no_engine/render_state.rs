@@ -0,0 +1,195 @@
+use ash::vk;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Msaa {
+    Off,
+    X2,
+    #[default]
+    X4,
+    X8,
+}
+
+impl Msaa {
+    #[inline(always)]
+    pub fn sample_count(self) -> vk::SampleCountFlags {
+        match self {
+            Msaa::Off => vk::SampleCountFlags::TYPE_1,
+            Msaa::X2 => vk::SampleCountFlags::TYPE_2,
+            Msaa::X4 => vk::SampleCountFlags::TYPE_4,
+            Msaa::X8 => vk::SampleCountFlags::TYPE_8,
+        }
+    }
+
+    #[inline(always)]
+    pub fn next(self) -> Self {
+        match self {
+            Msaa::Off => Msaa::X2,
+            Msaa::X2 => Msaa::X4,
+            Msaa::X4 => Msaa::X8,
+            Msaa::X8 => Msaa::Off,
+        }
+    }
+}
+
+/// Fixed-function state which has to be set dynamically, because shader objects
+/// have no pipelines to bake it into.
+#[derive(Clone, Copy)]
+pub struct RenderState {
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    pub topology: vk::PrimitiveTopology,
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_compare_op: vk::CompareOp,
+    /// Constant and slope factors.
+    pub depth_bias: Option<(f32, f32)>,
+    /// Clamps depth to the viewport instead of clipping at the near and far planes.
+    pub depth_clamp: bool,
+    pub samples: vk::SampleCountFlags,
+    /// Applies to the first color attachment.
+    pub blend_equation: Option<vk::ColorBlendEquationEXT>,
+    /// Applies to the color attachments after the first.
+    pub extra_blend_equation: Option<vk::ColorBlendEquationEXT>,
+    /// Write mask of the color attachments after the first, empty for overlays
+    /// which shouldn't touch them.
+    pub extra_write_mask: vk::ColorComponentFlags,
+    pub color_attachments_count: u32,
+}
+
+impl RenderState {
+    pub const MAX_COLOR_ATTACHMENTS: usize = 8;
+
+    pub fn opaque() -> Self {
+        Self {
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::BACK,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            depth_test: true,
+            depth_write: true,
+            depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
+            depth_bias: None,
+            depth_clamp: false,
+            samples: vk::SampleCountFlags::TYPE_1,
+            blend_equation: None,
+            extra_blend_equation: None,
+            extra_write_mask: vk::ColorComponentFlags::RGBA,
+            color_attachments_count: 1,
+        }
+    }
+
+    /// Single color attachment without depth, used by fullscreen passes.
+    pub fn fullscreen() -> Self {
+        Self {
+            cull_mode: vk::CullModeFlags::NONE,
+            depth_test: false,
+            depth_write: false,
+            ..Self::opaque()
+        }
+    }
+
+    /// Depth only, biased to keep shadow acne away. Casters between the light and a cascade
+    /// are outside its depth range, clamping keeps them at its near plane.
+    pub fn shadow() -> Self {
+        Self {
+            cull_mode: vk::CullModeFlags::NONE,
+            depth_bias: Some((1.25, 1.75)),
+            depth_clamp: true,
+            color_attachments_count: Default::default(),
+            ..Self::opaque()
+        }
+    }
+
+    pub fn apply(
+        &self,
+        device: &ash::Device,
+        shader_object: &ash::extensions::ext::ShaderObject,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+    ) {
+        let viewports = [vk::Viewport {
+            x: Default::default(),
+            y: Default::default(),
+            width: extent.width as _,
+            height: extent.height as _,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }];
+        let scissors = [vk::Rect2D {
+            offset: Default::default(),
+            extent,
+        }];
+
+        let attachments_count = self.color_attachments_count as usize;
+        let blend_equation = |index| {
+            if index == 0 {
+                self.blend_equation
+            } else {
+                self.extra_blend_equation
+            }
+        };
+        let blend_enables: [vk::Bool32; Self::MAX_COLOR_ATTACHMENTS] =
+            std::array::from_fn(|index| blend_equation(index).is_some().into());
+        let blend_equations: [vk::ColorBlendEquationEXT; Self::MAX_COLOR_ATTACHMENTS] =
+            std::array::from_fn(|index| blend_equation(index).unwrap_or_default());
+        let write_masks: [vk::ColorComponentFlags; Self::MAX_COLOR_ATTACHMENTS] =
+            std::array::from_fn(|index| {
+                if index == 0 {
+                    vk::ColorComponentFlags::RGBA
+                } else {
+                    self.extra_write_mask
+                }
+            });
+        let sample_mask = [vk::SampleMask::MAX];
+
+        unsafe {
+            shader_object.cmd_set_viewport_with_count(command_buffer, &viewports);
+            shader_object.cmd_set_scissor_with_count(command_buffer, &scissors);
+            shader_object.cmd_set_rasterizer_discard_enable(command_buffer, false);
+            shader_object.cmd_set_polygon_mode(command_buffer, self.polygon_mode);
+            shader_object.cmd_set_cull_mode(command_buffer, self.cull_mode);
+            shader_object.cmd_set_front_face(command_buffer, self.front_face);
+            shader_object.cmd_set_primitive_topology(command_buffer, self.topology);
+            shader_object.cmd_set_primitive_restart_enable(command_buffer, false);
+            shader_object.cmd_set_depth_test_enable(command_buffer, self.depth_test);
+            shader_object.cmd_set_depth_write_enable(command_buffer, self.depth_write);
+            shader_object.cmd_set_depth_compare_op(command_buffer, self.depth_compare_op);
+            shader_object.cmd_set_depth_clamp_enable(command_buffer, self.depth_clamp);
+            shader_object.cmd_set_depth_bias_enable(command_buffer, self.depth_bias.is_some());
+            if let Some((constant_factor, slope_factor)) = self.depth_bias {
+                device.cmd_set_depth_bias(
+                    command_buffer,
+                    constant_factor,
+                    Default::default(),
+                    slope_factor,
+                );
+            }
+            shader_object.cmd_set_depth_bounds_test_enable(command_buffer, false);
+            shader_object.cmd_set_stencil_test_enable(command_buffer, false);
+            shader_object.cmd_set_rasterization_samples(command_buffer, self.samples);
+            shader_object.cmd_set_sample_mask(command_buffer, self.samples, &sample_mask);
+            shader_object.cmd_set_alpha_to_coverage_enable(command_buffer, false);
+
+            if attachments_count > 0 {
+                shader_object.cmd_set_color_blend_enable(
+                    command_buffer,
+                    Default::default(),
+                    &blend_enables[..attachments_count],
+                );
+                shader_object.cmd_set_color_blend_equation(
+                    command_buffer,
+                    Default::default(),
+                    &blend_equations[..attachments_count],
+                );
+                shader_object.cmd_set_color_write_mask(
+                    command_buffer,
+                    Default::default(),
+                    &write_masks[..attachments_count],
+                );
+            }
+
+            device.cmd_set_line_width(command_buffer, 1.0);
+        }
+    }
+}
@@ -4,7 +4,9 @@ use ash::vk;
 
 pub struct RenderingInfo<'a> {
     pub command_buffer_info: vk::CommandBufferBeginInfo<'a>,
-    pub color_attachments: arrayvec::ArrayVec<vk::RenderingAttachmentInfoKHR<'a>, 1>,
+    /// Scene color and picking ids. The deferred G-buffer pass builds its own attachments, so
+    /// the capacity only covers the scene pass.
+    pub color_attachments: arrayvec::ArrayVec<vk::RenderingAttachmentInfoKHR<'a>, 2>,
     pub depth_attachment: vk::RenderingAttachmentInfoKHR<'a>,
     pub clear_values: vk::ClearValue,
     pub present_semaphores: Rc<[vk::Semaphore]>,
@@ -0,0 +1,213 @@
+use ash::vk;
+
+use super::{
+    allocator::{AllocatedBuffer, Allocator},
+    compute::{ComputeBarrier, ComputeDispatch},
+    gpu_data::OutlinePushConstants,
+    instances::InstanceManager,
+    objects::ObjectType,
+    render_graph::{
+        CompiledGraph, ImageAccess, ImageHandle, PassHandle, RenderGraph, TransientImage,
+        TransientPool,
+    },
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    Id,
+};
+
+/// Graph resources of the outline pass, the seed images are transient.
+pub struct SelectionPass {
+    pass: PassHandle,
+    ids: ImageHandle,
+    /// Packed seed coordinates, ping-ponged by the flood steps.
+    seeds: [ImageHandle; 2],
+    /// Flood steps from the largest one down.
+    steps: Vec<u32>,
+    /// Seed image the last step writes, what the outline is composited from.
+    pub outline: ImageHandle,
+}
+
+/// Selected instances and the distance field their outline is drawn from.
+///
+/// Pixels showing a selected instance in the picking ids seed a jump flood, which leaves
+/// every pixel with the closest seed within the outline thickness.
+pub struct Selection {
+    selected: Vec<Id>,
+    /// One flag per instance of the last packed frame.
+    flags: Vec<u32>,
+    flags_buffer: AllocatedBuffer,
+}
+
+impl Selection {
+    pub const SEED_SHADER_NAME: &'static str = "outline_seed";
+    pub const JUMP_FLOOD_SHADER_NAME: &'static str = "outline_jump_flood";
+    pub const SEED_FORMAT: vk::Format = vk::Format::R32_UINT;
+    pub const WORKGROUP_SIZE: u32 = 8;
+
+    pub const FLAGS_SLOT: u32 = 6;
+    pub const INPUT_SLOT: u32 = 0;
+    pub const OUTPUT_SLOT: u32 = 1;
+
+    pub fn new(allocator: &Allocator) -> Self {
+        let flags_buffer = allocator.allocate_uninit_buffer(
+            (InstanceManager::MAX_INSTANCES * std::mem::size_of::<u32>()) as _,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            ObjectType::Storage,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+
+        Self {
+            selected: Default::default(),
+            flags: Vec::with_capacity(InstanceManager::MAX_INSTANCES),
+            flags_buffer,
+        }
+    }
+
+    #[inline(always)]
+    pub fn selected(&self) -> &[Id] {
+        &self.selected
+    }
+
+    #[inline(always)]
+    pub fn is_selected(&self, id: Id) -> bool {
+        self.selected.contains(&id)
+    }
+
+    #[inline(always)]
+    pub fn select(&mut self, id: Id) {
+        if !self.is_selected(id) {
+            self.selected.push(id);
+        }
+    }
+
+    #[inline(always)]
+    pub fn deselect(&mut self, id: Id) {
+        self.selected.retain(|&selected_id| selected_id != id);
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.selected.clear();
+    }
+
+    /// Flags the selected instances of the last packed frame, `false` when none of them is
+    /// drawn and there is no outline to render.
+    pub fn pack(&mut self, allocator: &Allocator, instance_manager: &InstanceManager) -> bool {
+        self.flags.clear();
+        self.flags.extend(
+            instance_manager
+                .instance_ids()
+                .iter()
+                .map(|&id| self.selected.contains(&id) as u32),
+        );
+        if !self.flags.contains(&1) {
+            return false;
+        }
+        allocator.write_buffer(&self.flags_buffer, &self.flags);
+
+        true
+    }
+
+    /// Declares the seed and flood dispatches as one pass reading the single sampled `ids`.
+    /// Steps halve from the smallest power of two covering `thickness` down to one.
+    pub fn add_pass(
+        graph: &mut RenderGraph,
+        extent: vk::Extent2D,
+        ids: ImageHandle,
+        thickness: f32,
+    ) -> SelectionPass {
+        let description = TransientImage {
+            format: Self::SEED_FORMAT,
+            extent,
+            samples: vk::SampleCountFlags::TYPE_1,
+            usage: vk::ImageUsageFlags::STORAGE,
+        };
+        let seeds = [
+            graph.create_image("outline_seeds_ping", description),
+            graph.create_image("outline_seeds_pong", description),
+        ];
+        let pass = graph
+            .add_pass("selection")
+            .image(ids, ImageAccess::COMPUTE_STORAGE_READ)
+            .image(seeds[0], ImageAccess::COMPUTE_STORAGE_READ_WRITE)
+            .image(seeds[1], ImageAccess::COMPUTE_STORAGE_READ_WRITE)
+            .build();
+
+        let steps = std::iter::successors(
+            Some((thickness.ceil().max(1.0) as u32).next_power_of_two()),
+            |&step| (step > 1).then_some(step / 2),
+        )
+        .collect::<Vec<_>>();
+
+        SelectionPass {
+            pass,
+            ids,
+            seeds,
+            outline: seeds[steps.len() % 2],
+            steps,
+        }
+    }
+
+    /// Runs the seed and flood dispatches, the pass compositing the outline declares reading
+    /// it. Nothing is recorded if the graph culled the pass.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        selection_pass: &SelectionPass,
+    ) {
+        let (Some(ids_view), [Some(ping_view), Some(pong_view)]) = (
+            graph.image_view(selection_pass.ids, pool),
+            selection_pass
+                .seeds
+                .map(|seed| graph.image_view(seed, pool)),
+        ) else {
+            return;
+        };
+        let seed_views = [ping_view, pong_view];
+
+        graph.begin_pass(device, command_buffer, selection_pass.pass, pool);
+
+        let extent = vk::Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        };
+        let workgroup_size = [Self::WORKGROUP_SIZE, Self::WORKGROUP_SIZE, 1];
+
+        ComputeDispatch::covering(Self::SEED_SHADER_NAME, extent, workgroup_size)
+            .descriptors(
+                DescriptorWriter::new()
+                    .storage_buffer(Self::FLAGS_SLOT, &self.flags_buffer)
+                    .storage_image(Self::INPUT_SLOT, ids_view)
+                    .storage_image(Self::OUTPUT_SLOT, seed_views[0]),
+            )
+            .barrier(ComputeBarrier::COMPUTE_TO_COMPUTE)
+            .record(device, shader_manager, command_buffer);
+
+        let mut source_index = 0;
+        for (index, &step) in selection_pass.steps.iter().enumerate() {
+            let mut dispatch =
+                ComputeDispatch::covering(Self::JUMP_FLOOD_SHADER_NAME, extent, workgroup_size)
+                    .descriptors(
+                        DescriptorWriter::new()
+                            .storage_image(Self::INPUT_SLOT, seed_views[source_index])
+                            .storage_image(Self::OUTPUT_SLOT, seed_views[source_index ^ 1]),
+                    )
+                    .push_constants(&OutlinePushConstants { step });
+            if index != selection_pass.steps.len() - 1 {
+                dispatch = dispatch.barrier(ComputeBarrier::COMPUTE_TO_COMPUTE);
+            }
+            dispatch.record(device, shader_manager, command_buffer);
+            source_index ^= 1;
+        }
+    }
+
+    pub fn destroy(&self, allocator: &Allocator) {
+        allocator.destroy_buffer(&self.flags_buffer);
+    }
+}
@@ -1,10 +1,14 @@
-mod layouts;
+pub mod descriptors;
+pub mod layouts;
 
 use std::{collections::HashMap, ffi::CStr};
 
 use ash::vk;
 
-use self::layouts::ShaderLayout;
+use self::{
+    descriptors::{DescriptorSlots, DescriptorWriter},
+    layouts::ShaderLayout,
+};
 
 use super::Id;
 
@@ -13,6 +17,7 @@ enum ShaderType {
 }
 
 pub struct RawShader {
+    pub name: String,
     pub stage: vk::ShaderStageFlags,
     pub next_stage: vk::ShaderStageFlags,
     pub raw: Vec<u8>,
@@ -20,11 +25,13 @@ pub struct RawShader {
 
 impl RawShader {
     pub fn new(
+        name: String,
         shader_stage: vk::ShaderStageFlags,
         next_shader_stage: vk::ShaderStageFlags,
         raw: Vec<u8>,
     ) -> Self {
         Self {
+            name,
             stage: shader_stage,
             next_stage: next_shader_stage,
             raw,
@@ -32,15 +39,25 @@ impl RawShader {
     }
 }
 
+#[derive(getset::Getters, getset::CopyGetters)]
 pub struct ShaderObject<'a> {
+    #[getset(get_copy = "pub")]
     id: Id,
+    #[getset(get = "pub")]
+    name: String,
+    #[getset(get_copy = "pub")]
     stage: vk::ShaderStageFlags,
+    #[getset(get_copy = "pub")]
     shader: vk::ShaderEXT,
+    #[getset(get = "pub")]
     shader_layout: ShaderLayout<'a>,
 }
 
 pub struct ShaderManager<'a> {
-    shader_object: ash::extensions::ext::ShaderObject,
+    pub shader_object: ash::extensions::ext::ShaderObject,
+    pub push_descriptor: ash::extensions::khr::PushDescriptor,
+    pub descriptor_set_layout: vk::DescriptorSetLayout,
+    pub pipeline_layout: vk::PipelineLayout,
     compiler_options: shaderc::CompileOptions<'a>,
     compiler: shaderc::Compiler,
     compiled_shaders: HashMap<Id, RawShader>,
@@ -56,6 +73,19 @@ impl ShaderManager<'_> {
 
     pub fn new(instance: &ash::Instance, device: &ash::Device) -> Self {
         let shader_object = ash::extensions::ext::ShaderObject::new(instance, device);
+        let push_descriptor = ash::extensions::khr::PushDescriptor::new(instance, device);
+
+        let descriptor_set_layout = DescriptorSlots::create_set_layout(device);
+        let set_layouts = [descriptor_set_layout];
+        let push_constant_ranges = [DescriptorSlots::push_constant_range()];
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constant_ranges);
+        let pipeline_layout = unsafe {
+            device
+                .create_pipeline_layout(&pipeline_layout_info, None)
+                .unwrap()
+        };
 
         let compiler = shaderc::Compiler::new().unwrap();
         let mut compiler_options = shaderc::CompileOptions::new().unwrap();
@@ -71,6 +101,9 @@ impl ShaderManager<'_> {
 
         Self {
             shader_object,
+            push_descriptor,
+            descriptor_set_layout,
+            pipeline_layout,
             compiler,
             compiler_options,
             compiled_shaders: Default::default(),
@@ -144,39 +177,73 @@ impl ShaderManager<'_> {
 
         let current_stage = Self::map_shader_stage(shader_type);
         let next_stage = Self::map_next_stage(current_stage);
-        let compiled_shader =
-            RawShader::new(current_stage, next_stage, spirv.as_binary_u8().to_vec());
+        let compiled_shader = RawShader::new(
+            shader_name.to_owned(),
+            current_stage,
+            next_stage,
+            spirv.as_binary_u8().to_vec(),
+        );
 
         let id = Id::new();
         self.compiled_shaders.insert(id, compiled_shader);
         self.shader_queue_to_load.push(id)
     }
 
+    /// Vertex and fragment shaders with the same name are linked together,
+    /// compute shaders are always created unlinked.
     pub fn upload_required_shaders(&mut self) {
-        let shader_infos: Vec<_> = self
-            .shader_queue_to_load
-            .drain(..)
+        let mut programs: Vec<Vec<Id>> = Default::default();
+        for compiled_shader_id in std::mem::take(&mut self.shader_queue_to_load) {
+            let compiled_shader = self
+                .compiled_shaders
+                .get(&compiled_shader_id)
+                .expect("Shader not found");
+
+            let program = programs.iter_mut().find(|program| {
+                let linked_shader = &self.compiled_shaders[&program[0]];
+                compiled_shader.stage != vk::ShaderStageFlags::COMPUTE
+                    && linked_shader.stage != vk::ShaderStageFlags::COMPUTE
+                    && linked_shader.name == compiled_shader.name
+            });
+
+            match program {
+                Some(program) => program.push(compiled_shader_id),
+                None => programs.push(vec![compiled_shader_id]),
+            }
+        }
+
+        programs
+            .iter()
+            .for_each(|program| self.upload_program(program));
+    }
+
+    fn upload_program(&mut self, compiled_shader_ids: &[Id]) {
+        let set_layouts = [self.descriptor_set_layout];
+        let push_constant_ranges = [DescriptorSlots::push_constant_range()];
+
+        let shader_infos: Vec<_> = compiled_shader_ids
+            .iter()
             .map(|compiled_shader_id| {
                 let compiled_shader = self
                     .compiled_shaders
-                    .get(&compiled_shader_id)
+                    .get(compiled_shader_id)
                     .expect("Shader not found");
 
-                let shader_object = ShaderObject {
-                    id: Id::new(),
-                    stage: compiled_shader.stage,
-                    shader: Default::default(),
-                    shader_layout: ShaderLayout::new(),
+                let flags = if compiled_shader_ids.len() > 1 {
+                    vk::ShaderCreateFlagsEXT::LINK_STAGE
+                } else {
+                    vk::ShaderCreateFlagsEXT::empty()
                 };
 
-                self.uploaded_shaders.push(shader_object);
-
                 vk::ShaderCreateInfoEXT::default()
-                    .flags(vk::ShaderCreateFlagsEXT::LINK_STAGE)
+                    .flags(flags)
                     .stage(compiled_shader.stage)
                     .next_stage(compiled_shader.next_stage)
+                    .code_type(vk::ShaderCodeTypeEXT::SPIRV)
                     .name(Self::DEFAULT_ENTRY_POINT_RAW)
                     .code(&compiled_shader.raw)
+                    .set_layouts(&set_layouts)
+                    .push_constant_ranges(&push_constant_ranges)
             })
             .collect();
 
@@ -186,12 +253,21 @@ impl ShaderManager<'_> {
                 .unwrap()
         };
 
-        let start_idx = self.uploaded_shaders.len() - uploaded_shaders.len();
-        for (shader_object, uploaded_shader) in self.uploaded_shaders[start_idx..]
-            .iter_mut()
-            .zip(uploaded_shaders)
+        for (compiled_shader_id, uploaded_shader) in
+            compiled_shader_ids.iter().zip(uploaded_shaders)
         {
-            shader_object.shader = uploaded_shader;
+            let compiled_shader = &self.compiled_shaders[compiled_shader_id];
+
+            self.uploaded_shaders.push(ShaderObject {
+                id: Id::new(),
+                name: compiled_shader.name.clone(),
+                stage: compiled_shader.stage,
+                shader: uploaded_shader,
+                shader_layout: ShaderLayout::for_shader(
+                    &compiled_shader.name,
+                    compiled_shader.stage,
+                ),
+            });
         }
     }
 
@@ -200,6 +276,90 @@ impl ShaderManager<'_> {
         self.uploaded_shaders.as_slice()
     }
 
+    #[inline(always)]
+    pub fn get_shader(&self, name: &str, stage: vk::ShaderStageFlags) -> Option<&ShaderObject> {
+        self.uploaded_shaders
+            .iter()
+            .find(|shader_object| shader_object.stage == stage && shader_object.name == name)
+    }
+
+    /// Binds the vertex and fragment stages of a linked program, programs
+    /// without a fragment shader are rendered depth only.
+    #[inline(always)]
+    pub fn bind_graphics_program(&self, command_buffer: vk::CommandBuffer, name: &str) {
+        self.bind_graphics_shaders(command_buffer, name, name);
+    }
+
+    /// Shaders from different files can only be mixed when neither of them is linked.
+    #[inline(always)]
+    pub fn bind_graphics_shaders(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        vertex_name: &str,
+        fragment_name: &str,
+    ) {
+        let vertex_shader = self
+            .get_shader(vertex_name, vk::ShaderStageFlags::VERTEX)
+            .expect("Vertex shader not found");
+        let fragment_shader = self
+            .get_shader(fragment_name, vk::ShaderStageFlags::FRAGMENT)
+            .map(|fragment_shader| fragment_shader.shader)
+            .unwrap_or_default();
+
+        unsafe {
+            self.shader_object.cmd_bind_shaders(
+                command_buffer,
+                &[vk::ShaderStageFlags::VERTEX, vk::ShaderStageFlags::FRAGMENT],
+                &[vertex_shader.shader, fragment_shader],
+            );
+        }
+
+        vertex_shader
+            .shader_layout
+            .set_vertex_input(&self.shader_object, command_buffer);
+    }
+
+    /// `false` and nothing bound if no compute shader has the name.
+    #[inline(always)]
+    pub fn bind_compute_shader(&self, command_buffer: vk::CommandBuffer, name: &str) -> bool {
+        let Some(compute_shader) = self.get_shader(name, vk::ShaderStageFlags::COMPUTE) else {
+            return false;
+        };
+
+        unsafe {
+            self.shader_object.cmd_bind_shaders(
+                command_buffer,
+                &[vk::ShaderStageFlags::COMPUTE],
+                &[compute_shader.shader],
+            );
+        }
+
+        true
+    }
+
+    #[inline(always)]
+    pub fn push_descriptors(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_bind_point: vk::PipelineBindPoint,
+        descriptor_writer: &DescriptorWriter,
+    ) {
+        descriptor_writer.push(
+            &self.push_descriptor,
+            command_buffer,
+            pipeline_bind_point,
+            self.pipeline_layout,
+        );
+    }
+
+    #[inline(always)]
+    pub fn destroy_layouts(&mut self, device: &ash::Device) {
+        unsafe {
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+    }
+
     #[inline(always)]
     pub fn clear_uploaded_shaders(&mut self) {
         unsafe {
@@ -0,0 +1,197 @@
+use arrayvec::ArrayVec;
+use ash::vk;
+
+use crate::no_engine::allocator::AllocatedBuffer;
+
+/// Every shader shares one push descriptor set with fixed slots, so passes only
+/// push what their shaders actually read.
+pub struct DescriptorSlots;
+
+impl DescriptorSlots {
+    pub const FRAME_DATA: u32 = 0;
+    pub const STORAGE_BUFFERS: std::ops::Range<u32> = 1..9;
+    pub const SAMPLED_IMAGES: std::ops::Range<u32> = 9..21;
+    pub const STORAGE_IMAGES: std::ops::Range<u32> = 21..25;
+
+    pub const PUSH_CONSTANTS_SIZE: u32 = 128;
+
+    #[inline(always)]
+    pub fn storage_buffer(index: u32) -> u32 {
+        debug_assert!(index < Self::STORAGE_BUFFERS.len() as u32);
+        Self::STORAGE_BUFFERS.start + index
+    }
+
+    #[inline(always)]
+    pub fn sampled_image(index: u32) -> u32 {
+        debug_assert!(index < Self::SAMPLED_IMAGES.len() as u32);
+        Self::SAMPLED_IMAGES.start + index
+    }
+
+    #[inline(always)]
+    pub fn storage_image(index: u32) -> u32 {
+        debug_assert!(index < Self::STORAGE_IMAGES.len() as u32);
+        Self::STORAGE_IMAGES.start + index
+    }
+
+    pub fn create_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+        let binding = |binding: u32, descriptor_type: vk::DescriptorType| {
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(binding)
+                .descriptor_type(descriptor_type)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::ALL)
+        };
+
+        let bindings = std::iter::once(binding(
+            Self::FRAME_DATA,
+            vk::DescriptorType::UNIFORM_BUFFER,
+        ))
+        .chain(Self::STORAGE_BUFFERS.map(|slot| binding(slot, vk::DescriptorType::STORAGE_BUFFER)))
+        .chain(
+            Self::SAMPLED_IMAGES
+                .map(|slot| binding(slot, vk::DescriptorType::COMBINED_IMAGE_SAMPLER)),
+        )
+        .chain(Self::STORAGE_IMAGES.map(|slot| binding(slot, vk::DescriptorType::STORAGE_IMAGE)))
+        .collect::<Vec<_>>();
+
+        let set_layout_info = vk::DescriptorSetLayoutCreateInfo::default()
+            .flags(vk::DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR)
+            .bindings(&bindings);
+
+        unsafe {
+            device
+                .create_descriptor_set_layout(&set_layout_info, None)
+                .unwrap()
+        }
+    }
+
+    #[inline(always)]
+    pub fn push_constant_range() -> vk::PushConstantRange {
+        vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::ALL,
+            offset: Default::default(),
+            size: Self::PUSH_CONSTANTS_SIZE,
+        }
+    }
+}
+
+/// Collects descriptors of a single pass and pushes them in one call.
+#[derive(Clone, Default)]
+pub struct DescriptorWriter {
+    buffers: ArrayVec<(u32, vk::DescriptorType, vk::DescriptorBufferInfo), 16>,
+    images: ArrayVec<(u32, vk::DescriptorType, vk::DescriptorImageInfo), 16>,
+}
+
+impl DescriptorWriter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline(always)]
+    pub fn uniform_buffer(mut self, allocated_buffer: &AllocatedBuffer) -> Self {
+        self.buffers.push((
+            DescriptorSlots::FRAME_DATA,
+            vk::DescriptorType::UNIFORM_BUFFER,
+            Self::buffer_info(allocated_buffer),
+        ));
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn storage_buffer(mut self, index: u32, allocated_buffer: &AllocatedBuffer) -> Self {
+        self.buffers.push((
+            DescriptorSlots::storage_buffer(index),
+            vk::DescriptorType::STORAGE_BUFFER,
+            Self::buffer_info(allocated_buffer),
+        ));
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn sampled_image(
+        mut self,
+        index: u32,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+        image_layout: vk::ImageLayout,
+    ) -> Self {
+        self.images.push((
+            DescriptorSlots::sampled_image(index),
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            vk::DescriptorImageInfo {
+                sampler,
+                image_view,
+                image_layout,
+            },
+        ));
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn storage_image(mut self, index: u32, image_view: vk::ImageView) -> Self {
+        self.images.push((
+            DescriptorSlots::storage_image(index),
+            vk::DescriptorType::STORAGE_IMAGE,
+            vk::DescriptorImageInfo {
+                sampler: Default::default(),
+                image_view,
+                image_layout: vk::ImageLayout::GENERAL,
+            },
+        ));
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn push(
+        &self,
+        push_descriptor: &ash::extensions::khr::PushDescriptor,
+        command_buffer: vk::CommandBuffer,
+        pipeline_bind_point: vk::PipelineBindPoint,
+        pipeline_layout: vk::PipelineLayout,
+    ) {
+        let buffer_writes = self
+            .buffers
+            .iter()
+            .map(|(binding, descriptor_type, buffer_info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_binding(*binding)
+                    .descriptor_type(*descriptor_type)
+                    .buffer_info(std::slice::from_ref(buffer_info))
+            });
+        let image_writes = self
+            .images
+            .iter()
+            .map(|(binding, descriptor_type, image_info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_binding(*binding)
+                    .descriptor_type(*descriptor_type)
+                    .image_info(std::slice::from_ref(image_info))
+            });
+        let descriptor_writes = buffer_writes
+            .chain(image_writes)
+            .collect::<ArrayVec<_, 32>>();
+
+        unsafe {
+            push_descriptor.cmd_push_descriptor_set(
+                command_buffer,
+                pipeline_bind_point,
+                pipeline_layout,
+                Default::default(),
+                &descriptor_writes,
+            );
+        }
+    }
+
+    #[inline(always)]
+    fn buffer_info(allocated_buffer: &AllocatedBuffer) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: allocated_buffer.buffer,
+            offset: allocated_buffer.offset,
+            range: allocated_buffer.size,
+        }
+    }
+}
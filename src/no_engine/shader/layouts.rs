@@ -1,5 +1,10 @@
+use arrayvec::ArrayVec;
 use ash::vk;
 
+use crate::no_engine::{
+    debug_draw::DebugVertex, objects::vertex_format::VertexFormat, text::TextVertex,
+};
+
 pub struct ShaderBinding<'a> {
     pub binding_description: vk::VertexInputBindingDescription2EXT<'a>,
     pub attribute_descriptions: Vec<vk::VertexInputAttributeDescription2EXT<'a>>,
@@ -10,9 +15,210 @@ pub struct ShaderLayout<'a> {
 }
 
 impl ShaderLayout<'_> {
+    pub const MAX_BINDINGS: usize = 4;
+    pub const MAX_ATTRIBUTES: usize = 16;
+
     pub fn new() -> Self {
         Self {
             bindings: Default::default(),
         }
     }
+
+    /// One interleaved binding with the attributes at locations 0 to 3.
+    pub fn for_vertex_format(vertex_format: VertexFormat) -> Self {
+        let binding_description = vk::VertexInputBindingDescription2EXT::default()
+            .binding(Default::default())
+            .stride(vertex_format.stride())
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .divisor(1);
+
+        let attribute_descriptions = vertex_format
+            .attributes()
+            .into_iter()
+            .enumerate()
+            .map(|(location, (format, offset))| {
+                vk::VertexInputAttributeDescription2EXT::default()
+                    .location(location as _)
+                    .binding(Default::default())
+                    .format(format)
+                    .offset(offset)
+            })
+            .collect();
+
+        Self {
+            bindings: vec![ShaderBinding {
+                binding_description,
+                attribute_descriptions,
+            }],
+        }
+    }
+
+    /// World space position and RGBA8 color of `DebugDraw` lines.
+    pub fn debug_lines() -> Self {
+        let binding_description = vk::VertexInputBindingDescription2EXT::default()
+            .binding(Default::default())
+            .stride(std::mem::size_of::<DebugVertex>() as _)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .divisor(1);
+
+        let attribute = |location: u32, format: vk::Format, offset: usize| {
+            vk::VertexInputAttributeDescription2EXT::default()
+                .location(location)
+                .binding(Default::default())
+                .format(format)
+                .offset(offset as _)
+        };
+        let attribute_descriptions = vec![
+            attribute(
+                0,
+                vk::Format::R32G32B32_SFLOAT,
+                std::mem::offset_of!(DebugVertex, position),
+            ),
+            attribute(
+                1,
+                vk::Format::R8G8B8A8_UNORM,
+                std::mem::offset_of!(DebugVertex, color),
+            ),
+        ];
+
+        Self {
+            bindings: vec![ShaderBinding {
+                binding_description,
+                attribute_descriptions,
+            }],
+        }
+    }
+
+    /// Screen position in points, UV and sRGB premultiplied RGBA8 color of egui vertices.
+    pub fn ui() -> Self {
+        let binding_description = vk::VertexInputBindingDescription2EXT::default()
+            .binding(Default::default())
+            .stride(std::mem::size_of::<egui::epaint::Vertex>() as _)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .divisor(1);
+
+        let attribute = |location: u32, format: vk::Format, offset: usize| {
+            vk::VertexInputAttributeDescription2EXT::default()
+                .location(location)
+                .binding(Default::default())
+                .format(format)
+                .offset(offset as _)
+        };
+        let attribute_descriptions = vec![
+            attribute(
+                0,
+                vk::Format::R32G32_SFLOAT,
+                std::mem::offset_of!(egui::epaint::Vertex, pos),
+            ),
+            attribute(
+                1,
+                vk::Format::R32G32_SFLOAT,
+                std::mem::offset_of!(egui::epaint::Vertex, uv),
+            ),
+            attribute(
+                2,
+                vk::Format::R8G8B8A8_UNORM,
+                std::mem::offset_of!(egui::epaint::Vertex, color),
+            ),
+        ];
+
+        Self {
+            bindings: vec![ShaderBinding {
+                binding_description,
+                attribute_descriptions,
+            }],
+        }
+    }
+
+    /// Screen position in pixels, atlas UV and RGBA8 color of `TextRenderer` glyphs.
+    pub fn text() -> Self {
+        let binding_description = vk::VertexInputBindingDescription2EXT::default()
+            .binding(Default::default())
+            .stride(std::mem::size_of::<TextVertex>() as _)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .divisor(1);
+
+        let attribute = |location: u32, format: vk::Format, offset: usize| {
+            vk::VertexInputAttributeDescription2EXT::default()
+                .location(location)
+                .binding(Default::default())
+                .format(format)
+                .offset(offset as _)
+        };
+        let attribute_descriptions = vec![
+            attribute(
+                0,
+                vk::Format::R32G32_SFLOAT,
+                std::mem::offset_of!(TextVertex, position),
+            ),
+            attribute(
+                1,
+                vk::Format::R32G32_SFLOAT,
+                std::mem::offset_of!(TextVertex, uv),
+            ),
+            attribute(
+                2,
+                vk::Format::R8G8B8A8_UNORM,
+                std::mem::offset_of!(TextVertex, color),
+            ),
+        ];
+
+        Self {
+            bindings: vec![ShaderBinding {
+                binding_description,
+                attribute_descriptions,
+            }],
+        }
+    }
+
+    /// Same attributes advanced per instance instead, so every vertex can be expanded
+    /// into a primitive of its own.
+    pub fn with_input_rate(mut self, input_rate: vk::VertexInputRate) -> Self {
+        self.bindings.iter_mut().for_each(|binding| {
+            binding.binding_description = binding.binding_description.input_rate(input_rate);
+        });
+
+        self
+    }
+
+    /// Picks the vertex input layout by the shader's name, stages that don't
+    /// consume vertices get an empty one. Mesh shaders get the default vertex format,
+    /// drawing a mesh rebinds the layout of its own.
+    pub fn for_shader(shader_name: &str, stage: vk::ShaderStageFlags) -> Self {
+        match (shader_name, stage) {
+            ("unlit" | "lit" | "shadow" | "debug_mesh", vk::ShaderStageFlags::VERTEX) => {
+                Self::for_vertex_format(Default::default())
+            }
+            ("debug_lines", vk::ShaderStageFlags::VERTEX) => Self::debug_lines(),
+            ("ui", vk::ShaderStageFlags::VERTEX) => Self::ui(),
+            ("text", vk::ShaderStageFlags::VERTEX) => Self::text(),
+            _ => Self::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn set_vertex_input(
+        &self,
+        shader_object: &ash::extensions::ext::ShaderObject,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        let binding_descriptions = self
+            .bindings
+            .iter()
+            .map(|binding| binding.binding_description)
+            .collect::<ArrayVec<_, { Self::MAX_BINDINGS }>>();
+        let attribute_descriptions = self
+            .bindings
+            .iter()
+            .flat_map(|binding| binding.attribute_descriptions.iter().copied())
+            .collect::<ArrayVec<_, { Self::MAX_ATTRIBUTES }>>();
+
+        unsafe {
+            shader_object.cmd_set_vertex_input(
+                command_buffer,
+                &binding_descriptions,
+                &attribute_descriptions,
+            );
+        }
+    }
 }
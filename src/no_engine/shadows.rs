@@ -0,0 +1,360 @@
+use arrayvec::ArrayVec;
+use ash::vk;
+use glam::{Mat4, UVec4, Vec3, Vec4};
+
+use super::{
+    allocator::{AllocatedBuffer, AllocatedImage, Allocator},
+    camera::Camera,
+    gpu_data::{ShadowData, ShadowPushConstants},
+    instances::InstanceManager,
+    objects::{
+        light::{Light, LightType},
+        ObjectType,
+    },
+    register::Register,
+    render_graph::{
+        CompiledGraph, ImageAccess, ImageHandle, PassHandle, RenderGraph, TransientPool,
+    },
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    utils,
+};
+
+/// Graph resources of the shadow pass. Passes shading lit surfaces sample `shadow_map`.
+pub struct ShadowPass {
+    pass: PassHandle,
+    pub shadow_map: ImageHandle,
+}
+
+/// Cascaded shadow maps for the first directional light in the scene.
+pub struct ShadowManager {
+    shadow_map: AllocatedImage,
+    layer_views: ArrayVec<vk::ImageView, { Self::CASCADES_COUNT }>,
+    array_view: vk::ImageView,
+    sampler: vk::Sampler,
+    shadow_data_buffer: AllocatedBuffer,
+    shadow_data: ShadowData,
+    render_state: RenderState,
+    is_debug_enabled: bool,
+}
+
+impl ShadowManager {
+    pub const CASCADES_COUNT: usize = 4;
+    pub const SHADOW_MAP_SIZE: u32 = 2048;
+    pub const SHADOW_MAP_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+    /// Blend between logarithmic (1.0) and uniform (0.0) split distribution.
+    pub const SPLIT_LAMBDA: f32 = 0.75;
+    pub const SHADER_NAME: &'static str = "shadow";
+
+    pub const SHADOW_DATA_SLOT: u32 = 2;
+    pub const SHADOW_MAP_SLOT: u32 = 0;
+
+    /// Casters in front of a cascade are only kept with `does_support_depth_clamp`.
+    pub fn new(
+        device: &ash::Device,
+        allocator: &Allocator,
+        does_support_depth_clamp: bool,
+    ) -> Self {
+        let shadow_map = allocator.allocate_image(
+            Self::SHADOW_MAP_FORMAT,
+            vk::Extent3D {
+                width: Self::SHADOW_MAP_SIZE,
+                height: Self::SHADOW_MAP_SIZE,
+                depth: 1,
+            },
+            vk::ImageType::TYPE_2D,
+            Self::CASCADES_COUNT as _,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            Default::default(),
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+
+        let create_view =
+            |view_type: vk::ImageViewType, base_array_layer: u32, layer_count: u32| {
+                let image_view_info = vk::ImageViewCreateInfo {
+                    image: shadow_map.image,
+                    view_type,
+                    format: shadow_map.format,
+                    subresource_range: vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::DEPTH,
+                        level_count: 1,
+                        base_array_layer,
+                        layer_count,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+
+                unsafe { device.create_image_view(&image_view_info, None).unwrap() }
+            };
+
+        let layer_views = (0..Self::CASCADES_COUNT as u32)
+            .map(|layer| create_view(vk::ImageViewType::TYPE_2D, layer, 1))
+            .collect();
+        let array_view = create_view(
+            vk::ImageViewType::TYPE_2D_ARRAY,
+            Default::default(),
+            Self::CASCADES_COUNT as _,
+        );
+
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
+            .compare_enable(true)
+            .compare_op(vk::CompareOp::LESS_OR_EQUAL);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        let shadow_data_buffer = allocator.allocate_uninit_buffer(
+            std::mem::size_of::<ShadowData>() as _,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            ObjectType::Light,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+
+        Self {
+            shadow_map,
+            layer_views,
+            array_view,
+            sampler,
+            shadow_data_buffer,
+            shadow_data: Default::default(),
+            render_state: RenderState {
+                depth_clamp: does_support_depth_clamp,
+                ..RenderState::shadow()
+            },
+            is_debug_enabled: false,
+        }
+    }
+
+    #[inline(always)]
+    pub fn toggle_debug(&mut self) {
+        self.is_debug_enabled = !self.is_debug_enabled;
+    }
+
+    /// Fits cascades to the camera frustum, nothing is rendered if the scene has
+    /// no directional light.
+    pub fn update(&mut self, allocator: &Allocator, camera: &Camera, lights: &[Light]) {
+        let shadow_light = lights
+            .iter()
+            .take(super::lighting::LightManager::MAX_LIGHTS)
+            .enumerate()
+            .find(|(_, light)| light.light_type == LightType::Directional);
+
+        self.shadow_data = match shadow_light {
+            Some((light_index, light)) => {
+                let (light_view_projections, split_depths) =
+                    Self::compute_cascades(camera, light.direction);
+
+                ShadowData {
+                    light_view_projections,
+                    split_depths,
+                    info: UVec4::new(
+                        Self::CASCADES_COUNT as _,
+                        self.is_debug_enabled as _,
+                        light_index as _,
+                        Default::default(),
+                    ),
+                }
+            }
+            None => ShadowData {
+                info: UVec4::new(0, 0, u32::MAX, 0),
+                ..Default::default()
+            },
+        };
+
+        allocator.write_buffer(
+            &self.shadow_data_buffer,
+            std::slice::from_ref(&self.shadow_data),
+        );
+    }
+
+    #[inline(always)]
+    pub fn write_descriptors(&self, descriptor_writer: DescriptorWriter) -> DescriptorWriter {
+        descriptor_writer
+            .storage_buffer(Self::SHADOW_DATA_SLOT, &self.shadow_data_buffer)
+            .sampled_image(
+                Self::SHADOW_MAP_SLOT,
+                self.array_view,
+                self.sampler,
+                vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+            )
+    }
+
+    /// Declares the pass rendering every cascade. The previous frame may still be sampling
+    /// the shadow map, so the pass waits for that before clearing it.
+    pub fn add_pass(&self, graph: &mut RenderGraph) -> ShadowPass {
+        let shadow_map = graph.import_image(
+            "shadow_map",
+            self.shadow_map.image,
+            self.array_view,
+            vk::ImageAspectFlags::DEPTH,
+            ImageAccess {
+                layout: vk::ImageLayout::UNDEFINED,
+                ..ImageAccess::DEPTH_SAMPLED
+            },
+            None,
+        );
+        let pass = graph
+            .add_pass("shadows")
+            .image(shadow_map, ImageAccess::DEPTH_ATTACHMENT)
+            .build();
+
+        ShadowPass { pass, shadow_map }
+    }
+
+    /// Renders every cascade into its layer, the passes sampling the shadow map declare it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        instance_manager: &InstanceManager,
+        register: &Register,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        shadow_pass: &ShadowPass,
+    ) {
+        let cascades_count = self.shadow_data.info.x;
+
+        graph.begin_pass(device, command_buffer, shadow_pass.pass, pool);
+
+        let extent = vk::Extent2D {
+            width: Self::SHADOW_MAP_SIZE,
+            height: Self::SHADOW_MAP_SIZE,
+        };
+        let descriptor_writer = instance_manager.write_descriptors(
+            DescriptorWriter::new()
+                .storage_buffer(Self::SHADOW_DATA_SLOT, &self.shadow_data_buffer),
+        );
+
+        for (cascade_index, &layer_view) in self
+            .layer_views
+            .iter()
+            .enumerate()
+            .take(cascades_count as _)
+        {
+            let depth_attachment = vk::RenderingAttachmentInfo::default()
+                .image_view(layer_view)
+                .image_layout(vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .clear_value(vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: 1.0,
+                        stencil: 0,
+                    },
+                });
+            let rendering_info = vk::RenderingInfo::default()
+                .depth_attachment(&depth_attachment)
+                .render_area(vk::Rect2D {
+                    offset: Default::default(),
+                    extent,
+                })
+                .layer_count(1);
+
+            let push_constants = ShadowPushConstants {
+                cascade_index: cascade_index as _,
+            };
+
+            unsafe {
+                device.cmd_begin_rendering(command_buffer, &rendering_info);
+
+                self.render_state.apply(
+                    device,
+                    &shader_manager.shader_object,
+                    command_buffer,
+                    extent,
+                );
+                shader_manager.bind_graphics_program(command_buffer, Self::SHADER_NAME);
+                shader_manager.push_descriptors(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    &descriptor_writer,
+                );
+                device.cmd_push_constants(
+                    command_buffer,
+                    shader_manager.pipeline_layout,
+                    vk::ShaderStageFlags::ALL,
+                    Default::default(),
+                    utils::as_bytes(&push_constants),
+                );
+                instance_manager.draw(device, shader_manager, command_buffer, register);
+
+                device.cmd_end_rendering(command_buffer);
+            }
+        }
+    }
+
+    /// Splits the camera frustum and fits a stable orthographic projection
+    /// around every slice.
+    pub fn compute_cascades(
+        camera: &Camera,
+        light_direction: Vec3,
+    ) -> ([Mat4; Self::CASCADES_COUNT], Vec4) {
+        let mut light_view_projections = [Mat4::IDENTITY; Self::CASCADES_COUNT];
+        let mut split_depths = [Default::default(); Self::CASCADES_COUNT];
+
+        let (near, far) = (camera.near, camera.far);
+        let mut previous_split = near;
+        for (cascade_index, light_view_projection) in light_view_projections.iter_mut().enumerate()
+        {
+            let ratio = (cascade_index + 1) as f32 / Self::CASCADES_COUNT as f32;
+            let logarithmic_split = near * (far / near).powf(ratio);
+            let uniform_split = near + (far - near) * ratio;
+            let split =
+                Self::SPLIT_LAMBDA * logarithmic_split + (1.0 - Self::SPLIT_LAMBDA) * uniform_split;
+
+            let corners = camera.frustum_corners(previous_split, split);
+            let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+            // Radius is rounded, so the projection size never changes while the camera rotates.
+            let radius = corners
+                .iter()
+                .map(|corner| corner.distance(center))
+                .fold(f32::MIN, f32::max);
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let up = if light_direction.y.abs() > 0.99 {
+                Vec3::Z
+            } else {
+                Vec3::Y
+            };
+            let light_view = Mat4::look_at_rh(center - light_direction * radius, center, up);
+            let mut light_projection =
+                Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, radius * 2.0);
+
+            // Snap to whole texels to get rid of shimmering edges.
+            let half_size = Self::SHADOW_MAP_SIZE as f32 * 0.5;
+            let shadow_origin = (light_projection * light_view).transform_point3(Vec3::ZERO);
+            let texel_origin = shadow_origin.truncate() * half_size;
+            let snap_offset = (texel_origin.round() - texel_origin) / half_size;
+            light_projection.w_axis.x += snap_offset.x;
+            light_projection.w_axis.y += snap_offset.y;
+
+            *light_view_projection = light_projection * light_view;
+            split_depths[cascade_index] = split;
+            previous_split = split;
+        }
+
+        (light_view_projections, Vec4::from_array(split_depths))
+    }
+
+    pub fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        unsafe {
+            self.layer_views
+                .iter()
+                .for_each(|&layer_view| device.destroy_image_view(layer_view, None));
+            device.destroy_image_view(self.array_view, None);
+            device.destroy_sampler(self.sampler, None);
+        }
+
+        allocator.destroy_image(&self.shadow_map);
+        allocator.destroy_buffer(&self.shadow_data_buffer);
+    }
+}
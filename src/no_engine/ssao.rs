@@ -0,0 +1,429 @@
+use ash::vk;
+
+use super::{
+    allocator::Allocator,
+    fullscreen::FullscreenPass,
+    gpu_data::SsaoPushConstants,
+    instances::InstanceManager,
+    register::Register,
+    render_graph::{
+        BufferAccess, BufferHandle, CompiledGraph, ImageAccess, ImageHandle, PassHandle,
+        RenderGraph, TransientImage, TransientPool,
+    },
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    swapchain::{ColorTarget, SwapchainManager},
+    utils, NoEngine,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SsaoSettings {
+    /// View space distance the hemisphere samples reach.
+    pub radius: f32,
+    /// Scales the occlusion before it darkens the ambient light.
+    pub intensity: f32,
+    /// Clamped to `Ssao::MAX_SAMPLES`.
+    pub samples_count: u32,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            intensity: 1.0,
+            samples_count: 16,
+        }
+    }
+}
+
+/// Graph resources of the occlusion passes, all transient.
+pub struct SsaoPasses {
+    /// Only on the forward path, which has no normals to reuse.
+    prepass: Option<PassHandle>,
+    occlusion_pass: PassHandle,
+    blur_pass: PassHandle,
+    depth: ImageHandle,
+    normal: ImageHandle,
+    raw_occlusion: ImageHandle,
+    /// Blurred, what the lit shaders sample.
+    pub ambient_occlusion: ImageHandle,
+}
+
+/// Screen space ambient occlusion from depth and world space normals, sampled in a hemisphere
+/// around each normal and blurred before the lit shaders scale the ambient light with it.
+pub struct Ssao {
+    pub is_enabled: bool,
+    pub settings: SsaoSettings,
+    sampler: vk::Sampler,
+    occlusion_pass: FullscreenPass,
+    blur_pass: FullscreenPass,
+    /// Single texel of 1 bound while SSAO is off, so the lit shaders sample the same way.
+    neutral: ColorTarget,
+    is_neutral_cleared: bool,
+}
+
+impl Ssao {
+    pub const PREPASS_SHADER_NAME: &'static str = "ssao_prepass";
+    pub const OCCLUSION_SHADER_NAME: &'static str = "ssao";
+    pub const BLUR_SHADER_NAME: &'static str = "ssao_blur";
+
+    pub const FORMAT: vk::Format = vk::Format::R8_UNORM;
+    pub const NORMAL_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+    pub const DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+    pub const MAX_SAMPLES: u32 = 64;
+    pub const BIAS: f32 = 0.025;
+
+    /// Slot in the scene descriptors, after the ones of the deferred lighting pass.
+    pub const AMBIENT_OCCLUSION_SLOT: u32 = 5;
+    pub const DEPTH_SLOT: u32 = 1;
+    pub const NORMAL_SLOT: u32 = 2;
+    pub const INPUT_SLOT: u32 = 1;
+
+    pub fn new(device: &ash::Device, allocator: &Allocator) -> Self {
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        let neutral = SwapchainManager::create_color_target(
+            device,
+            allocator,
+            Self::FORMAT,
+            vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        );
+
+        Self {
+            is_enabled: false,
+            settings: Default::default(),
+            sampler,
+            occlusion_pass: FullscreenPass::new(),
+            blur_pass: FullscreenPass::new(),
+            neutral,
+            is_neutral_cleared: false,
+        }
+    }
+
+    /// Declares the occlusion and blur passes. `gbuffer` is the depth and normals of the
+    /// deferred path, without it a prepass renders the opaque instances into transient ones,
+    /// reading `draw_buffers` in GPU-driven mode.
+    pub fn add_passes(
+        graph: &mut RenderGraph,
+        extent: vk::Extent2D,
+        gbuffer: Option<(ImageHandle, ImageHandle)>,
+        draw_buffers: &[BufferHandle],
+    ) -> SsaoPasses {
+        let mut create_image = |name, format, usage| {
+            graph.create_image(
+                name,
+                TransientImage {
+                    format,
+                    extent,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                    usage: usage | vk::ImageUsageFlags::SAMPLED,
+                },
+            )
+        };
+        let raw_occlusion = create_image(
+            "ssao_raw",
+            Self::FORMAT,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        );
+        let ambient_occlusion =
+            create_image("ssao", Self::FORMAT, vk::ImageUsageFlags::COLOR_ATTACHMENT);
+        let (prepass, depth, normal) = match gbuffer {
+            Some((depth, normal)) => (None, depth, normal),
+            None => {
+                let depth = create_image(
+                    "ssao_depth",
+                    Self::DEPTH_FORMAT,
+                    vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                );
+                let normal = create_image(
+                    "ssao_normal",
+                    Self::NORMAL_FORMAT,
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT,
+                );
+                let prepass = graph
+                    .add_pass("ssao_prepass")
+                    .image(depth, ImageAccess::DEPTH_ATTACHMENT)
+                    .image(normal, ImageAccess::COLOR_ATTACHMENT_WRITE)
+                    .buffers(draw_buffers, BufferAccess::INDIRECT_READ)
+                    .build();
+
+                (Some(prepass), depth, normal)
+            }
+        };
+
+        let occlusion_pass = graph
+            .add_pass("ssao")
+            .image(depth, ImageAccess::DEPTH_SAMPLED)
+            .image(normal, ImageAccess::FRAGMENT_SAMPLED)
+            .image(raw_occlusion, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+        let blur_pass = graph
+            .add_pass("ssao_blur")
+            .image(raw_occlusion, ImageAccess::FRAGMENT_SAMPLED)
+            .image(ambient_occlusion, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            .build();
+
+        SsaoPasses {
+            prepass,
+            occlusion_pass,
+            blur_pass,
+            depth,
+            normal,
+            raw_occlusion,
+            ambient_occlusion,
+        }
+    }
+
+    /// Adds the occlusion the lit shaders sample, the neutral texel while `passes` is `None` or
+    /// culled.
+    pub fn write_descriptors(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        passes: Option<&SsaoPasses>,
+        descriptor_writer: DescriptorWriter,
+    ) -> DescriptorWriter {
+        let image_view =
+            match passes.and_then(|passes| graph.image_view(passes.ambient_occlusion, pool)) {
+                Some(image_view) => image_view,
+                None => {
+                    self.clear_neutral(device, command_buffer);
+                    self.neutral.image_view
+                }
+            };
+
+        descriptor_writer.sampled_image(
+            Self::AMBIENT_OCCLUSION_SLOT,
+            image_view,
+            self.sampler,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )
+    }
+
+    /// `descriptor_writer` holds the scene descriptors, the prepass draws with them. Nothing is
+    /// recorded if the graph culled the passes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        instance_manager: &InstanceManager,
+        register: &Register,
+        descriptor_writer: &DescriptorWriter,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        passes: &SsaoPasses,
+    ) {
+        let (
+            Some(normal_view),
+            Some(depth_view),
+            Some(raw_occlusion_view),
+            Some(ambient_occlusion_view),
+        ) = (
+            graph.image_view(passes.normal, pool),
+            graph.image_view(passes.depth, pool),
+            graph.image_view(passes.raw_occlusion, pool),
+            graph.image_view(passes.ambient_occlusion, pool),
+        )
+        else {
+            return;
+        };
+
+        if let Some(prepass) = passes.prepass {
+            graph.begin_pass(device, command_buffer, prepass, pool);
+            self.render_prepass(
+                device,
+                shader_manager,
+                command_buffer,
+                extent,
+                instance_manager,
+                register,
+                descriptor_writer,
+                normal_view,
+                depth_view,
+            );
+        }
+
+        let push_constants = SsaoPushConstants {
+            radius: self.settings.radius,
+            intensity: self.settings.intensity,
+            bias: Self::BIAS,
+            samples_count: self.settings.samples_count.clamp(1, Self::MAX_SAMPLES),
+        };
+        let occlusion_descriptors = descriptor_writer
+            .clone()
+            .sampled_image(
+                Self::DEPTH_SLOT,
+                depth_view,
+                self.sampler,
+                vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::NORMAL_SLOT,
+                normal_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+        graph.begin_pass(device, command_buffer, passes.occlusion_pass, pool);
+        self.occlusion_pass.draw(
+            device,
+            shader_manager,
+            command_buffer,
+            Self::OCCLUSION_SHADER_NAME,
+            raw_occlusion_view,
+            extent,
+            &occlusion_descriptors,
+            utils::as_bytes(&push_constants),
+        );
+
+        graph.begin_pass(device, command_buffer, passes.blur_pass, pool);
+        self.blur_pass.draw(
+            device,
+            shader_manager,
+            command_buffer,
+            Self::BLUR_SHADER_NAME,
+            ambient_occlusion_view,
+            extent,
+            &DescriptorWriter::new().sampled_image(
+                Self::INPUT_SLOT,
+                raw_occlusion_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            &[],
+        );
+    }
+
+    pub fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        self.neutral.destroy(device, allocator);
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+
+    /// Opaque instances only, transparent ones don't occlude.
+    #[allow(clippy::too_many_arguments)]
+    fn render_prepass(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        instance_manager: &InstanceManager,
+        register: &Register,
+        descriptor_writer: &DescriptorWriter,
+        normal_view: vk::ImageView,
+        depth_view: vk::ImageView,
+    ) {
+        let color_attachments = [vk::RenderingAttachmentInfo::default()
+            .image_view(normal_view)
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        let depth_attachment = vk::RenderingAttachmentInfo::default()
+            .image_view(depth_view)
+            .image_layout(vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .clear_value(vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            });
+        let rendering_info = vk::RenderingInfo::default()
+            .color_attachments(&color_attachments)
+            .depth_attachment(&depth_attachment)
+            .render_area(vk::Rect2D {
+                offset: Default::default(),
+                extent,
+            })
+            .layer_count(1);
+
+        unsafe {
+            device.cmd_begin_rendering(command_buffer, &rendering_info);
+
+            RenderState::opaque().apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_shaders(
+                command_buffer,
+                NoEngine::MESH_SHADER_NAME,
+                Self::PREPASS_SHADER_NAME,
+            );
+            shader_manager.push_descriptors(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                descriptor_writer,
+            );
+            instance_manager.draw_visible(device, shader_manager, command_buffer, register);
+
+            device.cmd_end_rendering(command_buffer);
+        }
+    }
+
+    /// Fills the neutral texel the first time it's bound and leaves it for sampling.
+    fn clear_neutral(&mut self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        if self.is_neutral_cleared {
+            return;
+        }
+
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            level_count: 1,
+            layer_count: 1,
+            ..Default::default()
+        };
+        let transfer_barriers = [vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::NONE)
+            .src_access_mask(vk::AccessFlags2::NONE)
+            .dst_stage_mask(vk::PipelineStageFlags2::CLEAR)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .image(self.neutral.allocated_image.image)
+            .subresource_range(subresource_range)];
+        let sampling_barriers = [vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::CLEAR)
+            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image(self.neutral.allocated_image.image)
+            .subresource_range(subresource_range)];
+
+        unsafe {
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&transfer_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+            device.cmd_clear_color_image(
+                command_buffer,
+                self.neutral.allocated_image.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &vk::ClearColorValue { float32: [1.0; 4] },
+                &[subresource_range],
+            );
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&sampling_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+        }
+        self.is_neutral_cleared = true;
+    }
+}
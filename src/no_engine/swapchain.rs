@@ -17,22 +17,51 @@ impl Depth {
     }
 }
 
+/// Offscreen color image together with its view.
+pub struct ColorTarget {
+    pub image_view: vk::ImageView,
+    pub allocated_image: allocator::AllocatedImage,
+}
+
+impl ColorTarget {
+    pub fn new(image_view: vk::ImageView, allocated_image: allocator::AllocatedImage) -> Self {
+        Self {
+            image_view,
+            allocated_image,
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        unsafe {
+            device.destroy_image_view(self.image_view, None);
+        }
+        allocator.destroy_image(&self.allocated_image);
+    }
+}
+
 pub struct SwapchainManager {
     pub swapchain_loader: ash::extensions::khr::Swapchain,
     pub swapchain: vk::SwapchainKHR,
     pub images: ArrayVec<vk::Image, 3>,
     pub image_views: ArrayVec<vk::ImageView, 3>,
     pub depth: Depth,
+    /// Scene is rendered or resolved here before tonemapping.
+    pub hdr_color: ColorTarget,
+    pub msaa_color: Option<ColorTarget>,
+    pub samples: vk::SampleCountFlags,
     pub extent: vk::Extent2D,
 }
 
 impl SwapchainManager {
+    pub const HDR_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
     pub fn new(
         instance: &ash::Instance,
         device_manager: &super::device::DeviceManager,
         extent: vk::Extent2D,
         surface: vk::SurfaceKHR,
         allocator: Allocator,
+        samples: vk::SampleCountFlags,
     ) -> Self {
         let image_count = crate::no_engine::NoEngine::FRAMES_IN_FLIGHT as u32;
 
@@ -47,7 +76,7 @@ impl SwapchainManager {
             .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
             .min_image_count(image_count)
             .pre_transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
-            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+            .composite_alpha(device_manager.composite_alpha)
             .present_mode(device_manager.present_mode)
             .surface(surface);
 
@@ -88,8 +117,78 @@ impl SwapchainManager {
             })
             .collect::<ArrayVec<_, 3>>();
 
-        let allocated_depth_image = allocator.allocate_image(
+        let samples = device_manager.clamp_sample_count(samples);
+        let depth = Self::create_depth(device, &allocator, extent, samples);
+        let hdr_color = Self::create_hdr_color(device, &allocator, extent);
+        let msaa_color = Self::create_msaa_color(device, &allocator, extent, samples);
+
+        Self {
+            swapchain_loader,
+            swapchain,
+            images,
+            image_views,
+            extent,
+            depth,
+            hdr_color,
+            msaa_color,
+            samples,
+        }
+    }
+
+    /// Recreates depth and multisampled color, the device must be idle.
+    pub fn recreate_render_targets(
+        &mut self,
+        device_manager: &super::device::DeviceManager,
+        allocator: &Allocator,
+        samples: vk::SampleCountFlags,
+    ) {
+        let device = &device_manager.device;
+        self.destroy_render_targets(device, allocator);
+
+        self.samples = device_manager.clamp_sample_count(samples);
+        self.depth = Self::create_depth(device, allocator, self.extent, self.samples);
+        self.hdr_color = Self::create_hdr_color(device, allocator, self.extent);
+        self.msaa_color = Self::create_msaa_color(device, allocator, self.extent, self.samples);
+    }
+
+    pub fn destroy_render_targets(&mut self, device: &ash::Device, allocator: &Allocator) {
+        unsafe {
+            device.destroy_image_view(self.depth.image_view, None);
+        }
+        allocator.destroy_image(&self.depth.allocated_image);
+
+        self.hdr_color.destroy(device, allocator);
+        if let Some(msaa_color) = self.msaa_color.take() {
+            msaa_color.destroy(device, allocator);
+        }
+    }
+
+    fn create_depth(
+        device: &ash::Device,
+        allocator: &Allocator,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+    ) -> Depth {
+        Self::create_depth_target(
+            device,
+            allocator,
             vk::Format::D32_SFLOAT,
+            extent,
+            samples,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+        )
+    }
+
+    pub fn create_depth_target(
+        device: &ash::Device,
+        allocator: &Allocator,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+        usage: vk::ImageUsageFlags,
+    ) -> Depth {
+        let allocated_depth_image = allocator.allocate_image(
+            format,
             vk::Extent3D {
                 width: extent.width,
                 height: extent.height,
@@ -98,8 +197,8 @@ impl SwapchainManager {
             vk::ImageType::TYPE_2D,
             1,
             1,
-            vk::SampleCountFlags::TYPE_1,
-            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            samples,
+            usage,
             Default::default(),
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
         );
@@ -118,21 +217,95 @@ impl SwapchainManager {
         };
 
         let depth_image_view = unsafe {
-            device_manager
-                .device
+            device
                 .create_image_view(&depth_image_view_info, None)
                 .unwrap()
         };
 
-        let depth = Depth::new(depth_image_view, allocated_depth_image);
+        Depth::new(depth_image_view, allocated_depth_image)
+    }
 
-        Self {
-            swapchain_loader,
-            swapchain,
-            images,
-            image_views,
+    fn create_hdr_color(
+        device: &ash::Device,
+        allocator: &Allocator,
+        extent: vk::Extent2D,
+    ) -> ColorTarget {
+        Self::create_color_target(
+            device,
+            allocator,
+            Self::HDR_FORMAT,
             extent,
-            depth,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::STORAGE,
+        )
+    }
+
+    /// Nothing to resolve without multisampling, so no color target is created.
+    fn create_msaa_color(
+        device: &ash::Device,
+        allocator: &Allocator,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+    ) -> Option<ColorTarget> {
+        if samples == vk::SampleCountFlags::TYPE_1 {
+            return None;
         }
+
+        Some(Self::create_color_target(
+            device,
+            allocator,
+            Self::HDR_FORMAT,
+            extent,
+            samples,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        ))
+    }
+
+    pub fn create_color_target(
+        device: &ash::Device,
+        allocator: &Allocator,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+        usage: vk::ImageUsageFlags,
+    ) -> ColorTarget {
+        let allocated_color_image = allocator.allocate_image(
+            format,
+            vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            vk::ImageType::TYPE_2D,
+            1,
+            1,
+            samples,
+            usage,
+            Default::default(),
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+
+        let color_image_view_info = vk::ImageViewCreateInfo {
+            image: allocated_color_image.image,
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                level_count: 1,
+                layer_count: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let color_image_view = unsafe {
+            device
+                .create_image_view(&color_image_view_info, None)
+                .unwrap()
+        };
+
+        ColorTarget::new(color_image_view, allocated_color_image)
     }
 }
@@ -0,0 +1,421 @@
+use ash::vk;
+use glam::{Vec2, Vec4};
+
+use super::{
+    allocator::{AllocatedBuffer, Allocator},
+    gpu_data::UiPushConstants,
+    objects::ObjectType,
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    swapchain::{ColorTarget, SwapchainManager},
+    tonemapping::DisplayMode,
+    utils,
+};
+
+/// Layout must match the `text` vertex input.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TextVertex {
+    pub position: Vec2,
+    pub uv: Vec2,
+    /// RGBA8.
+    pub color: [u8; 4],
+}
+
+/// Placement of a rasterized glyph, in atlas pixels at `ATLAS_FONT_SIZE`.
+#[derive(Clone, Copy, Default)]
+struct Glyph {
+    uv_min: Vec2,
+    uv_max: Vec2,
+    /// From the pen on the baseline to the top left corner of the bitmap.
+    offset: Vec2,
+    size: Vec2,
+    advance: f32,
+}
+
+/// Screen space text from a glyph atlas rasterized once at startup. Like `DebugDraw`,
+/// text is collected until the next frame is drawn and has to be added again every frame.
+pub struct TextRenderer {
+    atlas: ColorTarget,
+    /// Copied into the atlas by the first `render` and destroyed by the second one.
+    atlas_staging_buffer: Option<AllocatedBuffer>,
+    is_atlas_uploaded: bool,
+    atlas_extent: vk::Extent2D,
+    glyphs: Vec<Glyph>,
+    ascent: f32,
+    line_height: f32,
+    vertex_buffer: AllocatedBuffer,
+    vertices: Vec<TextVertex>,
+    sampler: vk::Sampler,
+    render_state: RenderState,
+}
+
+impl TextRenderer {
+    pub const SHADER_NAME: &'static str = "text";
+    pub const FONT_PATH: &'static str = "assets/fonts/DejaVuSansMono.ttf";
+    pub const ATLAS_SLOT: usize = 0;
+    pub const ATLAS_FONT_SIZE: f32 = 32.0;
+    pub const ATLAS_WIDTH: u32 = 512;
+    pub const GLYPH_PADDING: u32 = 1;
+    /// Printable ASCII, anything else is drawn as `FALLBACK_CHARACTER`.
+    pub const FIRST_CHARACTER: char = ' ';
+    pub const LAST_CHARACTER: char = '~';
+    pub const FALLBACK_CHARACTER: char = '?';
+    pub const MAX_GLYPHS: usize = 8192;
+    pub const VERTICES_PER_GLYPH: usize = 6;
+
+    pub fn new(device: &ash::Device, allocator: &Allocator) -> Self {
+        let font_data = std::fs::read(Self::FONT_PATH).unwrap();
+        let font = fontdue::Font::from_bytes(font_data, Default::default()).unwrap();
+        let line_metrics = font.horizontal_line_metrics(Self::ATLAS_FONT_SIZE).unwrap();
+
+        let (glyphs, pixels, atlas_extent) = Self::rasterize_atlas(&font);
+
+        let atlas = SwapchainManager::create_color_target(
+            device,
+            allocator,
+            vk::Format::R8_UNORM,
+            atlas_extent,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        );
+        let atlas_staging_buffer = allocator.allocate_buffer(
+            &pixels,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::SharingMode::EXCLUSIVE,
+        );
+
+        let vertex_buffer = allocator.allocate_uninit_buffer(
+            (Self::MAX_GLYPHS * Self::VERTICES_PER_GLYPH * std::mem::size_of::<TextVertex>()) as _,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            ObjectType::Ui,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        Self {
+            atlas,
+            atlas_staging_buffer: Some(atlas_staging_buffer),
+            is_atlas_uploaded: Default::default(),
+            atlas_extent,
+            glyphs,
+            ascent: line_metrics.ascent,
+            line_height: line_metrics.new_line_size,
+            vertex_buffer,
+            vertices: Default::default(),
+            sampler,
+            render_state: RenderState {
+                blend_equation: Some(Self::premultiplied_blend_equation()),
+                ..RenderState::fullscreen()
+            },
+        }
+    }
+
+    /// `position` is the top left corner in pixels, `size` the line height in pixels and
+    /// `color` linear. Lines are split on `\n`, glyphs past `MAX_GLYPHS` in a frame are dropped.
+    pub fn draw_text(&mut self, position: Vec2, size: f32, color: Vec4, text: &str) {
+        let scale = size / self.line_height;
+        let color = (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0)
+            .round()
+            .to_array()
+            .map(|channel| channel as u8);
+
+        let mut pen = position + Vec2::new(0.0, self.ascent * scale);
+        for character in text.chars() {
+            if character == '\n' {
+                pen = Vec2::new(position.x, pen.y + self.line_height * scale);
+                continue;
+            }
+
+            let glyph = self.glyphs[Self::glyph_index(character)];
+            if glyph.size.x > 0.0
+                && self.vertices.len() + Self::VERTICES_PER_GLYPH
+                    <= Self::MAX_GLYPHS * Self::VERTICES_PER_GLYPH
+            {
+                let min = (pen + glyph.offset * scale).round();
+                let max = min + glyph.size * scale;
+                let vertex = |x: bool, y: bool| TextVertex {
+                    position: Vec2::new(
+                        if x { max.x } else { min.x },
+                        if y { max.y } else { min.y },
+                    ),
+                    uv: Vec2::new(
+                        if x { glyph.uv_max.x } else { glyph.uv_min.x },
+                        if y { glyph.uv_max.y } else { glyph.uv_min.y },
+                    ),
+                    color,
+                };
+                self.vertices.extend([
+                    vertex(false, false),
+                    vertex(false, true),
+                    vertex(true, true),
+                    vertex(false, false),
+                    vertex(true, true),
+                    vertex(true, false),
+                ]);
+            }
+
+            pen.x += glyph.advance * scale;
+        }
+    }
+
+    /// Recorded after post-processing, `output_view` must be in `COLOR_ATTACHMENT_OPTIMAL`
+    /// and is drawn over. Forgets the text afterwards.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        output_view: vk::ImageView,
+        extent: vk::Extent2D,
+        display_mode: DisplayMode,
+        paper_white_nits: f32,
+    ) {
+        if !self.is_atlas_uploaded {
+            self.upload_atlas(device, command_buffer);
+            self.is_atlas_uploaded = true;
+        } else if let Some(atlas_staging_buffer) = self.atlas_staging_buffer.take() {
+            allocator.destroy_buffer(&atlas_staging_buffer);
+        }
+
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        allocator.write_buffer(&self.vertex_buffer, &self.vertices);
+
+        let push_constants = UiPushConstants {
+            screen_size: Vec2::new(extent.width as _, extent.height as _),
+            display_mode: display_mode as _,
+            paper_white_nits,
+        };
+
+        // The last post-processing pass wrote the output as an attachment as well.
+        let memory_barriers = [vk::MemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            )];
+        let dependency_info = vk::DependencyInfo::default().memory_barriers(&memory_barriers);
+
+        let color_attachments = [vk::RenderingAttachmentInfo::default()
+            .image_view(output_view)
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::LOAD)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        let rendering_info = vk::RenderingInfo::default()
+            .color_attachments(&color_attachments)
+            .render_area(vk::Rect2D {
+                offset: Default::default(),
+                extent,
+            })
+            .layer_count(1);
+
+        unsafe {
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+            device.cmd_begin_rendering(command_buffer, &rendering_info);
+
+            self.render_state.apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_program(command_buffer, Self::SHADER_NAME);
+            shader_manager.push_descriptors(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                &DescriptorWriter::new().sampled_image(
+                    Self::ATLAS_SLOT,
+                    self.atlas.image_view,
+                    self.sampler,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ),
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                shader_manager.pipeline_layout,
+                vk::ShaderStageFlags::ALL,
+                Default::default(),
+                utils::as_bytes(&push_constants),
+            );
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                Default::default(),
+                &[self.vertex_buffer.buffer],
+                &[Default::default()],
+            );
+            device.cmd_draw(
+                command_buffer,
+                self.vertices.len() as _,
+                1,
+                Default::default(),
+                Default::default(),
+            );
+
+            device.cmd_end_rendering(command_buffer);
+        }
+
+        self.vertices.clear();
+    }
+
+    pub fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        if let Some(atlas_staging_buffer) = &self.atlas_staging_buffer {
+            allocator.destroy_buffer(atlas_staging_buffer);
+        }
+        self.atlas.destroy(device, allocator);
+        allocator.destroy_buffer(&self.vertex_buffer);
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+
+    /// Shelf packs the glyphs in rows of `ATLAS_WIDTH`, the height fits the last row.
+    fn rasterize_atlas(font: &fontdue::Font) -> (Vec<Glyph>, Vec<u8>, vk::Extent2D) {
+        let bitmaps = (Self::FIRST_CHARACTER..=Self::LAST_CHARACTER)
+            .map(|character| font.rasterize(character, Self::ATLAS_FONT_SIZE))
+            .collect::<Vec<_>>();
+
+        let mut positions = Vec::with_capacity(bitmaps.len());
+        let (mut x, mut y, mut row_height) = (Self::GLYPH_PADDING, Self::GLYPH_PADDING, 0);
+        for (metrics, _) in &bitmaps {
+            let (width, height) = (metrics.width as u32, metrics.height as u32);
+            if x + width + Self::GLYPH_PADDING > Self::ATLAS_WIDTH {
+                x = Self::GLYPH_PADDING;
+                y += row_height + Self::GLYPH_PADDING;
+                row_height = 0;
+            }
+
+            positions.push((x, y));
+            x += width + Self::GLYPH_PADDING;
+            row_height = row_height.max(height);
+        }
+
+        let extent = vk::Extent2D {
+            width: Self::ATLAS_WIDTH,
+            height: y + row_height + Self::GLYPH_PADDING,
+        };
+        let atlas_size = Vec2::new(extent.width as _, extent.height as _);
+        let mut pixels = vec![0; (extent.width * extent.height) as usize];
+
+        let glyphs = bitmaps
+            .iter()
+            .zip(positions)
+            .map(|((metrics, bitmap), (x, y))| {
+                for row in 0..metrics.height {
+                    let destination = (y as usize + row) * extent.width as usize + x as usize;
+                    pixels[destination..destination + metrics.width]
+                        .copy_from_slice(&bitmap[row * metrics.width..(row + 1) * metrics.width]);
+                }
+
+                let position = Vec2::new(x as _, y as _);
+                let size = Vec2::new(metrics.width as _, metrics.height as _);
+                Glyph {
+                    uv_min: position / atlas_size,
+                    uv_max: (position + size) / atlas_size,
+                    offset: Vec2::new(
+                        metrics.xmin as _,
+                        -(metrics.ymin as f32) - metrics.height as f32,
+                    ),
+                    size,
+                    advance: metrics.advance_width,
+                }
+            })
+            .collect();
+
+        (glyphs, pixels, extent)
+    }
+
+    fn upload_atlas(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        let Some(atlas_staging_buffer) = &self.atlas_staging_buffer else {
+            return;
+        };
+
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            level_count: 1,
+            layer_count: 1,
+            ..Default::default()
+        };
+        let image = self.atlas.allocated_image.image;
+        let copy_regions = [vk::BufferImageCopy::default()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                layer_count: 1,
+                ..Default::default()
+            })
+            .image_extent(vk::Extent3D {
+                width: self.atlas_extent.width,
+                height: self.atlas_extent.height,
+                depth: 1,
+            })];
+
+        unsafe {
+            let image_barriers = [vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::NONE)
+                .src_access_mask(vk::AccessFlags2::NONE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .image(image)
+                .subresource_range(subresource_range)];
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                atlas_staging_buffer.buffer,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &copy_regions,
+            );
+
+            let image_barriers = [vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image(image)
+                .subresource_range(subresource_range)];
+            let dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+        }
+    }
+
+    #[inline(always)]
+    fn glyph_index(character: char) -> usize {
+        let character = if (Self::FIRST_CHARACTER..=Self::LAST_CHARACTER).contains(&character) {
+            character
+        } else {
+            Self::FALLBACK_CHARACTER
+        };
+
+        character as usize - Self::FIRST_CHARACTER as usize
+    }
+
+    #[inline(always)]
+    fn premultiplied_blend_equation() -> vk::ColorBlendEquationEXT {
+        vk::ColorBlendEquationEXT {
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use ash::vk;
+
+use super::{
+    fullscreen::FullscreenPass,
+    gpu_data::TonemapPushConstants,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    utils,
+};
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemapper {
+    #[default]
+    Aces,
+    Reinhard,
+    AgX,
+}
+
+impl Tonemapper {
+    #[inline(always)]
+    pub fn next(self) -> Self {
+        match self {
+            Tonemapper::Aces => Tonemapper::Reinhard,
+            Tonemapper::Reinhard => Tonemapper::AgX,
+            Tonemapper::AgX => Tonemapper::Aces,
+        }
+    }
+}
+
+/// How the tonemapped image is encoded for the swapchain.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayMode {
+    #[default]
+    Sdr,
+    /// BT.2020 primaries with the PQ transfer function.
+    Hdr10,
+    /// Linear BT.709 primaries, where 1.0 is 80 nits.
+    ScRgb,
+}
+
+impl DisplayMode {
+    #[inline(always)]
+    pub fn preferred_surface_format(self) -> Option<vk::SurfaceFormatKHR> {
+        match self {
+            DisplayMode::Sdr => None,
+            DisplayMode::Hdr10 => Some(vk::SurfaceFormatKHR {
+                format: vk::Format::A2B10G10R10_UNORM_PACK32,
+                color_space: vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+            }),
+            DisplayMode::ScRgb => Some(vk::SurfaceFormatKHR {
+                format: vk::Format::R16G16B16A16_SFLOAT,
+                color_space: vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn from_color_space(color_space: vk::ColorSpaceKHR) -> Self {
+        match color_space {
+            vk::ColorSpaceKHR::HDR10_ST2084_EXT => DisplayMode::Hdr10,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => DisplayMode::ScRgb,
+            _ => DisplayMode::Sdr,
+        }
+    }
+}
+
+/// Resolves the HDR scene target into the swapchain image with a fullscreen triangle.
+pub struct TonemapPass {
+    sampler: vk::Sampler,
+    fullscreen_pass: FullscreenPass,
+    pub tonemapper: Tonemapper,
+    pub exposure: f32,
+    pub paper_white_nits: f32,
+}
+
+impl TonemapPass {
+    pub const FRAGMENT_SHADER_NAME: &'static str = "tonemap";
+    pub const HDR_TARGET_SLOT: u32 = 0;
+    pub const DEFAULT_PAPER_WHITE_NITS: f32 = 200.0;
+
+    pub fn new(device: &ash::Device) -> Self {
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        Self {
+            sampler,
+            fullscreen_pass: FullscreenPass::new(),
+            tonemapper: Default::default(),
+            exposure: 1.0,
+            paper_white_nits: Self::DEFAULT_PAPER_WHITE_NITS,
+        }
+    }
+
+    /// `hdr_view` must be in `SHADER_READ_ONLY_OPTIMAL` and `output_view` in
+    /// `COLOR_ATTACHMENT_OPTIMAL`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        hdr_view: vk::ImageView,
+        output_view: vk::ImageView,
+        extent: vk::Extent2D,
+        display_mode: DisplayMode,
+    ) {
+        let descriptor_writer = DescriptorWriter::new().sampled_image(
+            Self::HDR_TARGET_SLOT,
+            hdr_view,
+            self.sampler,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+        let push_constants = TonemapPushConstants {
+            exposure: self.exposure,
+            tonemapper: self.tonemapper as _,
+            display_mode: display_mode as _,
+            paper_white_nits: self.paper_white_nits,
+        };
+
+        self.fullscreen_pass.draw(
+            device,
+            shader_manager,
+            command_buffer,
+            Self::FRAGMENT_SHADER_NAME,
+            output_view,
+            extent,
+            &descriptor_writer,
+            utils::as_bytes(&push_constants),
+        );
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
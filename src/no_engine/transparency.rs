@@ -0,0 +1,398 @@
+use ash::vk;
+
+use super::{
+    fullscreen::FullscreenPass,
+    instances::InstanceManager,
+    register::Register,
+    render_graph::{
+        BufferAccess, BufferHandle, CompiledGraph, ImageAccess, ImageHandle, PassHandle,
+        RenderGraph, TransientImage, TransientPool,
+    },
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager},
+    NoEngine,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransparencyMode {
+    /// Back to front in the scene pass, one draw per instance. Wrong where surfaces intersect.
+    #[default]
+    Sorted,
+    /// Weighted blended order-independent transparency into separate targets, composited over
+    /// the scene. An approximation, but needs no sorting and keeps instances batched.
+    WeightedBlended,
+}
+
+impl TransparencyMode {
+    #[inline(always)]
+    pub fn next(self) -> Self {
+        match self {
+            TransparencyMode::Sorted => TransparencyMode::WeightedBlended,
+            TransparencyMode::WeightedBlended => TransparencyMode::Sorted,
+        }
+    }
+}
+
+/// With MSAA rendered multisampled and resolved by averaging, both transient.
+struct WeightedImage {
+    resolved: ImageHandle,
+    msaa: Option<ImageHandle>,
+}
+
+impl WeightedImage {
+    fn new(
+        graph: &mut RenderGraph,
+        name: &'static str,
+        msaa_name: &'static str,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+    ) -> Self {
+        let resolved = graph.create_image(
+            name,
+            TransientImage {
+                format,
+                extent,
+                samples: vk::SampleCountFlags::TYPE_1,
+                usage: vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            },
+        );
+        let msaa = (samples != vk::SampleCountFlags::TYPE_1).then(|| {
+            graph.create_image(
+                msaa_name,
+                TransientImage {
+                    format,
+                    extent,
+                    samples,
+                    usage: vk::ImageUsageFlags::COLOR_ATTACHMENT
+                        | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                },
+            )
+        });
+
+        Self { resolved, msaa }
+    }
+
+    #[inline(always)]
+    fn images(&self) -> impl Iterator<Item = ImageHandle> {
+        std::iter::once(self.resolved).chain(self.msaa)
+    }
+
+    /// Resolved view with the attachment rendering into it, `None` if the graph culled the
+    /// passes using it.
+    fn attachment(
+        &self,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        clear_color: [f32; 4],
+    ) -> Option<(vk::ImageView, vk::RenderingAttachmentInfo<'static>)> {
+        let resolved_view = graph.image_view(self.resolved, pool)?;
+        let attachment = vk::RenderingAttachmentInfo::default()
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .clear_value(vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            });
+
+        let attachment = match self.msaa {
+            Some(msaa) => attachment
+                .image_view(graph.image_view(msaa, pool)?)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                .resolve_image_view(resolved_view)
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            None => attachment
+                .image_view(resolved_view)
+                .store_op(vk::AttachmentStoreOp::STORE),
+        };
+
+        Some((resolved_view, attachment))
+    }
+}
+
+/// Graph resources of the weighted blended passes.
+pub struct WeightedPasses {
+    accumulate_pass: PassHandle,
+    composite_pass: PassHandle,
+    /// Premultiplied color and alpha, both scaled by the weight.
+    accumulation: WeightedImage,
+    /// Product of one minus alpha of every layer.
+    revealage: WeightedImage,
+}
+
+/// Draws the transparent instances over the opaque scene the way `TransparencyMode` says.
+pub struct Transparency {
+    mode: TransparencyMode,
+    sampler: vk::Sampler,
+    composite_pass: FullscreenPass,
+}
+
+impl Transparency {
+    pub const COMPOSITE_SHADER_NAME: &'static str = "oit_composite";
+    pub const ACCUMULATION_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+    pub const REVEALAGE_FORMAT: vk::Format = vk::Format::R16_SFLOAT;
+
+    pub const ACCUMULATION_SLOT: u32 = 0;
+    pub const REVEALAGE_SLOT: u32 = 1;
+    /// Revealage is written at the location after the picking ids, which stay unbound.
+    pub const ATTACHMENTS_COUNT: u32 = 3;
+
+    pub fn new(device: &ash::Device) -> Self {
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        Self {
+            mode: Default::default(),
+            sampler,
+            composite_pass: FullscreenPass::blended(vk::ColorBlendEquationEXT {
+                src_color_blend_factor: vk::BlendFactor::SRC_ALPHA,
+                dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ONE,
+                dst_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                alpha_blend_op: vk::BlendOp::ADD,
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn mode(&self) -> TransparencyMode {
+        self.mode
+    }
+
+    #[inline(always)]
+    pub fn set_mode(&mut self, mode: TransparencyMode) {
+        self.mode = mode;
+    }
+
+    /// Recorded in the scene pass right after the opaque instances, with their shaders and
+    /// descriptors still bound. Does nothing in the weighted blended mode.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_sorted(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+        instance_manager: &InstanceManager,
+        register: &Register,
+    ) {
+        if self.mode != TransparencyMode::Sorted {
+            return;
+        }
+
+        instance_manager.draw_visible_transparent(
+            device,
+            shader_manager,
+            command_buffer,
+            register,
+            |blend_mode| {
+                let render_state = RenderState {
+                    depth_write: false,
+                    blend_equation: blend_mode.blend_equation(),
+                    ..*render_state
+                };
+                render_state.apply(
+                    device,
+                    &shader_manager.shader_object,
+                    command_buffer,
+                    extent,
+                );
+            },
+        );
+    }
+
+    /// Declares the accumulation pass, tested against the scene `depth` with the same
+    /// `samples`, and the pass compositing it over `hdr`. Both follow the scene pass.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_weighted_passes(
+        graph: &mut RenderGraph,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+        hdr: ImageHandle,
+        depth: ImageHandle,
+        shadow_map: ImageHandle,
+        tile_lights: BufferHandle,
+        draw_buffers: &[BufferHandle],
+    ) -> WeightedPasses {
+        let accumulation = WeightedImage::new(
+            graph,
+            "oit_accumulation",
+            "oit_accumulation_msaa",
+            Self::ACCUMULATION_FORMAT,
+            extent,
+            samples,
+        );
+        let revealage = WeightedImage::new(
+            graph,
+            "oit_revealage",
+            "oit_revealage_msaa",
+            Self::REVEALAGE_FORMAT,
+            extent,
+            samples,
+        );
+
+        let accumulate_pass = accumulation
+            .images()
+            .chain(revealage.images())
+            .fold(graph.add_pass("oit_accumulate"), |pass, image| {
+                pass.image(image, ImageAccess::COLOR_ATTACHMENT_WRITE)
+            })
+            .image(depth, ImageAccess::DEPTH_ATTACHMENT)
+            .image(shadow_map, ImageAccess::DEPTH_SAMPLED)
+            .buffer(tile_lights, BufferAccess::FRAGMENT_STORAGE_READ)
+            .buffers(draw_buffers, BufferAccess::INDIRECT_READ)
+            .build();
+        let composite_pass = graph
+            .add_pass("oit_composite")
+            .image(accumulation.resolved, ImageAccess::FRAGMENT_SAMPLED)
+            .image(revealage.resolved, ImageAccess::FRAGMENT_SAMPLED)
+            .image(hdr, ImageAccess::COLOR_ATTACHMENT_READ_WRITE)
+            .build();
+
+        WeightedPasses {
+            accumulate_pass,
+            composite_pass,
+            accumulation,
+            revealage,
+        }
+    }
+
+    /// Accumulates the transparent instances, tested against the scene depth in `depth_view`,
+    /// and blends them over `hdr_view`. Picking ids aren't written. Nothing is recorded if the
+    /// graph culled the passes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_weighted(
+        &self,
+        device: &ash::Device,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        render_state: &RenderState,
+        extent: vk::Extent2D,
+        instance_manager: &InstanceManager,
+        register: &Register,
+        descriptor_writer: &DescriptorWriter,
+        graph: &CompiledGraph,
+        pool: &TransientPool,
+        passes: &WeightedPasses,
+        hdr_view: vk::ImageView,
+        depth_view: vk::ImageView,
+    ) {
+        let (
+            Some((accumulation_view, accumulation_attachment)),
+            Some((revealage_view, revealage_attachment)),
+        ) = (
+            passes.accumulation.attachment(graph, pool, [0.0; 4]),
+            passes.revealage.attachment(graph, pool, [1.0; 4]),
+        )
+        else {
+            return;
+        };
+
+        let color_attachments = [
+            accumulation_attachment,
+            vk::RenderingAttachmentInfo::default(),
+            revealage_attachment,
+        ];
+        let depth_attachment = vk::RenderingAttachmentInfo::default()
+            .image_view(depth_view)
+            .image_layout(vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::LOAD)
+            .store_op(vk::AttachmentStoreOp::STORE);
+        let rendering_info = vk::RenderingInfo::default()
+            .color_attachments(&color_attachments)
+            .depth_attachment(&depth_attachment)
+            .render_area(vk::Rect2D {
+                offset: Default::default(),
+                extent,
+            })
+            .layer_count(1);
+
+        let render_state = RenderState {
+            depth_write: false,
+            blend_equation: Some(vk::ColorBlendEquationEXT {
+                src_color_blend_factor: vk::BlendFactor::ONE,
+                dst_color_blend_factor: vk::BlendFactor::ONE,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ONE,
+                dst_alpha_blend_factor: vk::BlendFactor::ONE,
+                alpha_blend_op: vk::BlendOp::ADD,
+            }),
+            extra_blend_equation: Some(vk::ColorBlendEquationEXT {
+                src_color_blend_factor: vk::BlendFactor::ZERO,
+                dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_COLOR,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ZERO,
+                dst_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                alpha_blend_op: vk::BlendOp::ADD,
+            }),
+            extra_write_mask: vk::ColorComponentFlags::RGBA,
+            color_attachments_count: Self::ATTACHMENTS_COUNT,
+            ..*render_state
+        };
+
+        graph.begin_pass(device, command_buffer, passes.accumulate_pass, pool);
+        unsafe {
+            device.cmd_begin_rendering(command_buffer, &rendering_info);
+
+            render_state.apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_program(command_buffer, NoEngine::MESH_SHADER_NAME);
+            shader_manager.push_descriptors(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                descriptor_writer,
+            );
+            instance_manager.draw_visible_transparent(
+                device,
+                shader_manager,
+                command_buffer,
+                register,
+                |_| (),
+            );
+
+            device.cmd_end_rendering(command_buffer);
+        }
+
+        let descriptor_writer = DescriptorWriter::new()
+            .sampled_image(
+                Self::ACCUMULATION_SLOT,
+                accumulation_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .sampled_image(
+                Self::REVEALAGE_SLOT,
+                revealage_view,
+                self.sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+        graph.begin_pass(device, command_buffer, passes.composite_pass, pool);
+        self.composite_pass.draw(
+            device,
+            shader_manager,
+            command_buffer,
+            Self::COMPOSITE_SHADER_NAME,
+            hdr_view,
+            extent,
+            &descriptor_writer,
+            &[],
+        );
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
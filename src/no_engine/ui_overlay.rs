@@ -0,0 +1,583 @@
+use std::{collections::HashMap, time::Duration};
+
+use ash::vk;
+use glam::Vec2;
+
+use super::{
+    allocator::{mesh::AllocatedMesh, AllocatedBuffer, Allocator},
+    gpu_data::UiPushConstants,
+    instances::CullingStats,
+    objects::ObjectType,
+    render_state::RenderState,
+    shader::{descriptors::DescriptorWriter, ShaderManager, ShaderObject},
+    swapchain::{ColorTarget, SwapchainManager},
+    tonemapping::DisplayMode,
+    utils,
+};
+
+/// What the built-in panels show, gathered by `NoEngine` every frame.
+pub struct UiStats {
+    pub frame_time: Duration,
+    pub culling: CullingStats,
+}
+
+/// Range of the index buffer drawn with a single texture and clip rectangle.
+struct UiDraw {
+    texture_id: egui::TextureId,
+    clip_rect: egui::Rect,
+    first_index: u32,
+    indices_count: u32,
+    vertex_offset: i32,
+}
+
+/// Egui windows drawn over the final image, after post-processing.
+pub struct UiOverlay {
+    context: egui::Context,
+    state: egui_winit::State,
+    textures: HashMap<egui::TextureId, ColorTarget>,
+    textures_delta: egui::TexturesDelta,
+    /// Kept until the next frame, the one recorded last may still use them.
+    garbage_textures: Vec<ColorTarget>,
+    staging_buffers: Vec<AllocatedBuffer>,
+    vertex_buffer: AllocatedBuffer,
+    index_buffer: AllocatedBuffer,
+    vertices: Vec<egui::epaint::Vertex>,
+    indices: Vec<u32>,
+    draws: Vec<UiDraw>,
+    sampler: vk::Sampler,
+    render_state: RenderState,
+    pub is_visible: bool,
+}
+
+impl UiOverlay {
+    pub const SHADER_NAME: &'static str = "ui";
+    pub const TEXTURE_SLOT: usize = 0;
+    pub const MAX_VERTICES: usize = 65536;
+    pub const MAX_INDICES: usize = Self::MAX_VERTICES * 3;
+
+    pub fn new(
+        device: &ash::Device,
+        allocator: &Allocator,
+        window: &winit::window::Window,
+    ) -> Self {
+        let mut state = egui_winit::State::new_with_wayland_display(None);
+        state.set_pixels_per_point(window.scale_factor() as _);
+
+        let vertex_buffer = allocator.allocate_uninit_buffer(
+            (Self::MAX_VERTICES * std::mem::size_of::<egui::epaint::Vertex>()) as _,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            ObjectType::Ui,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+        let index_buffer = allocator.allocate_uninit_buffer(
+            (Self::MAX_INDICES * std::mem::size_of::<u32>()) as _,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            ObjectType::Ui,
+            vk_mem_alloc::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+        );
+
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        Self {
+            context: Default::default(),
+            state,
+            textures: Default::default(),
+            textures_delta: Default::default(),
+            garbage_textures: Default::default(),
+            staging_buffers: Default::default(),
+            vertex_buffer,
+            index_buffer,
+            vertices: Default::default(),
+            indices: Default::default(),
+            draws: Default::default(),
+            sampler,
+            render_state: RenderState {
+                blend_equation: Some(Self::premultiplied_blend_equation()),
+                ..RenderState::fullscreen()
+            },
+            is_visible: true,
+        }
+    }
+
+    /// Returns whether egui wants the event for itself, hidden panels never do.
+    pub fn handle_window_event(&mut self, event: &winit::event::WindowEvent) -> bool {
+        if !self.is_visible {
+            return false;
+        }
+
+        if let winit::event::WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+            self.state.set_pixels_per_point(*scale_factor as _);
+        }
+
+        self.state.on_event(&self.context, event).consumed
+    }
+
+    /// Builds the panels and tessellates them for the next `render`.
+    pub fn update(
+        &mut self,
+        window: &winit::window::Window,
+        stats: &UiStats,
+        meshes: &[AllocatedMesh],
+        shaders: &[ShaderObject],
+    ) {
+        if !self.is_visible {
+            self.draws.clear();
+            return;
+        }
+
+        let raw_input = self.state.take_egui_input(window);
+        let full_output = self.context.run(raw_input, |context| {
+            Self::panels(context, stats, meshes, shaders)
+        });
+        self.state
+            .handle_platform_output(window, &self.context, full_output.platform_output);
+        self.textures_delta.append(full_output.textures_delta);
+
+        let primitives = self.context.tessellate(full_output.shapes);
+        self.pack(primitives);
+    }
+
+    /// Recorded after post-processing, `output_view` must be in `COLOR_ATTACHMENT_OPTIMAL`
+    /// and is drawn over.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        output_view: vk::ImageView,
+        extent: vk::Extent2D,
+        display_mode: DisplayMode,
+        paper_white_nits: f32,
+    ) {
+        std::mem::take(&mut self.garbage_textures)
+            .iter()
+            .for_each(|texture| texture.destroy(device, allocator));
+        std::mem::take(&mut self.staging_buffers)
+            .iter()
+            .for_each(|staging_buffer| allocator.destroy_buffer(staging_buffer));
+
+        self.upload_textures(device, allocator, command_buffer);
+
+        if !self.draws.is_empty() {
+            self.draw(
+                device,
+                allocator,
+                shader_manager,
+                command_buffer,
+                output_view,
+                extent,
+                display_mode,
+                paper_white_nits,
+            );
+        }
+
+        for texture_id in std::mem::take(&mut self.textures_delta.free) {
+            if let Some(texture) = self.textures.remove(&texture_id) {
+                self.garbage_textures.push(texture);
+            }
+        }
+    }
+
+    pub fn destroy(&mut self, device: &ash::Device, allocator: &Allocator) {
+        self.textures
+            .drain()
+            .map(|(_, texture)| texture)
+            .chain(self.garbage_textures.drain(..))
+            .for_each(|texture| texture.destroy(device, allocator));
+        self.staging_buffers
+            .iter()
+            .for_each(|staging_buffer| allocator.destroy_buffer(staging_buffer));
+        allocator.destroy_buffer(&self.vertex_buffer);
+        allocator.destroy_buffer(&self.index_buffer);
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+
+    fn panels(
+        context: &egui::Context,
+        stats: &UiStats,
+        meshes: &[AllocatedMesh],
+        shaders: &[ShaderObject],
+    ) {
+        egui::Window::new("Stats").show(context, |ui| {
+            let frame_time = stats.frame_time.as_secs_f32();
+            ui.label(format!("FPS: {:.0}", 1.0 / frame_time.max(f32::EPSILON)));
+            ui.label(format!("Frame time: {:.2} ms", frame_time * 1000.0));
+            ui.separator();
+            ui.label(format!("Instances: {}", stats.culling.instances));
+            ui.label(format!("Drawn: {}", stats.culling.drawn));
+            ui.label(format!("Culled: {}", stats.culling.culled));
+            ui.label(format!("Dropped: {}", stats.culling.dropped));
+            ui.label(format!("Draw calls: {}", stats.culling.draw_calls));
+        });
+
+        egui::Window::new("Meshes")
+            .default_open(false)
+            .show(context, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("meshes").striped(true).show(ui, |ui| {
+                        ["Id", "Vertices", "Indices", "LODs", "Format"]
+                            .iter()
+                            .for_each(|header| {
+                                ui.strong(*header);
+                            });
+                        ui.end_row();
+
+                        for mesh in meshes {
+                            let metadata = &mesh.metadata;
+                            ui.label(mesh.id.to_string());
+                            ui.label(metadata.vertices_count.to_string());
+                            ui.label(metadata.indices_count.to_string());
+                            ui.label(metadata.lods().len().to_string());
+                            ui.label(format!(
+                                "{:?} / {:?}",
+                                metadata.vertex_format.position, metadata.vertex_format.normal
+                            ));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+        egui::Window::new("Shaders")
+            .default_open(false)
+            .show(context, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("shaders").striped(true).show(ui, |ui| {
+                        ui.strong("Name");
+                        ui.strong("Stage");
+                        ui.end_row();
+
+                        for shader in shaders {
+                            ui.label(shader.name());
+                            ui.label(format!("{:?}", shader.stage()));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Meshes past the buffer sizes are dropped, paint callbacks aren't supported.
+    fn pack(&mut self, primitives: Vec<egui::ClippedPrimitive>) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.draws.clear();
+
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primitives
+        {
+            let egui::epaint::Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            if mesh.indices.is_empty()
+                || self.vertices.len() + mesh.vertices.len() > Self::MAX_VERTICES
+                || self.indices.len() + mesh.indices.len() > Self::MAX_INDICES
+            {
+                continue;
+            }
+
+            self.draws.push(UiDraw {
+                texture_id: mesh.texture_id,
+                clip_rect,
+                first_index: self.indices.len() as _,
+                indices_count: mesh.indices.len() as _,
+                vertex_offset: self.vertices.len() as _,
+            });
+            self.vertices.extend_from_slice(&mesh.vertices);
+            self.indices.extend_from_slice(&mesh.indices);
+        }
+    }
+
+    /// Whole textures are recreated, partial updates are copied into the existing ones.
+    fn upload_textures(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        for (texture_id, delta) in std::mem::take(&mut self.textures_delta.set) {
+            let pixels = match &delta.image {
+                egui::ImageData::Color(image) => image
+                    .pixels
+                    .iter()
+                    .flat_map(|color| color.to_array())
+                    .collect::<Vec<_>>(),
+                egui::ImageData::Font(image) => image
+                    .srgba_pixels(None)
+                    .flat_map(|color| color.to_array())
+                    .collect::<Vec<_>>(),
+            };
+            let [width, height] = delta.image.size();
+
+            let is_partial = delta.pos.is_some() && self.textures.contains_key(&texture_id);
+            let old_layout = if is_partial {
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            } else {
+                let texture = SwapchainManager::create_color_target(
+                    device,
+                    allocator,
+                    vk::Format::R8G8B8A8_UNORM,
+                    vk::Extent2D {
+                        width: width as _,
+                        height: height as _,
+                    },
+                    vk::SampleCountFlags::TYPE_1,
+                    vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                );
+                if let Some(old_texture) = self.textures.insert(texture_id, texture) {
+                    self.garbage_textures.push(old_texture);
+                }
+
+                vk::ImageLayout::UNDEFINED
+            };
+            let image = self.textures[&texture_id].allocated_image.image;
+            let [x, y] = delta.pos.unwrap_or_default();
+
+            let staging_buffer = allocator.allocate_buffer(
+                &pixels,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+                vk::SharingMode::EXCLUSIVE,
+            );
+
+            let copy_regions = [vk::BufferImageCopy::default()
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    layer_count: 1,
+                    ..Default::default()
+                })
+                .image_offset(vk::Offset3D {
+                    x: x as _,
+                    y: y as _,
+                    z: Default::default(),
+                })
+                .image_extent(vk::Extent3D {
+                    width: width as _,
+                    height: height as _,
+                    depth: 1,
+                })];
+
+            unsafe {
+                let image_barriers = [Self::texture_barrier(image, old_layout, true)];
+                let dependency_info =
+                    vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+                device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+
+                device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    staging_buffer.buffer,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &copy_regions,
+                );
+
+                let image_barriers = [Self::texture_barrier(
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    false,
+                )];
+                let dependency_info =
+                    vk::DependencyInfo::default().image_memory_barriers(&image_barriers);
+                device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+            }
+
+            self.staging_buffers.push(staging_buffer);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        shader_manager: &ShaderManager,
+        command_buffer: vk::CommandBuffer,
+        output_view: vk::ImageView,
+        extent: vk::Extent2D,
+        display_mode: DisplayMode,
+        paper_white_nits: f32,
+    ) {
+        allocator.write_buffer(&self.vertex_buffer, &self.vertices);
+        allocator.write_buffer(&self.index_buffer, &self.indices);
+
+        let pixels_per_point = self.context.pixels_per_point();
+        let push_constants = UiPushConstants {
+            screen_size: Vec2::new(extent.width as _, extent.height as _) / pixels_per_point,
+            display_mode: display_mode as _,
+            paper_white_nits,
+        };
+
+        // The last post-processing pass wrote the output as an attachment as well.
+        let memory_barriers = [vk::MemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            )];
+        let dependency_info = vk::DependencyInfo::default().memory_barriers(&memory_barriers);
+
+        let color_attachments = [vk::RenderingAttachmentInfo::default()
+            .image_view(output_view)
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::LOAD)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        let rendering_info = vk::RenderingInfo::default()
+            .color_attachments(&color_attachments)
+            .render_area(vk::Rect2D {
+                offset: Default::default(),
+                extent,
+            })
+            .layer_count(1);
+
+        unsafe {
+            device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+            device.cmd_begin_rendering(command_buffer, &rendering_info);
+
+            self.render_state.apply(
+                device,
+                &shader_manager.shader_object,
+                command_buffer,
+                extent,
+            );
+            shader_manager.bind_graphics_program(command_buffer, Self::SHADER_NAME);
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                Default::default(),
+                &[self.vertex_buffer.buffer],
+                &[Default::default()],
+            );
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                self.index_buffer.buffer,
+                Default::default(),
+                vk::IndexType::UINT32,
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                shader_manager.pipeline_layout,
+                vk::ShaderStageFlags::ALL,
+                Default::default(),
+                utils::as_bytes(&push_constants),
+            );
+
+            for draw in &self.draws {
+                let Some(texture) = self.textures.get(&draw.texture_id) else {
+                    continue;
+                };
+                let Some(scissor) = Self::scissor(draw.clip_rect, pixels_per_point, extent) else {
+                    continue;
+                };
+
+                shader_manager
+                    .shader_object
+                    .cmd_set_scissor_with_count(command_buffer, &[scissor]);
+                shader_manager.push_descriptors(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    &DescriptorWriter::new().sampled_image(
+                        Self::TEXTURE_SLOT,
+                        texture.image_view,
+                        self.sampler,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    ),
+                );
+                device.cmd_draw_indexed(
+                    command_buffer,
+                    draw.indices_count,
+                    1,
+                    draw.first_index,
+                    draw.vertex_offset,
+                    Default::default(),
+                );
+            }
+
+            device.cmd_end_rendering(command_buffer);
+        }
+    }
+
+    /// Clip rectangle in pixels clamped to the output, `None` when nothing is left of it.
+    #[inline(always)]
+    fn scissor(
+        clip_rect: egui::Rect,
+        pixels_per_point: f32,
+        extent: vk::Extent2D,
+    ) -> Option<vk::Rect2D> {
+        let min_x = (clip_rect.min.x * pixels_per_point).round().max(0.0) as u32;
+        let min_y = (clip_rect.min.y * pixels_per_point).round().max(0.0) as u32;
+        let max_x =
+            ((clip_rect.max.x * pixels_per_point).round().max(0.0) as u32).min(extent.width);
+        let max_y =
+            ((clip_rect.max.y * pixels_per_point).round().max(0.0) as u32).min(extent.height);
+
+        if min_x >= max_x || min_y >= max_y {
+            return None;
+        }
+
+        Some(vk::Rect2D {
+            offset: vk::Offset2D {
+                x: min_x as _,
+                y: min_y as _,
+            },
+            extent: vk::Extent2D {
+                width: max_x - min_x,
+                height: max_y - min_y,
+            },
+        })
+    }
+
+    /// Into `TRANSFER_DST_OPTIMAL` before the copy, into `SHADER_READ_ONLY_OPTIMAL` after.
+    #[inline(always)]
+    fn texture_barrier(
+        image: vk::Image,
+        old_layout: vk::ImageLayout,
+        is_before_copy: bool,
+    ) -> vk::ImageMemoryBarrier2<'static> {
+        let barrier = vk::ImageMemoryBarrier2::default()
+            .old_layout(old_layout)
+            .image(image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                level_count: 1,
+                layer_count: 1,
+                ..Default::default()
+            });
+
+        if is_before_copy {
+            barrier
+                .src_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .src_access_mask(vk::AccessFlags2::NONE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        } else {
+            barrier
+                .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        }
+    }
+
+    #[inline(always)]
+    fn premultiplied_blend_equation() -> vk::ColorBlendEquationEXT {
+        vk::ColorBlendEquationEXT {
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+        }
+    }
+}
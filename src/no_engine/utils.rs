@@ -6,3 +6,13 @@ pub fn hash<H: Hash>(hashable: &H) -> u64 {
 
     hasher.finish()
 }
+
+#[inline(always)]
+pub fn as_bytes<T>(data: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+#[inline(always)]
+pub fn slice_as_bytes<T>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}